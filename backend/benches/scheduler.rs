@@ -0,0 +1,55 @@
+use backend::Emu;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Makes an NROM ROM that runs `code` at $8000.
+fn make_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = b"NES\x1A\x01\x00".to_vec();
+    rom.resize(16, 0);
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..code.len()].copy_from_slice(code);
+    // The reset vector.
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    rom.extend_from_slice(&prg);
+    rom
+}
+
+/// Makes an emulator that runs `code` and has run the reset sequence.
+fn make_emu(code: &[u8]) -> Emu {
+    let mut emu = Emu::new(&make_rom(code));
+    emu.step();
+    emu
+}
+
+/// Checking for due events when none are, which happens before every
+/// instruction.
+fn check(c: &mut Criterion) {
+    let mut emu = make_emu(&[
+        0x4C, 0x00, 0x80, // JMP $8000
+    ]);
+    c.bench_function("check", |b| b.iter(|| emu.step()));
+}
+
+/// Queueing an event and popping it, which a soft reset does.
+fn queue_and_pop(c: &mut Criterion) {
+    let mut emu = make_emu(&[
+        0x4C, 0x00, 0x80, // JMP $8000
+    ]);
+    c.bench_function("queue_and_pop", |b| {
+        b.iter(|| {
+            emu.reset();
+            emu.step();
+        })
+    });
+}
+
+/// Rescheduling an event, which each write to $4017 does for the APU's IRQ.
+fn reschedule(c: &mut Criterion) {
+    let mut emu = make_emu(&[
+        0x8D, 0x17, 0x40, // STA $4017
+        0x4C, 0x00, 0x80, // JMP $8000
+    ]);
+    c.bench_function("reschedule", |b| b.iter(|| emu.step()));
+}
+
+criterion_group!(benches, check, queue_and_pop, reschedule);
+criterion_main!(benches);
@@ -14,7 +14,10 @@ fn main() {
         .file("src/apu/shim.cpp")
         .file("Nes_Snd_Emu/nes_apu/Blip_Buffer.cpp")
         .file("Nes_Snd_Emu/nes_apu/Nes_Apu.cpp")
+        .file("Nes_Snd_Emu/nes_apu/Nes_Fme7_Apu.cpp")
+        .file("Nes_Snd_Emu/nes_apu/Nes_Namco_Apu.cpp")
         .file("Nes_Snd_Emu/nes_apu/Nes_Oscs.cpp")
+        .file("Nes_Snd_Emu/nes_apu/Nes_Vrc6_Apu.cpp")
         .define("NDEBUG", None)
         .compile("nes_snd_emu");
 
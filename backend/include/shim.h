@@ -6,6 +6,18 @@
 static_assert(sizeof(std::error_condition) == 16, "");
 static_assert(alignof(std::error_condition) == alignof(void*), "");
 
+struct DmcReader;
+
 std::unique_ptr<Blip_Buffer> blip_buffer_new();
 
 std::unique_ptr<Nes_Apu> nes_apu_new();
+
+void set_dmc_reader(Nes_Apu& nes_apu, DmcReader* reader);
+
+int count_dmc_reads(const Nes_Apu& nes_apu, int time);
+
+std::unique_ptr<Nes_Vrc6_Apu> vrc6_apu_new();
+
+std::unique_ptr<Nes_Namco_Apu> namco_apu_new();
+
+std::unique_ptr<Nes_Fme7_Apu> fme7_apu_new();
@@ -1,5 +1,8 @@
 #![cfg_attr(test, allow(dead_code))]
 
+#[cfg(test)]
+mod tests;
+
 use std::{
     ffi::c_void,
     mem::{self, MaybeUninit},
@@ -8,13 +11,28 @@ use std::{
 
 use cxx::{type_id, UniquePtr};
 
-use crate::Emu;
+use crate::{
+    cpu::{self, IrqSource},
+    region::Region,
+    scheduler::{self, EventKind},
+    wav::Recorder,
+    Emu,
+};
 
 #[cxx::bridge]
 mod ffi {
+    extern "Rust" {
+        type DmcReader;
+
+        fn dmc_read(reader: &mut DmcReader, addr: u16) -> u8;
+    }
+
     unsafe extern "C++" {
         include!("backend/Nes_Snd_Emu/nes_apu/Blip_Buffer.h");
         include!("backend/Nes_Snd_Emu/nes_apu/Nes_Apu.h");
+        include!("backend/Nes_Snd_Emu/nes_apu/Nes_Fme7_Apu.h");
+        include!("backend/Nes_Snd_Emu/nes_apu/Nes_Namco_Apu.h");
+        include!("backend/Nes_Snd_Emu/nes_apu/Nes_Vrc6_Apu.h");
         include!("backend/include/shim.h");
 
         #[namespace = "std"]
@@ -32,6 +50,7 @@ mod ffi {
         ) -> ErrorCondition;
         fn clock_rate(self: Pin<&mut BlipBuffer>, clock_rate: i32);
         fn end_frame(self: Pin<&mut BlipBuffer>, time: i32);
+        fn samples_avail(self: &BlipBuffer) -> i32;
         unsafe fn read_samples(
             self: Pin<&mut BlipBuffer>,
             dst: *mut i16,
@@ -43,7 +62,11 @@ mod ffi {
         type NesApu;
 
         fn nes_apu_new() -> UniquePtr<NesApu>;
-        unsafe fn set_output(self: Pin<&mut NesApu>, buffer: *mut BlipBuffer);
+        unsafe fn osc_output(
+            self: Pin<&mut NesApu>,
+            index: i32,
+            buffer: *mut BlipBuffer,
+        );
         fn write_register(
             self: Pin<&mut NesApu>,
             time: i32,
@@ -51,12 +74,80 @@ mod ffi {
             data: u8,
         );
         fn read_status(self: Pin<&mut NesApu>, time: i32) -> u8;
+        fn reset(
+            self: Pin<&mut NesApu>,
+            pal_timing: bool,
+            initial_dmc_dac: i32,
+        );
+        fn earliest_irq(self: &NesApu, time: i32) -> i32;
         fn end_frame(self: Pin<&mut NesApu>, time: i32);
+
+        unsafe fn set_dmc_reader(
+            nes_apu: Pin<&mut NesApu>,
+            reader: *mut DmcReader,
+        );
+        fn count_dmc_reads(nes_apu: &NesApu, time: i32) -> i32;
+
+        #[cxx_name = "Nes_Vrc6_Apu"]
+        type Vrc6Apu;
+
+        fn vrc6_apu_new() -> UniquePtr<Vrc6Apu>;
+        unsafe fn osc_output(
+            self: Pin<&mut Vrc6Apu>,
+            index: i32,
+            buffer: *mut BlipBuffer,
+        );
+        fn write_osc(
+            self: Pin<&mut Vrc6Apu>,
+            time: i32,
+            osc: i32,
+            reg: i32,
+            data: i32,
+        );
+        fn reset(self: Pin<&mut Vrc6Apu>);
+        fn end_frame(self: Pin<&mut Vrc6Apu>, time: i32);
+
+        #[cxx_name = "Nes_Namco_Apu"]
+        type NamcoApu;
+
+        fn namco_apu_new() -> UniquePtr<NamcoApu>;
+        unsafe fn osc_output(
+            self: Pin<&mut NamcoApu>,
+            index: i32,
+            buffer: *mut BlipBuffer,
+        );
+        fn write_addr(self: Pin<&mut NamcoApu>, addr: i32);
+        fn write_data(self: Pin<&mut NamcoApu>, time: i32, data: i32);
+        fn reset(self: Pin<&mut NamcoApu>);
+        fn end_frame(self: Pin<&mut NamcoApu>, time: i32);
+
+        #[cxx_name = "Nes_Fme7_Apu"]
+        type Fme7Apu;
+
+        fn fme7_apu_new() -> UniquePtr<Fme7Apu>;
+        unsafe fn osc_output(
+            self: Pin<&mut Fme7Apu>,
+            index: i32,
+            buffer: *mut BlipBuffer,
+        );
+        fn write_latch(self: Pin<&mut Fme7Apu>, data: i32);
+        fn write_data(self: Pin<&mut Fme7Apu>, time: i32, data: i32);
+        fn reset(self: Pin<&mut Fme7Apu>);
+        fn end_frame(self: Pin<&mut Fme7Apu>, time: i32);
     }
 }
 
-const SAMPLE_RATE: i32 = 44100;
-const CLOCK_RATE: i32 = 1789773;
+/// The output sample rate in Hz.
+pub const SAMPLE_RATE: i32 = 44100;
+/// The number of sound channels, including expansion audio.
+const CHANNEL_COUNT: usize = 8;
+/// The number of oscillators in each sound chip.
+const NES_APU_OSC_COUNT: i32 = 5;
+const VRC6_OSC_COUNT: i32 = 3;
+const NAMCO_OSC_COUNT: i32 = 8;
+const FME7_OSC_COUNT: i32 = 3;
+/// What `Nes_Apu::earliest_irq` returns when no IRQ is coming.
+const NO_IRQ: i32 = i32::MAX / 2 + 1;
 
 #[repr(C)]
 struct ErrorCondition {
@@ -70,58 +161,524 @@ unsafe impl cxx::ExternType for ErrorCondition {
     type Kind = cxx::kind::Trivial;
 }
 
+/// The DMC's view of memory. Nes_Apu fetches sample bytes through a callback,
+/// so the byte is read through the bus (with DMA timing) ahead of time and
+/// handed over here.
+pub struct DmcReader {
+    /// The byte that the next DMC fetch returns.
+    data: u8,
+    /// The address of the last DMC fetch.
+    addr: u16,
+    /// The number of fetches Nes_Apu has made.
+    reads: u64,
+}
+
+fn dmc_read(reader: &mut DmcReader, addr: u16) -> u8 {
+    reader.addr = addr;
+    reader.reads += 1;
+    reader.data
+}
+
+/// Mirrors the DMC's sample address and length so that the address of the
+/// next fetch is known before Nes_Apu asks for it.
+struct Dmc {
+    /// The address of the next sample byte.
+    addr: u16,
+    /// The number of sample bytes left to fetch.
+    remaining: u16,
+    /// $4010
+    looping: bool,
+    /// $4012
+    sample_addr: u16,
+    /// $4013
+    sample_len: u16,
+    /// A write to $4015 that restarted the sample. Nes_Apu fetches the first
+    /// byte as soon as it sees the write, so the write is held back until a
+    /// DMA has fetched the byte.
+    start: Option<u8>,
+}
+
+impl Dmc {
+    fn restart(&mut self) {
+        self.addr = self.sample_addr;
+        self.remaining = self.sample_len;
+    }
+
+    fn advance(&mut self) {
+        // The address wraps around to 0x8000 instead of 0x0000.
+        self.addr = self.addr.checked_add(1).unwrap_or(0x8000);
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining == 0 && self.looping {
+            self.restart();
+        }
+    }
+}
+
+/// A sound channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+    /// Konami VRC6 expansion audio.
+    Vrc6,
+    /// Namco 163 expansion audio.
+    Namco163,
+    /// Sunsoft 5B expansion audio.
+    Sunsoft5b,
+}
+
+impl Channel {
+    /// All of the channels. The first five are in Nes_Apu's oscillator
+    /// order.
+    pub const ALL: [Channel; CHANNEL_COUNT] = [
+        Channel::Pulse1,
+        Channel::Pulse2,
+        Channel::Triangle,
+        Channel::Noise,
+        Channel::Dmc,
+        Channel::Vrc6,
+        Channel::Namco163,
+        Channel::Sunsoft5b,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Pulse1 => "pulse1",
+            Channel::Pulse2 => "pulse2",
+            Channel::Triangle => "triangle",
+            Channel::Noise => "noise",
+            Channel::Dmc => "dmc",
+            Channel::Vrc6 => "vrc6",
+            Channel::Namco163 => "namco163",
+            Channel::Sunsoft5b => "sunsoft5b",
+        }
+    }
+}
+
+/// How a channel is mixed into the output.
+struct Mix {
+    muted: bool,
+    volume: f32,
+    /// -1.0 is full left, 0.0 is center, and 1.0 is full right.
+    pan: f32,
+}
+
+/// Mixes a sample from each channel into a left and right sample.
+fn mix(
+    mixes: &[Mix; CHANNEL_COUNT],
+    samples: [i16; CHANNEL_COUNT],
+) -> [i16; 2] {
+    let mut left = 0.0;
+    let mut right = 0.0;
+    for (mix, sample) in mixes.iter().zip(samples) {
+        if mix.muted {
+            continue;
+        }
+        // Centered channels play at full volume on both sides.
+        let sample = sample as f32 * mix.volume;
+        left += sample * (1.0 - mix.pan).min(1.0);
+        right += sample * (1.0 + mix.pan).min(1.0);
+    }
+    // Float to integer casts saturate, so the mix can't wrap around.
+    [left as i16, right as i16]
+}
+
 pub struct Apu {
-    buffer: UniquePtr<ffi::BlipBuffer>,
+    /// Each channel has its own buffer so that it can be mixed separately.
+    buffers: [UniquePtr<ffi::BlipBuffer>; CHANNEL_COUNT],
     nes_apu: UniquePtr<ffi::NesApu>,
+    vrc6: UniquePtr<ffi::Vrc6Apu>,
+    namco: UniquePtr<ffi::NamcoApu>,
+    fme7: UniquePtr<ffi::Fme7Apu>,
+    mixes: [Mix; CHANNEL_COUNT],
+    /// Whether each channel's chip is there. Expansion chips that the
+    /// cartridge doesn't have aren't run, and their channels are silent.
+    present: [bool; CHANNEL_COUNT],
+    /// The samples of each channel from the last fill.
+    waveforms: [Vec<i16>; CHANNEL_COUNT],
+    recorder: Option<Recorder>,
+    /// The mixed samples from the last fill. Only kept while recording.
+    mixed: Vec<i16>,
+    reader: Box<DmcReader>,
+    dmc: Dmc,
+    /// The number of cycles the APU is behind the CPU. The APU is held back
+    /// while a DMC DMA is pending so that the fetched byte is ready before
+    /// Nes_Apu reads it. Register accesses in the meantime happen at the
+    /// start of the lag, otherwise Nes_Apu would run up to them and fetch
+    /// early.
+    lag: i32,
+    /// $4017
+    frame_counter: u8,
 }
 
 impl Apu {
     pub fn new() -> Apu {
-        let mut buffer = ffi::blip_buffer_new();
-        buffer.pin_mut().set_sample_rate(
-            SAMPLE_RATE,
-            Duration::from_millis(1000).as_millis() as i32,
-        );
-        buffer.pin_mut().clock_rate(CLOCK_RATE);
+        let mut buffers: [_; CHANNEL_COUNT] = std::array::from_fn(|_| {
+            let mut buffer = ffi::blip_buffer_new();
+            buffer.pin_mut().set_sample_rate(
+                SAMPLE_RATE,
+                Duration::from_millis(1000).as_millis() as i32,
+            );
+            buffer.pin_mut().clock_rate(Region::Ntsc.cpu_clock_rate() as i32);
+            buffer
+        });
+        // TODO: Do the sound chips do anything with the buffer pointers on
+        // destruction? Is there a risk of a double free?
+        let mut buffer_ptr = |channel: Channel| unsafe {
+            buffers[channel as usize].pin_mut().get_unchecked_mut()
+                as *mut ffi::BlipBuffer
+        };
 
         let mut nes_apu = ffi::nes_apu_new();
-        let buffer_ptr = buffer.into_raw();
+        for (index, channel) in Channel::ALL.into_iter().enumerate() {
+            if index as i32 == NES_APU_OSC_COUNT {
+                break;
+            }
+            unsafe {
+                nes_apu
+                    .pin_mut()
+                    .osc_output(index as i32, buffer_ptr(channel));
+            }
+        }
+
+        // The expansion chips mix all of their oscillators into one channel.
+        let mut vrc6 = ffi::vrc6_apu_new();
+        for index in 0..VRC6_OSC_COUNT {
+            unsafe {
+                vrc6.pin_mut().osc_output(index, buffer_ptr(Channel::Vrc6));
+            }
+        }
+        let mut namco = ffi::namco_apu_new();
+        for index in 0..NAMCO_OSC_COUNT {
+            unsafe {
+                namco
+                    .pin_mut()
+                    .osc_output(index, buffer_ptr(Channel::Namco163));
+            }
+        }
+        let mut fme7 = ffi::fme7_apu_new();
+        for index in 0..FME7_OSC_COUNT {
+            unsafe {
+                fme7.pin_mut()
+                    .osc_output(index, buffer_ptr(Channel::Sunsoft5b));
+            }
+        }
+
+        // The reader is boxed so that its address stays the same when the
+        // APU moves.
+        let mut reader = Box::new(DmcReader { data: 0, addr: 0, reads: 0 });
         unsafe {
-            nes_apu.pin_mut().set_output(buffer_ptr);
+            ffi::set_dmc_reader(nes_apu.pin_mut(), &mut *reader);
         }
-        // TODO: Does Nes_APU do anything with the buffer pointer on
-        // destruction? Is there a risk of a double free?
-        let buffer = unsafe { UniquePtr::from_raw(buffer_ptr) };
 
-        Apu { buffer, nes_apu }
+        Apu {
+            buffers,
+            nes_apu,
+            vrc6,
+            namco,
+            fme7,
+            mixes: std::array::from_fn(|_| Mix {
+                muted: false,
+                volume: 1.0,
+                pan: 0.0,
+            }),
+            present: std::array::from_fn(|index| {
+                index < NES_APU_OSC_COUNT as usize
+            }),
+            waveforms: Default::default(),
+            recorder: None,
+            mixed: Vec::new(),
+            reader,
+            dmc: Dmc {
+                addr: 0xC000,
+                remaining: 0,
+                looping: false,
+                sample_addr: 0xC000,
+                sample_len: 1,
+                start: None,
+            },
+            lag: 0,
+            frame_counter: 0,
+        }
     }
 }
 
 pub fn tick(emu: &mut Emu) {
-    emu.apu.nes_apu.pin_mut().end_frame(1);
-    emu.apu.buffer.pin_mut().end_frame(1);
+    emu.apu.lag += 1;
+    if !dmc_dma_pending(emu) {
+        run(emu);
+    }
+}
+
+/// Returns true if the DMC needs a sample byte before the APU can catch up to
+/// the CPU.
+pub fn dmc_dma_pending(emu: &Emu) -> bool {
+    emu.apu.dmc.start.is_some()
+        || ffi::count_dmc_reads(&emu.apu.nes_apu, emu.apu.lag) > 0
+}
+
+/// Returns the address of the next DMC sample byte.
+pub fn dmc_addr(emu: &Emu) -> u16 {
+    emu.apu.dmc.addr
+}
+
+/// Hands the byte fetched by a DMC DMA to the DMC and lets the APU catch up.
+pub fn dmc_fill(emu: &mut Emu, data: u8) {
+    emu.apu.reader.data = data;
+    let reads = emu.apu.reader.reads;
+    if let Some(status) = emu.apu.dmc.start.take() {
+        // The APU was held back since the write, so Nes_Apu still sees it at
+        // the right time.
+        emu.apu.nes_apu.pin_mut().write_register(0, 0x4015, status);
+    }
+    run(emu);
+    debug_assert_eq!(emu.apu.reader.reads, reads + 1);
+    debug_assert_eq!(emu.apu.reader.addr, emu.apu.dmc.addr);
+    emu.apu.dmc.advance();
+    // The DMC's IRQ is due when the last byte of the sample is played.
+    update_irq(emu);
+}
+
+fn run(emu: &mut Emu) {
+    if emu.apu.lag == 0 {
+        return;
+    }
+    let lag = emu.apu.lag;
+    let present = emu.apu.present;
+    emu.apu.nes_apu.pin_mut().end_frame(lag);
+    if present[Channel::Vrc6 as usize] {
+        emu.apu.vrc6.pin_mut().end_frame(lag);
+    }
+    if present[Channel::Namco163 as usize] {
+        emu.apu.namco.pin_mut().end_frame(lag);
+    }
+    if present[Channel::Sunsoft5b as usize] {
+        emu.apu.fme7.pin_mut().end_frame(lag);
+    }
+    for (buffer, present) in emu.apu.buffers.iter_mut().zip(present) {
+        if present {
+            buffer.pin_mut().end_frame(lag);
+        }
+    }
+    emu.apu.lag = 0;
+}
+
+/// Updates the CPU's IRQ line from the frame counter and DMC, and queues an
+/// update for when one of them will set its IRQ flag next.
+pub fn update_irq(emu: &mut Emu) {
+    let lag = emu.apu.lag;
+    let irq = emu.apu.nes_apu.earliest_irq(lag);
+    cpu::set_irq(emu, IrqSource::Apu, irq <= lag);
+    let kind = EventKind::Irq(IrqSource::Apu);
+    if irq > lag && irq < NO_IRQ {
+        scheduler::reschedule(emu, kind, (irq - lag) as u64);
+    } else {
+        scheduler::cancel(emu, kind);
+    }
 }
 
 pub fn read(emu: &mut Emu) -> u8 {
-    emu.apu.nes_apu.pin_mut().read_status(0)
+    let data = emu.apu.nes_apu.pin_mut().read_status(0);
+    // Reading $4015 clears the frame counter's IRQ flag.
+    update_irq(emu);
+    data
 }
 
 pub fn write(emu: &mut Emu, addr: u16, data: u8) {
+    match addr {
+        0x4010 => emu.apu.dmc.looping = data & 0x40 != 0,
+        0x4012 => emu.apu.dmc.sample_addr = 0xC000 | (data as u16) << 6,
+        0x4013 => emu.apu.dmc.sample_len = (data as u16) << 4 | 1,
+        0x4015 if data & 0x10 == 0 => {
+            emu.apu.dmc.remaining = 0;
+            emu.apu.dmc.start = None;
+        }
+        0x4015 if emu.apu.dmc.remaining == 0 => {
+            // The first byte of the sample is fetched by a DMA on the next
+            // read cycle, and the write goes through then.
+            emu.apu.dmc.restart();
+            emu.apu.dmc.start = Some(data);
+            return;
+        }
+        0x4017 => emu.apu.frame_counter = data,
+        _ => (),
+    }
+
+    let reads = emu.apu.reader.reads;
     emu.apu.nes_apu.pin_mut().write_register(0, addr, data);
+    if emu.apu.reader.reads != reads {
+        emu.apu.dmc.advance();
+    }
+    if matches!(addr, 0x4010 | 0x4015 | 0x4017) {
+        update_irq(emu);
+    }
+}
+
+/// Sets the clock rate and the noise, DMC, and frame counter timing for
+/// `region`. Only call this at power on since it resets the APU.
+pub fn set_region(emu: &mut Emu, region: Region) {
+    emu.apu.nes_apu.pin_mut().reset(region.has_pal_apu(), 0);
+    for buffer in &mut emu.apu.buffers {
+        buffer.pin_mut().clock_rate(region.cpu_clock_rate() as i32);
+    }
+    // The frame counter starts out with its IRQ enabled.
+    update_irq(emu);
+}
+
+/// Resets the APU like the reset button does. The channels are silenced and
+/// the frame counter restarts in the mode it was in.
+pub fn reset(emu: &mut Emu) {
+    write(emu, 0x4015, 0x00);
+    write(emu, 0x4017, emu.apu.frame_counter);
+}
+
+/// Puts the APU's and the expansion audio chips' registers back in their
+/// power-on state.
+pub fn power_on(emu: &mut Emu) {
+    write(emu, 0x4015, 0x00);
+    for addr in 0x4000..=0x4013 {
+        write(emu, addr, 0x00);
+    }
+    write(emu, 0x4017, 0x00);
+    reset_expansions(emu);
+}
+
+/// Silences the expansion audio chips and clears their registers.
+pub fn reset_expansions(emu: &mut Emu) {
+    emu.apu.vrc6.pin_mut().reset();
+    emu.apu.namco.pin_mut().reset();
+    emu.apu.fme7.pin_mut().reset();
+}
+
+/// Runs the expansion audio chip that `channel` comes from. The others stay
+/// silent.
+pub fn add_expansion(emu: &mut Emu, channel: Channel) {
+    emu.apu.present[channel as usize] = true;
+}
+
+/// Writes to a VRC6 sound register ($9000-$9003, $A000-$A002, or
+/// $B000-$B002).
+pub fn write_vrc6(emu: &mut Emu, addr: u16, data: u8) {
+    let osc = (addr >> 12) as i32 - 9;
+    let reg = (addr & 0x0003) as i32;
+    emu.apu.vrc6.pin_mut().write_osc(0, osc, reg, data as i32);
+}
+
+/// Writes to the Namco 163's address port ($F800-$FFFF).
+pub fn write_namco_addr(emu: &mut Emu, data: u8) {
+    emu.apu.namco.pin_mut().write_addr(data as i32);
 }
 
-/// Fills `dst` with samples.
+/// Writes to the Namco 163's data port ($4800-$4FFF).
+pub fn write_namco_data(emu: &mut Emu, data: u8) {
+    emu.apu.namco.pin_mut().write_data(0, data as i32);
+}
+
+/// Writes to the Sunsoft 5B's register select ($C000-$DFFF).
+pub fn write_fme7_latch(emu: &mut Emu, data: u8) {
+    emu.apu.fme7.pin_mut().write_latch(data as i32);
+}
+
+/// Writes to the Sunsoft 5B's selected register ($E000-$FFFF).
+pub fn write_fme7_data(emu: &mut Emu, data: u8) {
+    emu.apu.fme7.pin_mut().write_data(0, data as i32);
+}
+
+/// Returns the number of stereo samples that are ready to be filled.
+pub fn samples(emu: &Emu) -> usize {
+    // The channels are run together, so every buffer that's run has the
+    // same number of samples.
+    emu.apu.buffers[0].samples_avail() as usize
+}
+
+pub fn set_muted(emu: &mut Emu, channel: Channel, muted: bool) {
+    emu.apu.mixes[channel as usize].muted = muted;
+}
+
+pub fn set_volume(emu: &mut Emu, channel: Channel, volume: f32) {
+    emu.apu.mixes[channel as usize].volume = volume;
+}
+
+pub fn set_pan(emu: &mut Emu, channel: Channel, pan: f32) {
+    emu.apu.mixes[channel as usize].pan = pan.clamp(-1.0, 1.0);
+}
+
+/// Returns the unmixed samples of `channel` from the last fill.
+pub fn waveform(emu: &Emu, channel: Channel) -> &[i16] {
+    &emu.apu.waveforms[channel as usize]
+}
+
+/// Starts recording filled samples with `recorder`, returning the previous
+/// recorder, if any.
+pub fn set_recorder(
+    emu: &mut Emu,
+    recorder: Option<Recorder>,
+) -> Option<Recorder> {
+    std::mem::replace(&mut emu.apu.recorder, recorder)
+}
+
+pub fn is_recording(emu: &Emu) -> bool {
+    emu.apu.recorder.is_some()
+}
+
+/// Fills and throws away the samples that are ready, so the buffers don't
+/// overflow when nothing plays them. They're still recorded.
+pub fn discard_samples(emu: &mut Emu) {
+    const CHUNK_SIZE: usize = 1024;
+
+    let mut chunk = [MaybeUninit::uninit(); CHUNK_SIZE * 2];
+    while samples(emu) > 0 {
+        let len = samples(emu).min(CHUNK_SIZE);
+        fill(emu, &mut chunk[..len * 2]);
+    }
+}
+
+/// Fills `dst` with interleaved stereo samples, left first. `dst` should hold
+/// whole frames. If its length is odd, the last element is zeroed. Frames
+/// past the samples that are ready are silent.
 pub fn fill(emu: &mut Emu, dst: &mut [MaybeUninit<i16>]) {
-    if dst.is_empty() {
+    let len = dst.len() / 2;
+    if let [.., last] = &mut dst[len * 2..] {
+        last.write(0);
+    }
+    if len == 0 {
         return;
     }
 
-    unsafe {
-        // TODO: Check that the number of samples read equals dst.len().
-        emu.apu.buffer.pin_mut().read_samples(
-            dst.as_mut_ptr() as *mut _,
-            dst.len() as i32,
-            false,
-        );
-    };
+    let apu = &mut emu.apu;
+    for (index, waveform) in apu.waveforms.iter_mut().enumerate() {
+        waveform.resize(len, 0);
+        if !apu.present[index] {
+            waveform.fill(0);
+            continue;
+        }
+        let read = unsafe {
+            apu.buffers[index].pin_mut().read_samples(
+                waveform.as_mut_ptr(),
+                len as i32,
+                false,
+            )
+        };
+        // A buffer that's short (e.g., when asked for more than
+        // `samples`) is padded with silence.
+        waveform[read as usize..].fill(0);
+    }
+
+    emu.apu.mixed.clear();
+    for (i, frame) in dst.chunks_exact_mut(2).enumerate() {
+        let samples = std::array::from_fn(|c| emu.apu.waveforms[c][i]);
+        let [left, right] = mix(&emu.apu.mixes, samples);
+        frame[0].write(left);
+        frame[1].write(right);
+        if emu.apu.recorder.is_some() {
+            emu.apu.mixed.extend([left, right]);
+        }
+    }
+
+    if let Some(recorder) = &mut emu.apu.recorder {
+        recorder.write(&emu.apu.mixed, &emu.apu.waveforms);
+    }
 }
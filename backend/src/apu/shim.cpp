@@ -2,6 +2,10 @@
 
 #include "Blip_Buffer.h"
 #include "Nes_Apu.h"
+#include "Nes_Fme7_Apu.h"
+#include "Nes_Namco_Apu.h"
+#include "Nes_Vrc6_Apu.h"
+#include "backend/src/apu.rs.h"
 
 std::unique_ptr<Blip_Buffer> blip_buffer_new()
 {
@@ -12,3 +16,33 @@ std::unique_ptr<Nes_Apu> nes_apu_new()
 {
     return std::make_unique<Nes_Apu>();
 }
+
+static int dmc_read_callback(void* user_data, cpu_addr_t addr)
+{
+    return dmc_read(*static_cast<DmcReader*>(user_data), addr);
+}
+
+void set_dmc_reader(Nes_Apu& nes_apu, DmcReader* reader)
+{
+    nes_apu.dmc_reader(dmc_read_callback, reader);
+}
+
+int count_dmc_reads(const Nes_Apu& nes_apu, int time)
+{
+    return nes_apu.count_dmc_reads(time);
+}
+
+std::unique_ptr<Nes_Vrc6_Apu> vrc6_apu_new()
+{
+    return std::make_unique<Nes_Vrc6_Apu>();
+}
+
+std::unique_ptr<Nes_Namco_Apu> namco_apu_new()
+{
+    return std::make_unique<Nes_Namco_Apu>();
+}
+
+std::unique_ptr<Nes_Fme7_Apu> fme7_apu_new()
+{
+    return std::make_unique<Nes_Fme7_Apu>();
+}
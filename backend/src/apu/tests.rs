@@ -0,0 +1,56 @@
+use super::{mix, Mix, CHANNEL_COUNT};
+
+/// Returns unmuted, centered channels at full volume.
+fn make_mixes() -> [Mix; CHANNEL_COUNT] {
+    std::array::from_fn(|_| Mix { muted: false, volume: 1.0, pan: 0.0 })
+}
+
+/// Returns a sample for the first channel and silence for the rest.
+fn solo(sample: i16) -> [i16; CHANNEL_COUNT] {
+    let mut samples = [0; CHANNEL_COUNT];
+    samples[0] = sample;
+    samples
+}
+
+#[test]
+fn centered() {
+    let mixes = make_mixes();
+    assert_eq!(mix(&mixes, solo(1000)), [1000, 1000]);
+    assert_eq!(mix(&mixes, [100; CHANNEL_COUNT]), [800, 800]);
+}
+
+#[test]
+fn muted() {
+    let mut mixes = make_mixes();
+    mixes[0].muted = true;
+    assert_eq!(mix(&mixes, solo(1000)), [0, 0]);
+    assert_eq!(mix(&mixes, [100; CHANNEL_COUNT]), [700, 700]);
+}
+
+#[test]
+fn volume() {
+    let mut mixes = make_mixes();
+    mixes[0].volume = 0.5;
+    assert_eq!(mix(&mixes, solo(1000)), [500, 500]);
+    mixes[0].volume = 0.0;
+    assert_eq!(mix(&mixes, solo(1000)), [0, 0]);
+}
+
+#[test]
+fn pan() {
+    let mut mixes = make_mixes();
+    mixes[0].pan = -1.0;
+    assert_eq!(mix(&mixes, solo(1000)), [1000, 0]);
+    mixes[0].pan = 1.0;
+    assert_eq!(mix(&mixes, solo(1000)), [0, 1000]);
+    // The far side is attenuated, and the near side stays at full volume.
+    mixes[0].pan = 0.5;
+    assert_eq!(mix(&mixes, solo(1000)), [500, 1000]);
+}
+
+#[test]
+fn saturates() {
+    let mixes = make_mixes();
+    assert_eq!(mix(&mixes, [i16::MAX; CHANNEL_COUNT]), [i16::MAX; 2]);
+    assert_eq!(mix(&mixes, [i16::MIN; CHANNEL_COUNT]), [i16::MIN; 2]);
+}
@@ -0,0 +1,169 @@
+#![cfg_attr(test, allow(dead_code))]
+
+use std::io;
+
+use crate::{
+    apu::Channel,
+    config::RamInit,
+    nrom::{self, Nrom},
+    nsf::{self, Nsf},
+    region::Region,
+    Emu,
+};
+
+/// The cartridge, i.e., whatever is mapped into $4020-$FFFF.
+pub enum Cart {
+    Nrom(Nrom),
+    Nsf(Nsf),
+}
+
+impl Cart {
+    /// Loads an iNES, NSF, or NSFe file. Returns an error if it's malformed.
+    pub fn new(rom: &[u8]) -> io::Result<Cart> {
+        if nsf::is_nsf(rom) {
+            Ok(Cart::Nsf(Nsf::new(rom)?))
+        } else {
+            Ok(Cart::Nrom(Nrom::new(rom)?))
+        }
+    }
+}
+
+/// Reads the byte at `addr`, or returns `None` if the cartridge doesn't
+/// respond to `addr` and the read is open bus.
+pub fn read(emu: &mut Emu, addr: u16) -> Option<u8> {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => match addr {
+            0x6000..=0x7FFF => Some(nrom::read_prg_ram(nrom, addr)),
+            0x8000..=0xFFFF => Some(nrom::read_prg_rom(nrom, addr)),
+            _ => None,
+        },
+        Cart::Nsf(_) => nsf::read(emu, addr),
+    }
+}
+
+pub fn write(emu: &mut Emu, addr: u16, data: u8) {
+    match emu.cart {
+        Cart::Nrom(ref mut nrom) => match addr {
+            0x6000..=0x7FFF => nrom::write_prg_ram(nrom, addr, data),
+            0x8000..=0xFFFF => nrom::write_prg_rom(nrom, addr, data),
+            _ => (),
+        },
+        Cart::Nsf(_) => nsf::write(emu, addr, data),
+    }
+}
+
+/// Reads the byte at `addr` ($0000-$1FFF) in the PPU's pattern tables.
+pub fn read_chr(emu: &Emu, addr: u16) -> u8 {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => nrom::read_chr(nrom, addr),
+        // NSF players don't have anything on the PPU's bus.
+        Cart::Nsf(_) => 0,
+    }
+}
+
+pub fn write_chr(emu: &mut Emu, addr: u16, data: u8) {
+    match emu.cart {
+        Cart::Nrom(ref mut nrom) => nrom::write_chr(nrom, addr, data),
+        Cart::Nsf(_) => (),
+    }
+}
+
+/// Returns the offset into the PPU's nametable RAM that `addr`
+/// ($2000-$2FFF) is mapped to.
+pub fn nametable_offset(emu: &Emu, addr: u16) -> usize {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => nrom::nametable_offset(nrom, addr),
+        Cart::Nsf(_) => addr as usize & 0x07FF,
+    }
+}
+
+/// Fills PRG RAM with its power-on contents.
+pub fn init_prg_ram(emu: &mut Emu, init: RamInit) {
+    match emu.cart {
+        Cart::Nrom(ref mut nrom) => init.fill(&mut nrom.prg_ram),
+        // NSF players zero PRG RAM before each song.
+        Cart::Nsf(_) => (),
+    }
+}
+
+pub fn peek(emu: &Emu, addr: u16) -> Option<u8> {
+    // TODO: Do any mappers have side effects on reads? If they do, we need
+    // to call out to a peek method on the mappers that disables side effects.
+    match emu.cart {
+        Cart::Nrom(ref nrom) => match addr {
+            0x6000..=0x7FFF => Some(nrom::read_prg_ram(nrom, addr)),
+            0x8000..=0xFFFF => Some(nrom::read_prg_rom(nrom, addr)),
+            _ => None,
+        },
+        Cart::Nsf(_) => nsf::peek(emu, addr),
+    }
+}
+
+/// Returns the expansion audio channels on the cartridge.
+pub fn expansion_channels(emu: &Emu) -> Vec<Channel> {
+    match emu.cart {
+        Cart::Nrom(_) => Vec::new(),
+        Cart::Nsf(_) => nsf::expansion_channels(emu),
+    }
+}
+
+/// Returns the region in the cartridge's header.
+pub fn region(emu: &Emu) -> Region {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => nrom.region,
+        Cart::Nsf(_) => nsf::region(emu),
+    }
+}
+
+pub fn prg_rom_size(emu: &Emu) -> usize {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => nrom.prg_rom.len(),
+        Cart::Nsf(_) => nsf::prg_size(emu),
+    }
+}
+
+pub fn chr_rom_size(emu: &Emu) -> usize {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => nrom.chr_rom.len(),
+        Cart::Nsf(_) => 0,
+    }
+}
+
+/// Returns the offset into PRG ROM that `addr` is currently mapped to, if
+/// it's mapped to PRG ROM.
+pub fn prg_rom_offset(emu: &Emu, addr: u16) -> Option<usize> {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => match addr {
+            0x8000..=0xFFFF => Some(nrom::prg_rom_offset(nrom, addr)),
+            _ => None,
+        },
+        Cart::Nsf(_) => match addr {
+            0x8000..=0xFFFF => Some(nsf::prg_index(emu, addr)),
+            _ => None,
+        },
+    }
+}
+
+/// Returns the offset into CHR ROM that `addr` on the PPU's bus is
+/// currently mapped to, if it's mapped to CHR ROM.
+pub fn chr_rom_offset(emu: &Emu, addr: u16) -> Option<usize> {
+    match emu.cart {
+        Cart::Nrom(ref nrom) => match addr {
+            0x0000..=0x1FFF => nrom::chr_rom_offset(nrom, addr),
+            _ => None,
+        },
+        Cart::Nsf(_) => None,
+    }
+}
+
+pub fn poke(emu: &mut Emu, addr: u16, data: u8) -> bool {
+    match emu.cart {
+        Cart::Nrom(ref mut nrom) => match addr {
+            0x6000..=0x7FFF => nrom::write_prg_ram(nrom, addr, data),
+            0x8000..=0xFFFF => nrom::write_prg_rom(nrom, addr, data),
+            _ => return false,
+        },
+        Cart::Nsf(_) => return nsf::poke(emu, addr, data),
+    }
+    true
+}
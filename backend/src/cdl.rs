@@ -0,0 +1,182 @@
+//! A code/data logger that marks how each byte of PRG ROM and CHR ROM was
+//! used. Logs are in FCEUX's .cdl format: one flag byte per PRG ROM byte
+//! followed by one per CHR ROM byte.
+
+#![cfg_attr(test, allow(dead_code))]
+
+use std::{fs, io, path::Path};
+
+use crate::{cart, Emu};
+
+/// The PRG ROM byte was executed as an opcode or operand.
+pub const CODE: u8 = 1 << 0;
+/// The PRG ROM byte was read as data.
+pub const DATA: u8 = 1 << 1;
+/// The 8 KB window of $8000-$FFFF that the PRG ROM byte was last accessed
+/// through.
+pub const WINDOW: u8 = 0b11 << 2;
+/// The PRG ROM byte was the target of an indirect jump.
+pub const INDIRECT_CODE: u8 = 1 << 4;
+/// The PRG ROM byte was read through a pointer, i.e., with ($nn,X) or
+/// ($nn),Y.
+pub const INDIRECT_DATA: u8 = 1 << 5;
+/// The PRG ROM byte was read by the DMC. `DATA` is set too.
+pub const PCM: u8 = 1 << 6;
+
+/// The CHR ROM byte was rendered.
+pub const RENDERED: u8 = 1 << 0;
+/// The CHR ROM byte was read through $2007.
+pub const READ: u8 = 1 << 1;
+
+/// How the current instruction's operand is read.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Direct,
+    /// Through a pointer.
+    Indirect,
+    /// From the byte after the opcode, which is already marked as code.
+    Immediate,
+}
+
+pub struct Cdl {
+    prg: Box<[u8]>,
+    chr: Box<[u8]>,
+    operand: Operand,
+}
+
+impl Cdl {
+    fn new(prg_size: usize, chr_size: usize) -> Cdl {
+        Cdl {
+            prg: vec![0; prg_size].into(),
+            chr: vec![0; chr_size].into(),
+            operand: Operand::Direct,
+        }
+    }
+
+    /// Loads the log at `path`. It has to be the right size for a cartridge
+    /// with `prg_size` bytes of PRG ROM and `chr_size` bytes of CHR ROM.
+    fn load(path: &Path, prg_size: usize, chr_size: usize) -> io::Result<Cdl> {
+        let mut prg = fs::read(path)?;
+        if prg.len() != prg_size + chr_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected a {} byte log, but it's {} bytes",
+                    prg_size + chr_size,
+                    prg.len()
+                ),
+            ));
+        }
+        let chr = prg.split_off(prg_size);
+        Ok(Cdl { prg: prg.into(), chr: chr.into(), operand: Operand::Direct })
+    }
+
+    /// Writes the log to `path` in FCEUX's .cdl format.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, [&self.prg[..], &self.chr[..]].concat())
+    }
+
+    /// Returns the flags of each PRG ROM byte.
+    pub fn prg(&self) -> &[u8] {
+        &self.prg
+    }
+
+    /// Returns the flags of each CHR ROM byte.
+    pub fn chr(&self) -> &[u8] {
+        &self.chr
+    }
+}
+
+/// Starts logging with an empty log, unless logging is already on.
+pub fn start(emu: &mut Emu) {
+    if emu.cdl.is_none() {
+        emu.cdl =
+            Some(Cdl::new(cart::prg_rom_size(emu), cart::chr_rom_size(emu)));
+    }
+}
+
+/// Loads the log at `path` and continues logging into it.
+pub fn load(emu: &mut Emu, path: &Path) -> io::Result<()> {
+    let cdl =
+        Cdl::load(path, cart::prg_rom_size(emu), cart::chr_rom_size(emu))?;
+    emu.cdl = Some(cdl);
+    Ok(())
+}
+
+/// Marks the byte at `addr` with `flags` if it's in PRG ROM.
+#[inline]
+pub fn log(emu: &mut Emu, addr: u16, flags: u8) {
+    if emu.cdl.is_some() {
+        log_prg(emu, addr, flags);
+    }
+}
+
+#[cold]
+fn log_prg(emu: &mut Emu, addr: u16, flags: u8) {
+    let Some(offset) = cart::prg_rom_offset(emu, addr) else {
+        return;
+    };
+    let window = (addr >> 13 & 0b11) as u8;
+    let cdl = emu.cdl.as_mut().unwrap();
+    let byte = &mut cdl.prg[offset];
+    *byte = *byte & !WINDOW | flags | window << 2;
+}
+
+/// Marks the byte at `addr` on the PPU's bus with `flags` if it's in CHR
+/// ROM.
+#[inline]
+pub fn log_chr(emu: &mut Emu, addr: u16, flags: u8) {
+    if emu.cdl.is_some() {
+        log_chr_rom(emu, addr, flags);
+    }
+}
+
+#[cold]
+fn log_chr_rom(emu: &mut Emu, addr: u16, flags: u8) {
+    let Some(offset) = cart::chr_rom_offset(emu, addr) else {
+        return;
+    };
+    emu.cdl.as_mut().unwrap().chr[offset] |= flags;
+}
+
+/// Marks an opcode or operand fetch.
+#[inline]
+pub fn log_code(emu: &mut Emu, addr: u16) {
+    if let Some(cdl) = &mut emu.cdl {
+        cdl.operand = Operand::Direct;
+        log_prg(emu, addr, CODE);
+    }
+}
+
+/// Marks the fetch of an immediate operand. It's code, so the read of it
+/// isn't marked as data.
+#[inline]
+pub fn log_immediate(emu: &mut Emu, addr: u16) {
+    if let Some(cdl) = &mut emu.cdl {
+        cdl.operand = Operand::Immediate;
+        log_prg(emu, addr, CODE);
+    }
+}
+
+/// Marks the current instruction as using a pointer, so its data read is
+/// marked as indirect. This lasts until the next opcode or operand fetch.
+#[inline]
+pub fn set_indirect(emu: &mut Emu) {
+    if let Some(cdl) = &mut emu.cdl {
+        cdl.operand = Operand::Indirect;
+    }
+}
+
+/// Marks a read of the current instruction's operand.
+#[inline]
+pub fn log_data(emu: &mut Emu, addr: u16) {
+    let Some(cdl) = &emu.cdl else {
+        return;
+    };
+    let flags = match cdl.operand {
+        Operand::Direct => DATA,
+        Operand::Indirect => DATA | INDIRECT_DATA,
+        Operand::Immediate => return,
+    };
+    log_prg(emu, addr, flags);
+}
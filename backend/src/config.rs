@@ -0,0 +1,68 @@
+//! Settings that are picked when the emulator is made, like what's in memory
+//! at power on.
+
+#[cfg(test)]
+mod tests;
+
+use crate::region::Region;
+
+/// What memory holds at power on. Real hardware powers on with mostly, but
+/// not entirely, predictable contents, so games shouldn't depend on them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamInit {
+    /// Every byte is $00.
+    #[default]
+    Zeros,
+    /// Every byte is $FF.
+    Ones,
+    /// Four bytes of $00 followed by four bytes of $FF, repeated. This is
+    /// the most common pattern on real consoles.
+    Pattern,
+    /// Random bytes from the seed. The same seed gives the same bytes.
+    Random(u64),
+}
+
+impl RamInit {
+    /// Fills `ram` with the pattern.
+    pub fn fill(self, ram: &mut [u8]) {
+        match self {
+            RamInit::Zeros => ram.fill(0x00),
+            RamInit::Ones => ram.fill(0xFF),
+            RamInit::Pattern => {
+                for (i, byte) in ram.iter_mut().enumerate() {
+                    *byte = if i & 4 == 0 { 0x00 } else { 0xFF };
+                }
+            }
+            RamInit::Random(seed) => {
+                // splitmix64, which works for any seed, including 0.
+                let mut state = seed;
+                for byte in ram {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ z >> 30).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ z >> 27).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    *byte = (z ^ z >> 31) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// The power-on contents of each memory. They're only applied on a cold
+/// boot, and a soft reset leaves memory alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// The CPU's internal RAM at $0000-$07FF.
+    pub cpu_ram: RamInit,
+    /// The cartridge's PRG RAM at $6000-$7FFF. NSFs always start with it
+    /// zeroed.
+    pub prg_ram: RamInit,
+    /// The PPU's object attribute memory.
+    pub oam: RamInit,
+    /// The PPU's 2 KB of nametable RAM.
+    pub vram: RamInit,
+    /// The PPU's palette RAM. Only the low 6 bits of each entry are kept.
+    pub palette: RamInit,
+    /// The console region, or `None` to use the one in the ROM's header.
+    pub region: Option<Region>,
+}
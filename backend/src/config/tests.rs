@@ -0,0 +1,33 @@
+use super::RamInit;
+
+#[test]
+fn zeros_and_ones() {
+    let mut ram = [0x12; 16];
+    RamInit::Zeros.fill(&mut ram);
+    assert_eq!(ram, [0x00; 16]);
+    RamInit::Ones.fill(&mut ram);
+    assert_eq!(ram, [0xFF; 16]);
+}
+
+#[test]
+fn pattern() {
+    let mut ram = [0; 16];
+    RamInit::Pattern.fill(&mut ram);
+    assert_eq!(ram[..8], [0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(ram[..8], ram[8..]);
+}
+
+#[test]
+fn random() {
+    let mut a = [0; 256];
+    let mut b = [0; 256];
+    RamInit::Random(1).fill(&mut a);
+    RamInit::Random(1).fill(&mut b);
+    assert_eq!(a, b);
+    RamInit::Random(2).fill(&mut b);
+    assert_ne!(a, b);
+    // 0 is still random, and it isn't the same as 1.
+    RamInit::Random(0).fill(&mut b);
+    assert!(b.iter().any(|&byte| byte != b[0]));
+    assert_ne!(a, b);
+}
@@ -0,0 +1,65 @@
+//! Standard controllers plugged into both ports. The buttons are latched
+//! while bit 0 of $4016 is set and then read out one at a time from $4016
+//! (port 1) and $4017 (port 2).
+
+use crate::Emu;
+
+/// The buttons, as bits in the order they're read out.
+pub const A: u8 = 1 << 0;
+pub const B: u8 = 1 << 1;
+pub const SELECT: u8 = 1 << 2;
+pub const START: u8 = 1 << 3;
+pub const UP: u8 = 1 << 4;
+pub const DOWN: u8 = 1 << 5;
+pub const LEFT: u8 = 1 << 6;
+pub const RIGHT: u8 = 1 << 7;
+
+pub struct Controllers {
+    /// The buttons that are held on each controller.
+    buttons: [u8; 2],
+    /// The buttons that haven't been read out yet.
+    shift: [u8; 2],
+    /// Bit 0 of the last write to $4016. The buttons are latched over and
+    /// over while it's set.
+    strobe: bool,
+}
+
+impl Default for Controllers {
+    fn default() -> Controllers {
+        Controllers::new()
+    }
+}
+
+impl Controllers {
+    pub fn new() -> Controllers {
+        Controllers { buttons: [0; 2], shift: [0; 2], strobe: false }
+    }
+}
+
+/// Holds `buttons` on the controller in `port` (0 or 1) and lets go of the
+/// rest.
+pub fn set_buttons(emu: &mut Emu, port: usize, buttons: u8) {
+    emu.controllers.buttons[port] = buttons;
+    if emu.controllers.strobe {
+        emu.controllers.shift[port] = buttons;
+    }
+}
+
+pub fn write(emu: &mut Emu, data: u8) {
+    emu.controllers.strobe = data & 1 != 0;
+    if emu.controllers.strobe {
+        emu.controllers.shift = emu.controllers.buttons;
+    }
+}
+
+/// Reads the next button from the controller in `port`. Official
+/// controllers return 1 once all eight have been read.
+pub fn read(emu: &mut Emu, port: usize) -> u8 {
+    let controllers = &mut emu.controllers;
+    if controllers.strobe {
+        return controllers.buttons[port] & 1;
+    }
+    let bit = controllers.shift[port] & 1;
+    controllers.shift[port] = controllers.shift[port] >> 1 | 0x80;
+    bit
+}
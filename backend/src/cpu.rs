@@ -4,6 +4,7 @@ mod bus;
 mod instruction;
 mod mode;
 mod stack;
+mod trace;
 
 #[cfg(test)]
 #[path = "cpu/tests/bus.rs"]
@@ -13,10 +14,19 @@ mod tests;
 
 use proc_bitfield::bitfield;
 
-use crate::{cpu::bus::Bus, emu::Emu};
+use crate::{
+    cdl,
+    config::RamInit,
+    cpu::{bus::Bus, mode::Mode},
+    emu::Emu,
+    scheduler,
+};
 
+const NMI_VECTOR: u16 = 0xFFFA;
 const RESET_VECTOR: u16 = 0xFFFC;
 const IRQ_VECTOR: u16 = 0xFFFE;
+/// The most common value of the ANE and LXA magic constant.
+const DEFAULT_MAGIC: u8 = 0xEE;
 
 bitfield! {
     #[derive(Clone, Copy)]
@@ -46,6 +56,36 @@ impl Status {
     }
 }
 
+/// A device that can pull the CPU's IRQ line. Each one is a bit in the line's
+/// state, and the line is low while any of them pulls it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrqSource {
+    /// The APU's frame counter or DMC.
+    Apu = 1 << 0,
+}
+
+/// A function that's called with a trace line before each instruction.
+pub type Tracer = Box<dyn FnMut(&str)>;
+
+/// A snapshot of the CPU's registers and timing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub pc: u16,
+    pub s: u8,
+    /// The status register.
+    pub p: u8,
+    /// The number of CPU cycles since power on.
+    pub cycles: u64,
+    /// True if a device is pulling the IRQ line.
+    pub irq: bool,
+    /// True if the PPU has pulled the NMI line and the CPU hasn't serviced
+    /// the NMI yet.
+    pub nmi: bool,
+}
+
 pub struct Cpu {
     /// The accumulator.
     a: u8,
@@ -62,8 +102,40 @@ pub struct Cpu {
 
     bus: Bus,
 
+    /// The opcode of the current instruction.
+    opc: u8,
+    /// The cycle of the current instruction that runs next. The opcode is
+    /// fetched on cycle 0.
+    cycle: u8,
+    /// The cycle that the instruction's operation started on once its
+    /// addressing mode was done, or 0 if it hasn't started.
+    op_start: u8,
+    /// The effective address.
     addr: u16,
     carry: bool,
+    /// A byte that's kept between cycles, e.g., the low byte of a pointer.
+    data: u8,
+
+    /// The constant that the unstable ANE and LXA opcodes OR into A. It
+    /// varies between CPUs (and even with temperature).
+    magic: u8,
+    /// True if the CPU executed a JAM opcode.
+    jammed: bool,
+    /// The devices pulling the IRQ line, as `IrqSource` bits.
+    irq: u8,
+    /// True if an NMI is pending. NMI is edge-triggered, so it stays pending
+    /// until it's serviced.
+    nmi: bool,
+    /// The I flag as it was before the last cycle of the last instruction,
+    /// which is when IRQs are polled. E.g., an IRQ is only taken after the
+    /// instruction that follows CLI.
+    irq_masked: bool,
+    /// True if the current instruction is an NMI or IRQ sequence instead of
+    /// the opcode at PC.
+    interrupt: bool,
+
+    /// Called with a trace line before each instruction.
+    tracer: Option<Tracer>,
 }
 
 impl Cpu {
@@ -73,278 +145,524 @@ impl Cpu {
             x: 0,
             y: 0,
             pc: 0,
-            s: 0xFD,
+            // S is 0 at power on, and the reset sequence decrements it to
+            // 0xFD.
+            s: 0x00,
             p: Status(0x34),
 
             bus: Bus::new(),
 
+            opc: 0,
+            cycle: 0,
+            op_start: 0,
             addr: 0,
             carry: false,
+            data: 0,
+
+            magic: DEFAULT_MAGIC,
+            jammed: false,
+            irq: 0,
+            nmi: false,
+            irq_masked: true,
+            interrupt: false,
+
+            tracer: None,
         }
     }
 }
 
-/// Steps the CPU by one instruction.
-pub fn step(emu: &mut Emu) {
+/// Steps the CPU by one cycle. Returns true if the cycle finished the
+/// instruction. Interrupts are serviced between instructions, and the
+/// sequence counts as an instruction of its own.
+pub fn step_cycle(emu: &mut Emu) -> bool {
+    if emu.cpu.jammed {
+        // Only a reset can recover the CPU, so it keeps reading $FFFF.
+        bus::read(emu, 0xFFFF);
+        return true;
+    }
+
+    let cycle = emu.cpu.cycle;
+    let masked = emu.cpu.p.i();
+    let done = if cycle == 0 {
+        emu.cpu.interrupt =
+            emu.cpu.nmi || emu.cpu.irq != 0 && !emu.cpu.irq_masked;
+        if emu.cpu.interrupt {
+            // The opcode fetch still happens, but PC isn't incremented, and
+            // the rest of the sequence is BRK's.
+            bus::read(emu, emu.cpu.pc);
+            emu.cpu.opc = 0x00;
+        } else {
+            if emu.cpu.tracer.is_some() {
+                trace::log(emu);
+            }
+            emu.cpu.opc = eat_byte(emu);
+        }
+        emu.cpu.op_start = 0;
+        false
+    } else {
+        execute(emu, cycle)
+    };
+    if done {
+        emu.cpu.irq_masked = masked;
+    }
+    emu.cpu.cycle = if done { 0 } else { cycle + 1 };
+    done
+}
+
+/// Runs `cycle` of the current instruction. Returns true if it was the last.
+#[rustfmt::skip]
+fn execute(emu: &mut Emu, cycle: u8) -> bool {
     const R: bool = false;
     const W: bool = true;
 
-    let opc = eat_byte(emu);
-    #[rustfmt::skip]
-    match opc {
-        0x00 => {                      instruction::brk(emu); }
-        0x01 => { mode::idx(emu);      instruction::ora(emu); }
-        0x03 => { mode::idx(emu);      instruction::slo(emu); }
-        0x04 => { mode::zpg(emu);      instruction::nop(emu); }
-        0x05 => { mode::zpg(emu);      instruction::ora(emu); }
-        0x06 => { mode::zpg(emu);      instruction::asl(emu); }
-        0x07 => { mode::zpg(emu);      instruction::slo(emu); }
-        0x08 => {                      instruction::php(emu); }
-        0x09 => { mode::imm(emu);      instruction::ora(emu); }
-        0x0A => {                      instruction::asl_a(emu); }
-        0x0B => { mode::imm(emu);      instruction::anc(emu); }
-        0x0C => { mode::abs(emu);      instruction::nop(emu); }
-        0x0D => { mode::abs(emu);      instruction::ora(emu); }
-        0x0E => { mode::abs(emu);      instruction::asl(emu); }
-        0x0F => { mode::abs(emu);      instruction::slo(emu); }
-        0x10 => {                      instruction::bpl(emu); }
-        0x11 => { mode::idy::<R>(emu); instruction::ora(emu); }
-        0x13 => { mode::idy::<W>(emu); instruction::slo(emu); }
-        0x14 => { mode::zpx(emu);      instruction::nop(emu); }
-        0x15 => { mode::zpx(emu);      instruction::ora(emu); }
-        0x16 => { mode::zpx(emu);      instruction::asl(emu); }
-        0x17 => { mode::zpx(emu);      instruction::slo(emu); }
-        0x18 => {                      instruction::clc(emu); }
-        0x19 => { mode::aby::<R>(emu); instruction::ora(emu); }
-        0x1A => { mode::imp(emu);      instruction::nop(emu); }
-        0x1B => { mode::aby::<W>(emu); instruction::slo(emu); }
-        0x1C => { mode::abx::<R>(emu); instruction::nop(emu); }
-        0x1D => { mode::abx::<R>(emu); instruction::ora(emu); }
-        0x1E => { mode::abx::<W>(emu); instruction::asl(emu); }
-        0x1F => { mode::abx::<W>(emu); instruction::slo(emu); }
-        0x20 => {                      instruction::jsr(emu); }
-        0x21 => { mode::idx(emu);      instruction::and(emu); }
-        0x23 => { mode::idx(emu);      instruction::rla(emu); }
-        0x24 => { mode::zpg(emu);      instruction::bit(emu); }
-        0x25 => { mode::zpg(emu);      instruction::and(emu); }
-        0x26 => { mode::zpg(emu);      instruction::rol(emu); }
-        0x27 => { mode::zpg(emu);      instruction::rla(emu); }
-        0x28 => {                      instruction::plp(emu); }
-        0x29 => { mode::imm(emu);      instruction::and(emu); }
-        0x2A => {                      instruction::rol_a(emu); }
-        0x2B => { mode::imm(emu);      instruction::anc(emu); }
-        0x2C => { mode::abs(emu);      instruction::bit(emu); }
-        0x2D => { mode::abs(emu);      instruction::and(emu); }
-        0x2E => { mode::abs(emu);      instruction::rol(emu); }
-        0x2F => { mode::abs(emu);      instruction::rla(emu); }
-        0x30 => {                      instruction::bmi(emu); }
-        0x31 => { mode::idy::<R>(emu); instruction::and(emu); }
-        0x33 => { mode::idy::<W>(emu); instruction::rla(emu); }
-        0x34 => { mode::zpx(emu);      instruction::nop(emu); }
-        0x35 => { mode::zpx(emu);      instruction::and(emu); }
-        0x36 => { mode::zpx(emu);      instruction::rol(emu); }
-        0x37 => { mode::zpx(emu);      instruction::rla(emu); }
-        0x38 => {                      instruction::sec(emu); }
-        0x39 => { mode::aby::<R>(emu); instruction::and(emu); }
-        0x3A => { mode::imp(emu);      instruction::nop(emu); }
-        0x3B => { mode::aby::<W>(emu); instruction::rla(emu); }
-        0x3C => { mode::abx::<R>(emu); instruction::nop(emu); }
-        0x3D => { mode::abx::<R>(emu); instruction::and(emu); }
-        0x3E => { mode::abx::<W>(emu); instruction::rol(emu); }
-        0x3F => { mode::abx::<W>(emu); instruction::rla(emu); }
-        0x40 => {                      instruction::rti(emu); }
-        0x41 => { mode::idx(emu);      instruction::eor(emu); }
-        0x43 => { mode::idx(emu);      instruction::sre(emu); }
-        0x44 => { mode::zpg(emu);      instruction::nop(emu); }
-        0x45 => { mode::zpg(emu);      instruction::eor(emu); }
-        0x46 => { mode::zpg(emu);      instruction::lsr(emu); }
-        0x47 => { mode::zpg(emu);      instruction::sre(emu); }
-        0x48 => {                      instruction::pha(emu); }
-        0x49 => { mode::imm(emu);      instruction::eor(emu); }
-        0x4A => {                      instruction::lsr_a(emu); }
-        0x4B => { mode::imm(emu);      instruction::alr(emu); }
-        0x4C => { mode::abs(emu);      instruction::jmp(emu); }
-        0x4D => { mode::abs(emu);      instruction::eor(emu); }
-        0x4E => { mode::abs(emu);      instruction::lsr(emu); }
-        0x4F => { mode::abs(emu);      instruction::sre(emu); }
-        0x50 => {                      instruction::bvc(emu); }
-        0x51 => { mode::idy::<R>(emu); instruction::eor(emu); }
-        0x53 => { mode::idy::<W>(emu); instruction::sre(emu); }
-        0x54 => { mode::zpx(emu);      instruction::nop(emu); }
-        0x55 => { mode::zpx(emu);      instruction::eor(emu); }
-        0x56 => { mode::zpx(emu);      instruction::lsr(emu); }
-        0x57 => { mode::zpx(emu);      instruction::sre(emu); }
-        0x58 => {                      instruction::cli(emu); }
-        0x59 => { mode::aby::<R>(emu); instruction::eor(emu); }
-        0x5A => { mode::imp(emu);      instruction::nop(emu); }
-        0x5B => { mode::aby::<W>(emu); instruction::sre(emu); }
-        0x5C => { mode::abx::<R>(emu); instruction::nop(emu); }
-        0x5D => { mode::abx::<R>(emu); instruction::eor(emu); }
-        0x5E => { mode::abx::<W>(emu); instruction::lsr(emu); }
-        0x5F => { mode::abx::<W>(emu); instruction::sre(emu); }
-        0x60 => {                      instruction::rts(emu); }
-        0x61 => { mode::idx(emu);      instruction::adc(emu); }
-        0x63 => { mode::idx(emu);      instruction::rra(emu); }
-        0x64 => { mode::zpg(emu);      instruction::nop(emu); }
-        0x65 => { mode::zpg(emu);      instruction::adc(emu); }
-        0x66 => { mode::zpg(emu);      instruction::ror(emu); }
-        0x67 => { mode::zpg(emu);      instruction::rra(emu); }
-        0x68 => {                      instruction::pla(emu); }
-        0x69 => { mode::imm(emu);      instruction::adc(emu); }
-        0x6A => {                      instruction::ror_a(emu); }
-        0x6B => { mode::imm(emu);      instruction::arr(emu); }
-        0x6C => { mode::ind(emu);      instruction::jmp(emu); }
-        0x6D => { mode::abs(emu);      instruction::adc(emu); }
-        0x6E => { mode::abs(emu);      instruction::ror(emu); }
-        0x6F => { mode::abs(emu);      instruction::rra(emu); }
-        0x70 => {                      instruction::bvs(emu); }
-        0x71 => { mode::idy::<R>(emu); instruction::adc(emu); }
-        0x73 => { mode::idy::<W>(emu); instruction::rra(emu); }
-        0x74 => { mode::zpx(emu);      instruction::nop(emu); }
-        0x75 => { mode::zpx(emu);      instruction::adc(emu); }
-        0x76 => { mode::zpx(emu);      instruction::ror(emu); }
-        0x77 => { mode::zpx(emu);      instruction::rra(emu); }
-        0x78 => {                      instruction::sei(emu); }
-        0x79 => { mode::aby::<R>(emu); instruction::adc(emu); }
-        0x7A => { mode::imp(emu);      instruction::nop(emu); }
-        0x7B => { mode::aby::<W>(emu); instruction::rra(emu); }
-        0x7C => { mode::abx::<R>(emu); instruction::nop(emu); }
-        0x7D => { mode::abx::<R>(emu); instruction::adc(emu); }
-        0x7E => { mode::abx::<W>(emu); instruction::ror(emu); }
-        0x7F => { mode::abx::<W>(emu); instruction::rra(emu); }
-        0x80 => { mode::imm(emu);      instruction::nop(emu); }
-        0x81 => { mode::idx(emu);      instruction::sta(emu); }
-        0x82 => { mode::imm(emu);      instruction::nop(emu); }
-        0x83 => { mode::idx(emu);      instruction::sax(emu); }
-        0x84 => { mode::zpg(emu);      instruction::sty(emu); }
-        0x85 => { mode::zpg(emu);      instruction::sta(emu); }
-        0x86 => { mode::zpg(emu);      instruction::stx(emu); }
-        0x87 => { mode::zpg(emu);      instruction::sax(emu); }
-        0x88 => {                      instruction::dey(emu); }
-        0x89 => { mode::imm(emu);      instruction::nop(emu); }
-        0x8A => {                      instruction::txa(emu); }
-        0x8B => {                      instruction::ane(emu); }
-        0x8C => { mode::abs(emu);      instruction::sty(emu); }
-        0x8D => { mode::abs(emu);      instruction::sta(emu); }
-        0x8E => { mode::abs(emu);      instruction::stx(emu); }
-        0x8F => { mode::abs(emu);      instruction::sax(emu); }
-        0x90 => {                      instruction::bcc(emu); }
-        0x91 => { mode::idy::<W>(emu); instruction::sta(emu); }
-        0x93 => { mode::idy::<W>(emu); instruction::sha(emu); }
-        0x94 => { mode::zpx(emu);      instruction::sty(emu); }
-        0x95 => { mode::zpx(emu);      instruction::sta(emu); }
-        0x96 => { mode::zpy(emu);      instruction::stx(emu); }
-        0x97 => { mode::zpy(emu);      instruction::sax(emu); }
-        0x98 => {                      instruction::tya(emu); }
-        0x99 => { mode::aby::<W>(emu); instruction::sta(emu); }
-        0x9A => {                      instruction::txs(emu); }
-        0x9B => { mode::aby::<W>(emu); instruction::tas(emu); }
-        0x9C => { mode::abx::<W>(emu); instruction::shy(emu); }
-        0x9D => { mode::abx::<W>(emu); instruction::sta(emu); }
-        0x9E => { mode::aby::<W>(emu); instruction::shx(emu); }
-        0x9F => { mode::aby::<W>(emu); instruction::sha(emu); }
-        0xA0 => { mode::imm(emu);      instruction::ldy(emu); }
-        0xA1 => { mode::idx(emu);      instruction::lda(emu); }
-        0xA2 => { mode::imm(emu);      instruction::ldx(emu); }
-        0xA3 => { mode::idx(emu);      instruction::lax(emu); }
-        0xA4 => { mode::zpg(emu);      instruction::ldy(emu); }
-        0xA5 => { mode::zpg(emu);      instruction::lda(emu); }
-        0xA6 => { mode::zpg(emu);      instruction::ldx(emu); }
-        0xA7 => { mode::zpg(emu);      instruction::lax(emu); }
-        0xA8 => {                      instruction::tay(emu); }
-        0xA9 => { mode::imm(emu);      instruction::lda(emu); }
-        0xAA => {                      instruction::tax(emu); }
-        0xAB => { mode::imm(emu);      instruction::lxa(emu); }
-        0xAC => { mode::abs(emu);      instruction::ldy(emu); }
-        0xAD => { mode::abs(emu);      instruction::lda(emu); }
-        0xAE => { mode::abs(emu);      instruction::ldx(emu); }
-        0xAF => { mode::abs(emu);      instruction::lax(emu); }
-        0xB0 => {                      instruction::bcs(emu); }
-        0xB1 => { mode::idy::<R>(emu); instruction::lda(emu); }
-        0xB3 => { mode::idy::<R>(emu); instruction::lax(emu); }
-        0xB4 => { mode::zpx(emu);      instruction::ldy(emu); }
-        0xB5 => { mode::zpx(emu);      instruction::lda(emu); }
-        0xB6 => { mode::zpy(emu);      instruction::ldx(emu); }
-        0xB7 => { mode::zpy(emu);      instruction::lax(emu); }
-        0xB8 => {                      instruction::clv(emu); }
-        0xB9 => { mode::aby::<R>(emu); instruction::lda(emu); }
-        0xBA => {                      instruction::tsx(emu); }
-        0xBB => { mode::aby::<R>(emu); instruction::las(emu); }
-        0xBC => { mode::abx::<R>(emu); instruction::ldy(emu); }
-        0xBD => { mode::abx::<R>(emu); instruction::lda(emu); }
-        0xBE => { mode::aby::<R>(emu); instruction::ldx(emu); }
-        0xBF => { mode::aby::<R>(emu); instruction::lax(emu); }
-        0xC0 => { mode::imm(emu);      instruction::cpy(emu); }
-        0xC1 => { mode::idx(emu);      instruction::cmp(emu); }
-        0xC2 => { mode::imm(emu);      instruction::nop(emu); }
-        0xC3 => { mode::idx(emu);      instruction::dcp(emu); }
-        0xC4 => { mode::zpg(emu);      instruction::cpy(emu); }
-        0xC5 => { mode::zpg(emu);      instruction::cmp(emu); }
-        0xC6 => { mode::zpg(emu);      instruction::dec(emu); }
-        0xC7 => { mode::zpg(emu);      instruction::dcp(emu); }
-        0xC8 => {                      instruction::iny(emu); }
-        0xC9 => { mode::imm(emu);      instruction::cmp(emu); }
-        0xCA => {                      instruction::dex(emu); }
-        0xCB => { mode::imm(emu);      instruction::sbx(emu); }
-        0xCC => { mode::abs(emu);      instruction::cpy(emu); }
-        0xCD => { mode::abs(emu);      instruction::cmp(emu); }
-        0xCE => { mode::abs(emu);      instruction::dec(emu); }
-        0xCF => { mode::abs(emu);      instruction::dcp(emu); }
-        0xD0 => {                      instruction::bne(emu); }
-        0xD1 => { mode::idy::<R>(emu); instruction::cmp(emu); }
-        0xD3 => { mode::idy::<W>(emu); instruction::dcp(emu); }
-        0xD4 => { mode::zpx(emu);      instruction::nop(emu); }
-        0xD5 => { mode::zpx(emu);      instruction::cmp(emu); }
-        0xD6 => { mode::zpx(emu);      instruction::dec(emu); }
-        0xD7 => { mode::zpx(emu);      instruction::dcp(emu); }
-        0xD8 => {                      instruction::cld(emu); }
-        0xD9 => { mode::aby::<R>(emu); instruction::cmp(emu); }
-        0xDA => { mode::imp(emu);      instruction::nop(emu); }
-        0xDB => { mode::aby::<W>(emu); instruction::dcp(emu); }
-        0xDC => { mode::abx::<R>(emu); instruction::nop(emu); }
-        0xDD => { mode::abx::<R>(emu); instruction::cmp(emu); }
-        0xDE => { mode::abx::<W>(emu); instruction::dec(emu); }
-        0xDF => { mode::abx::<W>(emu); instruction::dcp(emu); }
-        0xE0 => { mode::imm(emu);      instruction::cpx(emu); }
-        0xE1 => { mode::idx(emu);      instruction::sbc(emu); }
-        0xE2 => { mode::imm(emu);      instruction::nop(emu); }
-        0xE3 => { mode::idx(emu);      instruction::isc(emu); }
-        0xE4 => { mode::zpg(emu);      instruction::cpx(emu); }
-        0xE5 => { mode::zpg(emu);      instruction::sbc(emu); }
-        0xE6 => { mode::zpg(emu);      instruction::inc(emu); }
-        0xE7 => { mode::zpg(emu);      instruction::isc(emu); }
-        0xE8 => {                      instruction::inx(emu); }
-        0xE9 => { mode::imm(emu);      instruction::sbc(emu); }
-        0xEA => { mode::imp(emu);      instruction::nop(emu); }
-        0xEB => { mode::imm(emu);      instruction::sbc(emu); }
-        0xEC => { mode::abs(emu);      instruction::cpx(emu); }
-        0xED => { mode::abs(emu);      instruction::sbc(emu); }
-        0xEE => { mode::abs(emu);      instruction::inc(emu); }
-        0xEF => { mode::abs(emu);      instruction::isc(emu); }
-        0xF0 => {                      instruction::beq(emu); }
-        0xF1 => { mode::idy::<R>(emu); instruction::sbc(emu); }
-        0xF3 => { mode::idy::<W>(emu); instruction::isc(emu); }
-        0xF4 => { mode::zpx(emu);      instruction::nop(emu); }
-        0xF5 => { mode::zpx(emu);      instruction::sbc(emu); }
-        0xF6 => { mode::zpx(emu);      instruction::inc(emu); }
-        0xF7 => { mode::zpx(emu);      instruction::isc(emu); }
-        0xF8 => {                      instruction::sed(emu); }
-        0xF9 => { mode::aby::<R>(emu); instruction::sbc(emu); }
-        0xFA => { mode::imp(emu);      instruction::nop(emu); }
-        0xFB => { mode::aby::<W>(emu); instruction::isc(emu); }
-        0xFC => { mode::abx::<R>(emu); instruction::nop(emu); }
-        0xFD => { mode::abx::<R>(emu); instruction::sbc(emu); }
-        0xFE => { mode::abx::<W>(emu); instruction::inc(emu); }
-        0xFF => { mode::abx::<W>(emu); instruction::isc(emu); }
-        _ => unreachable!("unexpected opcode: 0x{:04X}", opc)
-    };
+    match emu.cpu.opc {
+        0x00 => instruction::brk(emu, cycle),
+        0x01 => run(emu, mode::idx,      instruction::ora),
+        0x02 => implied(emu, instruction::jam),
+        0x03 => rmw(emu, mode::idx,      instruction::slo),
+        0x04 => run(emu, mode::zpg,      instruction::nop),
+        0x05 => run(emu, mode::zpg,      instruction::ora),
+        0x06 => rmw(emu, mode::zpg,      instruction::asl),
+        0x07 => rmw(emu, mode::zpg,      instruction::slo),
+        0x08 => instruction::php(emu, cycle),
+        0x09 => run(emu, mode::imm,      instruction::ora),
+        0x0A => implied(emu, instruction::asl_a),
+        0x0B => run(emu, mode::imm,      instruction::anc),
+        0x0C => run(emu, mode::abs,      instruction::nop),
+        0x0D => run(emu, mode::abs,      instruction::ora),
+        0x0E => rmw(emu, mode::abs,      instruction::asl),
+        0x0F => rmw(emu, mode::abs,      instruction::slo),
+        0x10 => instruction::bpl(emu, cycle),
+        0x11 => run(emu, mode::idy::<R>, instruction::ora),
+        0x12 => implied(emu, instruction::jam),
+        0x13 => rmw(emu, mode::idy::<W>, instruction::slo),
+        0x14 => run(emu, mode::zpx,      instruction::nop),
+        0x15 => run(emu, mode::zpx,      instruction::ora),
+        0x16 => rmw(emu, mode::zpx,      instruction::asl),
+        0x17 => rmw(emu, mode::zpx,      instruction::slo),
+        0x18 => implied(emu, instruction::clc),
+        0x19 => run(emu, mode::aby::<R>, instruction::ora),
+        0x1A => run(emu, mode::imp,      instruction::nop),
+        0x1B => rmw(emu, mode::aby::<W>, instruction::slo),
+        0x1C => run(emu, mode::abx::<R>, instruction::nop),
+        0x1D => run(emu, mode::abx::<R>, instruction::ora),
+        0x1E => rmw(emu, mode::abx::<W>, instruction::asl),
+        0x1F => rmw(emu, mode::abx::<W>, instruction::slo),
+        0x20 => instruction::jsr(emu, cycle),
+        0x21 => run(emu, mode::idx,      instruction::and),
+        0x22 => implied(emu, instruction::jam),
+        0x23 => rmw(emu, mode::idx,      instruction::rla),
+        0x24 => run(emu, mode::zpg,      instruction::bit),
+        0x25 => run(emu, mode::zpg,      instruction::and),
+        0x26 => rmw(emu, mode::zpg,      instruction::rol),
+        0x27 => rmw(emu, mode::zpg,      instruction::rla),
+        0x28 => instruction::plp(emu, cycle),
+        0x29 => run(emu, mode::imm,      instruction::and),
+        0x2A => implied(emu, instruction::rol_a),
+        0x2B => run(emu, mode::imm,      instruction::anc),
+        0x2C => run(emu, mode::abs,      instruction::bit),
+        0x2D => run(emu, mode::abs,      instruction::and),
+        0x2E => rmw(emu, mode::abs,      instruction::rol),
+        0x2F => rmw(emu, mode::abs,      instruction::rla),
+        0x30 => instruction::bmi(emu, cycle),
+        0x31 => run(emu, mode::idy::<R>, instruction::and),
+        0x32 => implied(emu, instruction::jam),
+        0x33 => rmw(emu, mode::idy::<W>, instruction::rla),
+        0x34 => run(emu, mode::zpx,      instruction::nop),
+        0x35 => run(emu, mode::zpx,      instruction::and),
+        0x36 => rmw(emu, mode::zpx,      instruction::rol),
+        0x37 => rmw(emu, mode::zpx,      instruction::rla),
+        0x38 => implied(emu, instruction::sec),
+        0x39 => run(emu, mode::aby::<R>, instruction::and),
+        0x3A => run(emu, mode::imp,      instruction::nop),
+        0x3B => rmw(emu, mode::aby::<W>, instruction::rla),
+        0x3C => run(emu, mode::abx::<R>, instruction::nop),
+        0x3D => run(emu, mode::abx::<R>, instruction::and),
+        0x3E => rmw(emu, mode::abx::<W>, instruction::rol),
+        0x3F => rmw(emu, mode::abx::<W>, instruction::rla),
+        0x40 => instruction::rti(emu, cycle),
+        0x41 => run(emu, mode::idx,      instruction::eor),
+        0x42 => implied(emu, instruction::jam),
+        0x43 => rmw(emu, mode::idx,      instruction::sre),
+        0x44 => run(emu, mode::zpg,      instruction::nop),
+        0x45 => run(emu, mode::zpg,      instruction::eor),
+        0x46 => rmw(emu, mode::zpg,      instruction::lsr),
+        0x47 => rmw(emu, mode::zpg,      instruction::sre),
+        0x48 => instruction::pha(emu, cycle),
+        0x49 => run(emu, mode::imm,      instruction::eor),
+        0x4A => implied(emu, instruction::lsr_a),
+        0x4B => run(emu, mode::imm,      instruction::alr),
+        0x4C => instruction::jmp_abs(emu, cycle),
+        0x4D => run(emu, mode::abs,      instruction::eor),
+        0x4E => rmw(emu, mode::abs,      instruction::lsr),
+        0x4F => rmw(emu, mode::abs,      instruction::sre),
+        0x50 => instruction::bvc(emu, cycle),
+        0x51 => run(emu, mode::idy::<R>, instruction::eor),
+        0x52 => implied(emu, instruction::jam),
+        0x53 => rmw(emu, mode::idy::<W>, instruction::sre),
+        0x54 => run(emu, mode::zpx,      instruction::nop),
+        0x55 => run(emu, mode::zpx,      instruction::eor),
+        0x56 => rmw(emu, mode::zpx,      instruction::lsr),
+        0x57 => rmw(emu, mode::zpx,      instruction::sre),
+        0x58 => implied(emu, instruction::cli),
+        0x59 => run(emu, mode::aby::<R>, instruction::eor),
+        0x5A => run(emu, mode::imp,      instruction::nop),
+        0x5B => rmw(emu, mode::aby::<W>, instruction::sre),
+        0x5C => run(emu, mode::abx::<R>, instruction::nop),
+        0x5D => run(emu, mode::abx::<R>, instruction::eor),
+        0x5E => rmw(emu, mode::abx::<W>, instruction::lsr),
+        0x5F => rmw(emu, mode::abx::<W>, instruction::sre),
+        0x60 => instruction::rts(emu, cycle),
+        0x61 => run(emu, mode::idx,      instruction::adc),
+        0x62 => implied(emu, instruction::jam),
+        0x63 => rmw(emu, mode::idx,      instruction::rra),
+        0x64 => run(emu, mode::zpg,      instruction::nop),
+        0x65 => run(emu, mode::zpg,      instruction::adc),
+        0x66 => rmw(emu, mode::zpg,      instruction::ror),
+        0x67 => rmw(emu, mode::zpg,      instruction::rra),
+        0x68 => instruction::pla(emu, cycle),
+        0x69 => run(emu, mode::imm,      instruction::adc),
+        0x6A => implied(emu, instruction::ror_a),
+        0x6B => run(emu, mode::imm,      instruction::arr),
+        0x6C => instruction::jmp_ind(emu, cycle),
+        0x6D => run(emu, mode::abs,      instruction::adc),
+        0x6E => rmw(emu, mode::abs,      instruction::ror),
+        0x6F => rmw(emu, mode::abs,      instruction::rra),
+        0x70 => instruction::bvs(emu, cycle),
+        0x71 => run(emu, mode::idy::<R>, instruction::adc),
+        0x72 => implied(emu, instruction::jam),
+        0x73 => rmw(emu, mode::idy::<W>, instruction::rra),
+        0x74 => run(emu, mode::zpx,      instruction::nop),
+        0x75 => run(emu, mode::zpx,      instruction::adc),
+        0x76 => rmw(emu, mode::zpx,      instruction::ror),
+        0x77 => rmw(emu, mode::zpx,      instruction::rra),
+        0x78 => implied(emu, instruction::sei),
+        0x79 => run(emu, mode::aby::<R>, instruction::adc),
+        0x7A => run(emu, mode::imp,      instruction::nop),
+        0x7B => rmw(emu, mode::aby::<W>, instruction::rra),
+        0x7C => run(emu, mode::abx::<R>, instruction::nop),
+        0x7D => run(emu, mode::abx::<R>, instruction::adc),
+        0x7E => rmw(emu, mode::abx::<W>, instruction::ror),
+        0x7F => rmw(emu, mode::abx::<W>, instruction::rra),
+        0x80 => run(emu, mode::imm,      instruction::nop),
+        0x81 => run(emu, mode::idx,      instruction::sta),
+        0x82 => run(emu, mode::imm,      instruction::nop),
+        0x83 => run(emu, mode::idx,      instruction::sax),
+        0x84 => run(emu, mode::zpg,      instruction::sty),
+        0x85 => run(emu, mode::zpg,      instruction::sta),
+        0x86 => run(emu, mode::zpg,      instruction::stx),
+        0x87 => run(emu, mode::zpg,      instruction::sax),
+        0x88 => implied(emu, instruction::dey),
+        0x89 => run(emu, mode::imm,      instruction::nop),
+        0x8A => implied(emu, instruction::txa),
+        0x8B => run(emu, mode::imm,      instruction::ane),
+        0x8C => run(emu, mode::abs,      instruction::sty),
+        0x8D => run(emu, mode::abs,      instruction::sta),
+        0x8E => run(emu, mode::abs,      instruction::stx),
+        0x8F => run(emu, mode::abs,      instruction::sax),
+        0x90 => instruction::bcc(emu, cycle),
+        0x91 => run(emu, mode::idy::<W>, instruction::sta),
+        0x92 => implied(emu, instruction::jam),
+        0x93 => run(emu, mode::idy::<W>, instruction::sha),
+        0x94 => run(emu, mode::zpx,      instruction::sty),
+        0x95 => run(emu, mode::zpx,      instruction::sta),
+        0x96 => run(emu, mode::zpy,      instruction::stx),
+        0x97 => run(emu, mode::zpy,      instruction::sax),
+        0x98 => implied(emu, instruction::tya),
+        0x99 => run(emu, mode::aby::<W>, instruction::sta),
+        0x9A => implied(emu, instruction::txs),
+        0x9B => run(emu, mode::aby::<W>, instruction::tas),
+        0x9C => run(emu, mode::abx::<W>, instruction::shy),
+        0x9D => run(emu, mode::abx::<W>, instruction::sta),
+        0x9E => run(emu, mode::aby::<W>, instruction::shx),
+        0x9F => run(emu, mode::aby::<W>, instruction::sha),
+        0xA0 => run(emu, mode::imm,      instruction::ldy),
+        0xA1 => run(emu, mode::idx,      instruction::lda),
+        0xA2 => run(emu, mode::imm,      instruction::ldx),
+        0xA3 => run(emu, mode::idx,      instruction::lax),
+        0xA4 => run(emu, mode::zpg,      instruction::ldy),
+        0xA5 => run(emu, mode::zpg,      instruction::lda),
+        0xA6 => run(emu, mode::zpg,      instruction::ldx),
+        0xA7 => run(emu, mode::zpg,      instruction::lax),
+        0xA8 => implied(emu, instruction::tay),
+        0xA9 => run(emu, mode::imm,      instruction::lda),
+        0xAA => implied(emu, instruction::tax),
+        0xAB => run(emu, mode::imm,      instruction::lxa),
+        0xAC => run(emu, mode::abs,      instruction::ldy),
+        0xAD => run(emu, mode::abs,      instruction::lda),
+        0xAE => run(emu, mode::abs,      instruction::ldx),
+        0xAF => run(emu, mode::abs,      instruction::lax),
+        0xB0 => instruction::bcs(emu, cycle),
+        0xB1 => run(emu, mode::idy::<R>, instruction::lda),
+        0xB2 => implied(emu, instruction::jam),
+        0xB3 => run(emu, mode::idy::<R>, instruction::lax),
+        0xB4 => run(emu, mode::zpx,      instruction::ldy),
+        0xB5 => run(emu, mode::zpx,      instruction::lda),
+        0xB6 => run(emu, mode::zpy,      instruction::ldx),
+        0xB7 => run(emu, mode::zpy,      instruction::lax),
+        0xB8 => implied(emu, instruction::clv),
+        0xB9 => run(emu, mode::aby::<R>, instruction::lda),
+        0xBA => implied(emu, instruction::tsx),
+        0xBB => run(emu, mode::aby::<R>, instruction::las),
+        0xBC => run(emu, mode::abx::<R>, instruction::ldy),
+        0xBD => run(emu, mode::abx::<R>, instruction::lda),
+        0xBE => run(emu, mode::aby::<R>, instruction::ldx),
+        0xBF => run(emu, mode::aby::<R>, instruction::lax),
+        0xC0 => run(emu, mode::imm,      instruction::cpy),
+        0xC1 => run(emu, mode::idx,      instruction::cmp),
+        0xC2 => run(emu, mode::imm,      instruction::nop),
+        0xC3 => rmw(emu, mode::idx,      instruction::dcp),
+        0xC4 => run(emu, mode::zpg,      instruction::cpy),
+        0xC5 => run(emu, mode::zpg,      instruction::cmp),
+        0xC6 => rmw(emu, mode::zpg,      instruction::dec),
+        0xC7 => rmw(emu, mode::zpg,      instruction::dcp),
+        0xC8 => implied(emu, instruction::iny),
+        0xC9 => run(emu, mode::imm,      instruction::cmp),
+        0xCA => implied(emu, instruction::dex),
+        0xCB => run(emu, mode::imm,      instruction::sbx),
+        0xCC => run(emu, mode::abs,      instruction::cpy),
+        0xCD => run(emu, mode::abs,      instruction::cmp),
+        0xCE => rmw(emu, mode::abs,      instruction::dec),
+        0xCF => rmw(emu, mode::abs,      instruction::dcp),
+        0xD0 => instruction::bne(emu, cycle),
+        0xD1 => run(emu, mode::idy::<R>, instruction::cmp),
+        0xD2 => implied(emu, instruction::jam),
+        0xD3 => rmw(emu, mode::idy::<W>, instruction::dcp),
+        0xD4 => run(emu, mode::zpx,      instruction::nop),
+        0xD5 => run(emu, mode::zpx,      instruction::cmp),
+        0xD6 => rmw(emu, mode::zpx,      instruction::dec),
+        0xD7 => rmw(emu, mode::zpx,      instruction::dcp),
+        0xD8 => implied(emu, instruction::cld),
+        0xD9 => run(emu, mode::aby::<R>, instruction::cmp),
+        0xDA => run(emu, mode::imp,      instruction::nop),
+        0xDB => rmw(emu, mode::aby::<W>, instruction::dcp),
+        0xDC => run(emu, mode::abx::<R>, instruction::nop),
+        0xDD => run(emu, mode::abx::<R>, instruction::cmp),
+        0xDE => rmw(emu, mode::abx::<W>, instruction::dec),
+        0xDF => rmw(emu, mode::abx::<W>, instruction::dcp),
+        0xE0 => run(emu, mode::imm,      instruction::cpx),
+        0xE1 => run(emu, mode::idx,      instruction::sbc),
+        0xE2 => run(emu, mode::imm,      instruction::nop),
+        0xE3 => rmw(emu, mode::idx,      instruction::isc),
+        0xE4 => run(emu, mode::zpg,      instruction::cpx),
+        0xE5 => run(emu, mode::zpg,      instruction::sbc),
+        0xE6 => rmw(emu, mode::zpg,      instruction::inc),
+        0xE7 => rmw(emu, mode::zpg,      instruction::isc),
+        0xE8 => implied(emu, instruction::inx),
+        0xE9 => run(emu, mode::imm,      instruction::sbc),
+        0xEA => run(emu, mode::imp,      instruction::nop),
+        0xEB => run(emu, mode::imm,      instruction::sbc),
+        0xEC => run(emu, mode::abs,      instruction::cpx),
+        0xED => run(emu, mode::abs,      instruction::sbc),
+        0xEE => rmw(emu, mode::abs,      instruction::inc),
+        0xEF => rmw(emu, mode::abs,      instruction::isc),
+        0xF0 => instruction::beq(emu, cycle),
+        0xF1 => run(emu, mode::idy::<R>, instruction::sbc),
+        0xF2 => implied(emu, instruction::jam),
+        0xF3 => rmw(emu, mode::idy::<W>, instruction::isc),
+        0xF4 => run(emu, mode::zpx,      instruction::nop),
+        0xF5 => run(emu, mode::zpx,      instruction::sbc),
+        0xF6 => rmw(emu, mode::zpx,      instruction::inc),
+        0xF7 => rmw(emu, mode::zpx,      instruction::isc),
+        0xF8 => implied(emu, instruction::sed),
+        0xF9 => run(emu, mode::aby::<R>, instruction::sbc),
+        0xFA => run(emu, mode::imp,      instruction::nop),
+        0xFB => rmw(emu, mode::aby::<W>, instruction::isc),
+        0xFC => run(emu, mode::abx::<R>, instruction::nop),
+        0xFD => run(emu, mode::abx::<R>, instruction::sbc),
+        0xFE => rmw(emu, mode::abx::<W>, instruction::inc),
+        0xFF => rmw(emu, mode::abx::<W>, instruction::isc),
+    }
+}
+
+/// Runs the next cycle of an instruction that reads or writes its operand
+/// on the cycle after `mode` is done.
+fn run(emu: &mut Emu, mode: Mode, op: fn(&mut Emu)) -> bool {
+    if !operand_ready(emu, mode) {
+        return false;
+    }
+    op(emu);
+    true
+}
+
+/// Runs the next cycle of a read-modify-write instruction. The operand is
+/// read, written back unchanged while `op` modifies it, and then written
+/// again.
+fn rmw(emu: &mut Emu, mode: Mode, op: fn(&mut Emu, u8) -> u8) -> bool {
+    if !operand_ready(emu, mode) {
+        return false;
+    }
+    match emu.cpu.cycle - emu.cpu.op_start {
+        0 => {
+            emu.cpu.data = instruction::read_operand(emu);
+            false
+        }
+        1 => {
+            bus::write(emu, emu.cpu.addr, emu.cpu.data);
+            false
+        }
+        _ => {
+            let data = op(emu, emu.cpu.data);
+            bus::write(emu, emu.cpu.addr, data);
+            true
+        }
+    }
+}
+
+/// Runs an instruction that takes a single cycle after the opcode fetch.
+fn implied(emu: &mut Emu, op: fn(&mut Emu)) -> bool {
+    op(emu);
+    true
+}
+
+/// Runs the current cycle of `mode` unless it's done. Returns true if the
+/// effective address is ready and the cycle hasn't been used.
+fn operand_ready(emu: &mut Emu, mode: Mode) -> bool {
+    if emu.cpu.op_start == 0 {
+        if !mode(emu, emu.cpu.cycle) {
+            return false;
+        }
+        emu.cpu.op_start = emu.cpu.cycle;
+    }
+    true
+}
+
+/// Returns true if an instruction is partway done.
+pub fn in_instruction(emu: &Emu) -> bool {
+    emu.cpu.cycle != 0
 }
 
 pub fn peek(emu: &mut Emu, addr: u16) -> Option<u8> {
     bus::peek(emu, addr)
 }
 
+/// Writes to memory without side effects. Returns false if `addr` isn't
+/// backed by memory, e.g., it's an I/O register.
+pub fn poke(emu: &mut Emu, addr: u16, data: u8) -> bool {
+    bus::poke(emu, addr, data)
+}
+
+pub fn oam_dma(emu: &mut Emu, page: u8) {
+    bus::oam_dma(emu, page);
+}
+
+/// Sets the constant that the unstable ANE ($8B) and LXA ($AB) opcodes OR
+/// into A.
+pub fn set_magic(emu: &mut Emu, magic: u8) {
+    emu.cpu.magic = magic;
+}
+
+/// Sets whether `source` is pulling the IRQ line.
+pub fn set_irq(emu: &mut Emu, source: IrqSource, pulled: bool) {
+    if pulled {
+        emu.cpu.irq |= source as u8;
+    } else {
+        emu.cpu.irq &= !(source as u8);
+    }
+}
+
+/// Makes an NMI pending.
+pub fn set_nmi(emu: &mut Emu) {
+    emu.cpu.nmi = true;
+}
+
+/// Returns the address of the JAM opcode that locked up the CPU, if any.
+pub fn jammed_at(emu: &Emu) -> Option<u16> {
+    emu.cpu.jammed.then(|| emu.cpu.pc.wrapping_sub(1))
+}
+
+/// Returns a snapshot of the CPU. During an instruction that's partway done,
+/// the registers are as they are partway through it, e.g., PC may have moved
+/// past the opcode.
+pub fn state(emu: &Emu) -> CpuState {
+    CpuState {
+        a: emu.cpu.a,
+        x: emu.cpu.x,
+        y: emu.cpu.y,
+        pc: emu.cpu.pc,
+        s: emu.cpu.s,
+        p: emu.cpu.p.0,
+        cycles: scheduler::ticks(emu),
+        irq: emu.cpu.irq != 0,
+        nmi: emu.cpu.nmi,
+    }
+}
+
+/// Sets the CPU's registers from `state`. The cycle count and pending
+/// interrupts are ignored.
+pub fn set_state(emu: &mut Emu, state: &CpuState) {
+    assert!(
+        !in_instruction(emu),
+        "can't set the CPU's registers partway through an instruction"
+    );
+    emu.cpu.a = state.a;
+    emu.cpu.x = state.x;
+    emu.cpu.y = state.y;
+    emu.cpu.pc = state.pc;
+    emu.cpu.s = state.s;
+    emu.cpu.p = Status(state.p);
+}
+
+/// Sets a function that's called with a line in the format of nestest.log
+/// before each instruction.
+pub fn set_tracer(emu: &mut Emu, tracer: Option<Tracer>) {
+    emu.cpu.tracer = tracer;
+}
+
+/// Returns the program counter.
+pub fn pc(emu: &Emu) -> u16 {
+    emu.cpu.pc
+}
+
+/// Zeroes the CPU's internal RAM.
+pub fn clear_ram(emu: &mut Emu) {
+    bus::clear_ram(emu);
+}
+
+/// Fills the CPU's internal RAM with its power-on contents.
+pub fn init_ram(emu: &mut Emu, init: RamInit) {
+    bus::init_ram(emu, init);
+}
+
+/// Returns the CPU's internal RAM.
+pub fn ram(emu: &Emu) -> &[u8] {
+    bus::ram(emu)
+}
+
+/// Calls the subroutine at `addr` with `a` and `x` in A and X, like a JSR
+/// from `ret`. The subroutine returns to `ret`.
+pub fn call(emu: &mut Emu, addr: u16, ret: u16, a: u8, x: u8) {
+    let [low, high] = ret.wrapping_sub(1).to_le_bytes();
+    stack::push(emu, high);
+    stack::push(emu, low);
+    emu.cpu.a = a;
+    emu.cpu.x = x;
+    emu.cpu.pc = addr;
+}
+
+/// Puts the registers and the ANE and LXA magic constant back in their
+/// power-on state and drops a pending NMI. The reset sequence still has to
+/// run afterwards.
+pub fn power_on(emu: &mut Emu) {
+    emu.cpu.a = 0;
+    emu.cpu.x = 0;
+    emu.cpu.y = 0;
+    emu.cpu.s = 0x00;
+    emu.cpu.p = Status(0x34);
+    emu.cpu.magic = DEFAULT_MAGIC;
+    emu.cpu.jammed = false;
+    emu.cpu.nmi = false;
+}
+
+/// Runs the reset sequence. Only S and the I flag are changed, and RAM keeps
+/// its contents.
+///
+/// Reset is an interrupt like BRK, so it takes 7 cycles: two reads of PC,
+/// three pushes that are turned into reads, and two reads of the vector.
 pub fn reset(emu: &mut Emu) {
+    emu.cpu.jammed = false;
+    emu.cpu.interrupt = false;
+    bus::read(emu, emu.cpu.pc);
     bus::read(emu, emu.cpu.pc);
     stack::peek(emu);
     emu.cpu.s = emu.cpu.s.wrapping_sub(1);
@@ -353,6 +671,7 @@ pub fn reset(emu: &mut Emu) {
     stack::peek(emu);
     emu.cpu.s = emu.cpu.s.wrapping_sub(1);
     emu.cpu.p.set_i(true);
+    emu.cpu.irq_masked = true;
     let pcl = bus::read(emu, RESET_VECTOR);
     let pch = bus::read(emu, RESET_VECTOR + 1);
     emu.cpu.pc = pcl as u16 | (pch as u16) << 8;
@@ -360,15 +679,8 @@ pub fn reset(emu: &mut Emu) {
 
 /// Returns the byte at PC and increments PC.
 fn eat_byte(emu: &mut Emu) -> u8 {
+    cdl::log_code(emu, emu.cpu.pc);
     let data = bus::read(emu, emu.cpu.pc);
     emu.cpu.pc = emu.cpu.pc.wrapping_add(1);
     data
 }
-
-/// Returns the word at PC and increments PC twice.
-fn eat_word(emu: &mut Emu) -> u16 {
-    let low = bus::read(emu, emu.cpu.pc);
-    let high = bus::read(emu, emu.cpu.pc.wrapping_add(1));
-    emu.cpu.pc = emu.cpu.pc.wrapping_add(2);
-    low as u16 | (high as u16) << 8
-}
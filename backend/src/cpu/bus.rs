@@ -1,4 +1,12 @@
-use crate::{apu, emu::Emu, nrom, scheduler};
+use crate::{
+    apu, cart, cdl,
+    config::RamInit,
+    controller,
+    debugger::{self, Access},
+    emu::Emu,
+    ppu,
+    scheduler::{self, EventKind},
+};
 
 /// The size of the CPU's internal ram in bytes.
 const RAM_SIZE: u16 = 0x0800;
@@ -24,21 +32,43 @@ impl Bus {
 
 /// Reads the byte at address `addr`.
 pub fn read(emu: &mut Emu, addr: u16) -> u8 {
+    // DMAs can only halt the CPU on a read cycle.
+    if apu::dmc_dma_pending(emu) {
+        dmc_dma(emu, addr);
+    }
+
+    read_cycle(emu, addr)
+}
+
+/// Reads the byte at address `addr` without checking for DMAs.
+fn read_cycle(emu: &mut Emu, addr: u16) -> u8 {
     scheduler::tick(emu);
     apu::tick(emu);
 
+    // Reads from addresses that nothing responds to get the last value on
+    // the data bus, i.e., open bus.
+    let open_bus = emu.cpu.bus.data;
     let data = match addr {
         // 0x0800-0x1FFF are mirrors of 0x0000-0x07FF.
         0x0000..=0x1FFF => emu.cpu.bus.ram[(addr & 0x07FF) as usize],
-        0x2000..=0x4014 => 0,
-        0x4015 => apu::read(emu),
-        0x4016..=0x401F => 0,
-        0x6000..=0x7FFF => nrom::read_prg_ram(emu, addr),
-        0x8000..=0xFFFF => nrom::read_prg_rom(emu, addr),
-        _ => todo!(),
+        0x2000..=0x3FFF => ppu::read(emu, addr),
+        // $4015 is inside the CPU, so reading it doesn't drive the data bus.
+        // Bit 5 isn't connected and reads as open bus.
+        0x4015 => apu::read(emu) & !0x20 | open_bus & 0x20,
+        // The controller ports only drive the low five bits.
+        0x4016..=0x4017 => {
+            controller::read(emu, (addr & 1) as usize) | open_bus & 0xE0
+        }
+        0x4000..=0x401F => open_bus,
+        // The cartridge decodes the rest, including the expansion area at
+        // $4020-$5FFF. Most boards leave it unmapped.
+        0x4020..=0xFFFF => cart::read(emu, addr).unwrap_or(open_bus),
     };
     emu.cpu.bus.addr = addr;
-    emu.cpu.bus.data = data;
+    if addr != 0x4015 {
+        emu.cpu.bus.data = data;
+    }
+    debugger::check_access(emu, addr, data, Access::Read);
     data
 }
 
@@ -52,24 +82,104 @@ pub fn write(emu: &mut Emu, addr: u16, data: u8) {
     match addr {
         // 0x0800-0x1FFF are mirrors of 0x0000-0x07FF.
         0x0000..=0x1FFF => emu.cpu.bus.ram[(addr & 0x07FF) as usize] = data,
-        0x2000..=0x3FFF => (),
+        0x2000..=0x3FFF => ppu::write(emu, addr, data),
+        // The DMA starts on the next read cycle, which is the next
+        // instruction's opcode fetch.
+        0x4014 => scheduler::queue(emu, EventKind::OamDma(data), 0),
+        0x4016 => controller::write(emu, data),
         0x4000..=0x4017 => apu::write(emu, addr, data),
         0x4018..=0x401F => (),
-        0x6000..=0x7FFF => nrom::write_prg_ram(emu, addr, data),
-        0x8000..=0xFFFF => nrom::write_prg_rom(emu, addr, data),
-        _ => todo!(),
+        0x4020..=0xFFFF => cart::write(emu, addr, data),
     };
+    debugger::check_access(emu, addr, data, Access::Write);
+}
+
+/// Halts the CPU to fetch a DMC sample byte. The halted CPU keeps repeating
+/// the read it was on, so reads with side effects (e.g., $4016 and $2007)
+/// happen more than once.
+fn dmc_dma(emu: &mut Emu, addr: u16) {
+    // Halt cycle.
+    read_cycle(emu, addr);
+    // Dummy cycle.
+    read_cycle(emu, addr);
+    // Alignment cycle. The DMA can only read on a get cycle.
+    if !is_get_cycle(emu) {
+        read_cycle(emu, addr);
+    }
+    dmc_read_cycle(emu);
+}
+
+/// Reads the next DMC sample byte and hands it to the DMC.
+fn dmc_read_cycle(emu: &mut Emu) {
+    let addr = apu::dmc_addr(emu);
+    cdl::log(emu, addr, cdl::DATA | cdl::PCM);
+    let data = read_cycle(emu, addr);
+    apu::dmc_fill(emu, data);
+}
+
+/// Copies page `page` into OAM. The copy alternates between reading a byte on a
+/// get cycle and writing it to $2004 on a put cycle, so it takes 513 cycles
+/// plus one more if it has to wait for a get cycle. A DMC DMA takes over the
+/// get cycle it lands on and costs an extra put cycle to realign.
+pub fn oam_dma(emu: &mut Emu, page: u8) {
+    // The CPU is halted on its next read, the opcode fetch.
+    let addr = emu.cpu.pc;
+
+    // Halt cycle.
+    read_cycle(emu, addr);
+    // Alignment cycle.
+    if !is_get_cycle(emu) {
+        read_cycle(emu, addr);
+    }
+
+    for low in 0..=0xFF {
+        while apu::dmc_dma_pending(emu) {
+            dmc_read_cycle(emu);
+            read_cycle(emu, addr);
+        }
+
+        let data = read_cycle(emu, (page as u16) << 8 | low);
+        write(emu, 0x2004, data);
+    }
+}
+
+/// Returns true if the next cycle is a get (read) cycle. The APU alternates
+/// between get and put cycles, and DMAs can only read on get cycles.
+fn is_get_cycle(emu: &Emu) -> bool {
+    scheduler::ticks(emu) & 1 == 0
+}
+
+/// Zeroes the CPU's internal RAM.
+pub fn clear_ram(emu: &mut Emu) {
+    emu.cpu.bus.ram.fill(0);
+}
+
+/// Fills the CPU's internal RAM with its power-on contents.
+pub fn init_ram(emu: &mut Emu, init: RamInit) {
+    init.fill(&mut emu.cpu.bus.ram[..]);
+}
+
+/// Returns the CPU's internal RAM.
+pub fn ram(emu: &Emu) -> &[u8] {
+    &emu.cpu.bus.ram[..]
 }
 
 pub fn peek(emu: &mut Emu, addr: u16) -> Option<u8> {
     match addr {
         // 0x0800-0x1FFF are mirrors of 0x0000-0x07FF.
         0x0000..=0x1FFF => Some(emu.cpu.bus.ram[(addr & 0x07FF) as usize]),
-        // TODO: Do any mappers have side effects on reads? If they do, we need
-        // to call out to a peek method on the mappers that disables side
-        // effects.
-        0x6000..=0x7FFF => Some(nrom::read_prg_ram(emu, addr)),
-        0x8000..=0xFFFF => Some(nrom::read_prg_rom(emu, addr)),
+        0x4020..=0xFFFF => cart::peek(emu, addr),
         _ => None,
     }
 }
+
+pub fn poke(emu: &mut Emu, addr: u16, data: u8) -> bool {
+    match addr {
+        0x0000..=0x1FFF => {
+            emu.cpu.bus.ram[(addr & 0x07FF) as usize] = data;
+            true
+        }
+        0x4020..=0xFFFF => cart::poke(emu, addr, data),
+        _ => false,
+    }
+}
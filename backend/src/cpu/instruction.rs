@@ -1,17 +1,24 @@
 use proc_bitfield::Bit;
 
 use crate::{
-    cpu::{self, bus, stack, Status, IRQ_VECTOR},
+    cdl,
+    cpu::{self, bus, stack, Status, IRQ_VECTOR, NMI_VECTOR},
     emu::Emu,
 };
 
+/// Reads the byte at the effective address.
+pub fn read_operand(emu: &mut Emu) -> u8 {
+    cdl::log_data(emu, emu.cpu.addr);
+    bus::read(emu, emu.cpu.addr)
+}
+
 pub fn adc(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     add(emu, data);
 }
 
 pub fn alr(emu: &mut Emu) {
-    emu.cpu.a &= bus::read(emu, emu.cpu.addr);
+    emu.cpu.a &= read_operand(emu);
     let carry = emu.cpu.a.bit::<0>();
     emu.cpu.a >>= 1;
     emu.cpu.p.set_c(carry);
@@ -19,36 +26,36 @@ pub fn alr(emu: &mut Emu) {
 }
 
 pub fn anc(emu: &mut Emu) {
-    emu.cpu.a &= bus::read(emu, emu.cpu.addr);
+    emu.cpu.a &= read_operand(emu);
     emu.cpu.p.set_c(emu.cpu.a.bit::<7>());
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
 pub fn and(emu: &mut Emu) {
-    emu.cpu.a &= bus::read(emu, emu.cpu.addr);
+    emu.cpu.a &= read_operand(emu);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn ane(_: &mut Emu) {
-    todo!();
+pub fn ane(emu: &mut Emu) {
+    let data = read_operand(emu);
+    emu.cpu.a = (emu.cpu.a | emu.cpu.magic) & emu.cpu.x & data;
+    emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
 pub fn arr(emu: &mut Emu) {
-    emu.cpu.a &= bus::read(emu, emu.cpu.addr);
+    emu.cpu.a &= read_operand(emu);
     emu.cpu.a = (emu.cpu.a >> 1).set_bit::<7>(emu.cpu.p.c());
     emu.cpu.p.set_c(emu.cpu.a.bit::<6>());
     emu.cpu.p.set_v(emu.cpu.p.c() ^ emu.cpu.a.bit::<5>());
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn asl(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn asl(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<7>();
-    data <<= 1;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = data << 1;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(data);
+    data
 }
 
 pub fn asl_a(emu: &mut Emu) {
@@ -59,55 +66,79 @@ pub fn asl_a(emu: &mut Emu) {
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn bcc(emu: &mut Emu) {
-    branch(emu, !emu.cpu.p.c());
+pub fn bcc(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, !emu.cpu.p.c())
 }
 
-pub fn bcs(emu: &mut Emu) {
-    branch(emu, emu.cpu.p.c());
+pub fn bcs(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, emu.cpu.p.c())
 }
 
-pub fn beq(emu: &mut Emu) {
-    branch(emu, emu.cpu.p.z());
+pub fn beq(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, emu.cpu.p.z())
 }
 
 pub fn bit(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     let status = Status(data);
     emu.cpu.p.set_z(emu.cpu.a & data == 0);
     emu.cpu.p.set_v(status.v());
     emu.cpu.p.set_n(status.n());
 }
 
-pub fn bmi(emu: &mut Emu) {
-    branch(emu, emu.cpu.p.n());
+pub fn bmi(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, emu.cpu.p.n())
 }
 
-pub fn bne(emu: &mut Emu) {
-    branch(emu, !emu.cpu.p.z());
+pub fn bne(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, !emu.cpu.p.z())
 }
 
-pub fn bpl(emu: &mut Emu) {
-    branch(emu, !emu.cpu.p.n());
+pub fn bpl(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, !emu.cpu.p.n())
 }
 
-pub fn brk(emu: &mut Emu) {
-    cpu::eat_byte(emu);
-    stack::push(emu, (emu.cpu.pc >> 8) as u8);
-    stack::push(emu, emu.cpu.pc as u8);
-    stack::push(emu, emu.cpu.p.with_b(true).0);
-    emu.cpu.p.set_i(true);
-    let pcl = bus::read(emu, IRQ_VECTOR);
-    let pch = bus::read(emu, IRQ_VECTOR + 1);
-    emu.cpu.pc = pcl as u16 | (pch as u16) << 8;
+/// Runs BRK, or an NMI or IRQ sequence. They only differ in the B flag that's
+/// pushed and in that BRK skips the byte after its opcode.
+pub fn brk(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            if emu.cpu.interrupt {
+                bus::read(emu, emu.cpu.pc);
+            } else {
+                cpu::eat_byte(emu);
+            }
+        }
+        2 => stack::push(emu, (emu.cpu.pc >> 8) as u8),
+        3 => stack::push(emu, emu.cpu.pc as u8),
+        4 => {
+            stack::push(emu, emu.cpu.p.with_b(!emu.cpu.interrupt).0);
+            emu.cpu.p.set_i(true);
+            // An NMI that's pending by now hijacks the vector fetch, even
+            // for BRK and IRQ.
+            emu.cpu.addr = if emu.cpu.nmi {
+                emu.cpu.nmi = false;
+                NMI_VECTOR
+            } else {
+                IRQ_VECTOR
+            };
+        }
+        5 => emu.cpu.data = bus::read(emu, emu.cpu.addr),
+        _ => {
+            let pch = bus::read(emu, emu.cpu.addr + 1);
+            emu.cpu.pc = emu.cpu.data as u16 | (pch as u16) << 8;
+            return true;
+        }
+    }
+    false
 }
 
-pub fn bvc(emu: &mut Emu) {
-    branch(emu, !emu.cpu.p.v());
+pub fn bvc(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, !emu.cpu.p.v())
 }
 
-pub fn bvs(emu: &mut Emu) {
-    branch(emu, emu.cpu.p.v());
+pub fn bvs(emu: &mut Emu, cycle: u8) -> bool {
+    branch(emu, cycle, emu.cpu.p.v())
 }
 
 pub fn clc(emu: &mut Emu) {
@@ -131,34 +162,30 @@ pub fn clv(emu: &mut Emu) {
 }
 
 pub fn cmp(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     compare(emu, emu.cpu.a, data);
 }
 
 pub fn cpx(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     compare(emu, emu.cpu.x, data);
 }
 
 pub fn cpy(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     compare(emu, emu.cpu.y, data);
 }
 
-pub fn dcp(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
-    data = data.wrapping_sub(1);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn dcp(emu: &mut Emu, data: u8) -> u8 {
+    let data = data.wrapping_sub(1);
     compare(emu, emu.cpu.a, data);
+    data
 }
 
-pub fn dec(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
-    data = data.wrapping_sub(1);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn dec(emu: &mut Emu, data: u8) -> u8 {
+    let data = data.wrapping_sub(1);
     emu.cpu.p.set_z_and_n(data);
+    data
 }
 
 pub fn dex(emu: &mut Emu) {
@@ -174,16 +201,14 @@ pub fn dey(emu: &mut Emu) {
 }
 
 pub fn eor(emu: &mut Emu) {
-    emu.cpu.a ^= bus::read(emu, emu.cpu.addr);
+    emu.cpu.a ^= read_operand(emu);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn inc(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
-    data = data.wrapping_add(1);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn inc(emu: &mut Emu, data: u8) -> u8 {
+    let data = data.wrapping_add(1);
     emu.cpu.p.set_z_and_n(data);
+    data
 }
 
 pub fn inx(emu: &mut Emu) {
@@ -198,63 +223,103 @@ pub fn iny(emu: &mut Emu) {
     emu.cpu.p.set_z_and_n(emu.cpu.y);
 }
 
-pub fn isc(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
-    data = data.wrapping_add(1);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn isc(emu: &mut Emu, data: u8) -> u8 {
+    let data = data.wrapping_add(1);
     add(emu, data ^ 0xFF);
+    data
 }
 
-pub fn jmp(emu: &mut Emu) {
-    emu.cpu.pc = emu.cpu.addr;
+pub fn jmp_abs(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.data = cpu::eat_byte(emu),
+        _ => {
+            let pch = cpu::eat_byte(emu);
+            emu.cpu.pc = emu.cpu.data as u16 | (pch as u16) << 8;
+            return true;
+        }
+    }
+    false
 }
 
-pub fn jsr(emu: &mut Emu) {
-    let pcl = cpu::eat_byte(emu);
-    stack::peek(emu);
-    stack::push(emu, (emu.cpu.pc >> 8) as u8);
-    stack::push(emu, emu.cpu.pc as u8);
-    let pch = cpu::eat_byte(emu);
-    emu.cpu.pc = pcl as u16 | (pch as u16) << 8;
+pub fn jmp_ind(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        2 => emu.cpu.addr |= (cpu::eat_byte(emu) as u16) << 8,
+        3 => {
+            cdl::log(emu, emu.cpu.addr, cdl::DATA);
+            emu.cpu.data = bus::read(emu, emu.cpu.addr);
+        }
+        _ => {
+            // The pointer's high byte is read from the same page, even if the
+            // low byte is at the end of one.
+            let ptr = emu.cpu.addr & 0xFF00
+                | (emu.cpu.addr as u8).wrapping_add(1) as u16;
+            cdl::log(emu, ptr, cdl::DATA);
+            let pch = bus::read(emu, ptr);
+            emu.cpu.pc = emu.cpu.data as u16 | (pch as u16) << 8;
+            cdl::log(emu, emu.cpu.pc, cdl::INDIRECT_CODE);
+            return true;
+        }
+    }
+    false
+}
+
+pub fn jsr(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.data = cpu::eat_byte(emu),
+        2 => stack::peek(emu),
+        3 => stack::push(emu, (emu.cpu.pc >> 8) as u8),
+        4 => stack::push(emu, emu.cpu.pc as u8),
+        _ => {
+            let pch = cpu::eat_byte(emu);
+            emu.cpu.pc = emu.cpu.data as u16 | (pch as u16) << 8;
+            return true;
+        }
+    }
+    false
 }
 
 pub fn las(emu: &mut Emu) {
-    emu.cpu.a = bus::read(emu, emu.cpu.addr) & emu.cpu.s;
+    emu.cpu.a = read_operand(emu) & emu.cpu.s;
     emu.cpu.x = emu.cpu.a;
     emu.cpu.s = emu.cpu.a;
     emu.cpu.p.set_z_and_n(emu.cpu.s);
 }
 
+pub fn jam(emu: &mut Emu) {
+    // The CPU reads the next byte and then locks up. It doesn't increment PC,
+    // so PC is left pointing after the opcode.
+    bus::read(emu, emu.cpu.pc);
+    emu.cpu.jammed = true;
+}
+
 pub fn lax(emu: &mut Emu) {
-    emu.cpu.a = bus::read(emu, emu.cpu.addr);
+    emu.cpu.a = read_operand(emu);
     emu.cpu.x = emu.cpu.a;
     emu.cpu.p.set_z_and_n(emu.cpu.x);
 }
 
 pub fn lda(emu: &mut Emu) {
-    emu.cpu.a = bus::read(emu, emu.cpu.addr);
+    emu.cpu.a = read_operand(emu);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
 pub fn ldx(emu: &mut Emu) {
-    emu.cpu.x = bus::read(emu, emu.cpu.addr);
+    emu.cpu.x = read_operand(emu);
     emu.cpu.p.set_z_and_n(emu.cpu.x);
 }
 
 pub fn ldy(emu: &mut Emu) {
-    emu.cpu.y = bus::read(emu, emu.cpu.addr);
+    emu.cpu.y = read_operand(emu);
     emu.cpu.p.set_z_and_n(emu.cpu.y);
 }
 
-pub fn lsr(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn lsr(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<0>();
-    data >>= 1;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = data >> 1;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(data);
+    data
 }
 
 pub fn lsr_a(emu: &mut Emu) {
@@ -265,62 +330,93 @@ pub fn lsr_a(emu: &mut Emu) {
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn lxa(_: &mut Emu) {
-    todo!("lxa");
+pub fn lxa(emu: &mut Emu) {
+    let data = read_operand(emu);
+    emu.cpu.a = (emu.cpu.a | emu.cpu.magic) & data;
+    emu.cpu.x = emu.cpu.a;
+    emu.cpu.p.set_z_and_n(emu.cpu.x);
 }
 
 pub fn nop(emu: &mut Emu) {
+    // NOPs read their operand, but the data isn't used.
     bus::read(emu, emu.cpu.addr);
 }
 
 pub fn ora(emu: &mut Emu) {
-    emu.cpu.a |= bus::read(emu, emu.cpu.addr);
+    emu.cpu.a |= read_operand(emu);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn pha(emu: &mut Emu) {
-    bus::read(emu, emu.cpu.pc);
-    stack::push(emu, emu.cpu.a);
+pub fn pha(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            bus::read(emu, emu.cpu.pc);
+        }
+        _ => {
+            stack::push(emu, emu.cpu.a);
+            return true;
+        }
+    }
+    false
 }
 
-pub fn php(emu: &mut Emu) {
-    bus::read(emu, emu.cpu.pc);
-    stack::push(emu, emu.cpu.p.with_b(true).with_u(true).0);
+pub fn php(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            bus::read(emu, emu.cpu.pc);
+        }
+        _ => {
+            stack::push(emu, emu.cpu.p.with_b(true).with_u(true).0);
+            return true;
+        }
+    }
+    false
 }
 
-pub fn pla(emu: &mut Emu) {
-    bus::read(emu, emu.cpu.pc);
-    stack::peek(emu);
-    emu.cpu.a = stack::pop(emu);
-    emu.cpu.p.set_z_and_n(emu.cpu.a);
+pub fn pla(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            bus::read(emu, emu.cpu.pc);
+        }
+        2 => stack::peek(emu),
+        _ => {
+            emu.cpu.a = stack::pop(emu);
+            emu.cpu.p.set_z_and_n(emu.cpu.a);
+            return true;
+        }
+    }
+    false
 }
 
-pub fn plp(emu: &mut Emu) {
-    bus::read(emu, emu.cpu.pc);
-    stack::peek(emu);
-    emu.cpu.p =
-        Status(stack::pop(emu)).with_b(emu.cpu.p.b()).with_u(emu.cpu.p.u());
+pub fn plp(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            bus::read(emu, emu.cpu.pc);
+        }
+        2 => stack::peek(emu),
+        _ => {
+            pull_status(emu);
+            return true;
+        }
+    }
+    false
 }
 
-pub fn rla(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn rla(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<7>();
-    data = emu.cpu.p.c() as u8 | data << 1;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = emu.cpu.p.c() as u8 | data << 1;
     emu.cpu.a &= data;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
+    data
 }
 
-pub fn rol(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn rol(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<7>();
-    data = emu.cpu.p.c() as u8 | data << 1;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = emu.cpu.p.c() as u8 | data << 1;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(data);
+    data
 }
 
 pub fn rol_a(emu: &mut Emu) {
@@ -331,14 +427,12 @@ pub fn rol_a(emu: &mut Emu) {
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn ror(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn ror(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<0>();
-    data = data >> 1 | (emu.cpu.p.c() as u8) << 7;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = data >> 1 | (emu.cpu.p.c() as u8) << 7;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(data);
+    data
 }
 
 pub fn ror_a(emu: &mut Emu) {
@@ -349,33 +443,48 @@ pub fn ror_a(emu: &mut Emu) {
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-pub fn rra(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn rra(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<0>();
-    data = data >> 1 | (emu.cpu.p.c() as u8) << 7;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = data >> 1 | (emu.cpu.p.c() as u8) << 7;
     emu.cpu.p.set_c(carry);
     add(emu, data);
+    data
 }
 
-pub fn rti(emu: &mut Emu) {
-    bus::read(emu, emu.cpu.pc);
-    stack::peek(emu);
-    emu.cpu.p =
-        Status(stack::pop(emu)).with_b(emu.cpu.p.b()).with_u(emu.cpu.p.u());
-    let pcl = stack::pop(emu);
-    let pch = stack::pop(emu);
-    emu.cpu.pc = pcl as u16 | (pch as u16) << 8;
+pub fn rti(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            bus::read(emu, emu.cpu.pc);
+        }
+        2 => stack::peek(emu),
+        3 => pull_status(emu),
+        4 => emu.cpu.data = stack::pop(emu),
+        _ => {
+            let pch = stack::pop(emu);
+            emu.cpu.pc = emu.cpu.data as u16 | (pch as u16) << 8;
+            return true;
+        }
+    }
+    false
 }
 
-pub fn rts(emu: &mut Emu) {
-    bus::read(emu, emu.cpu.pc);
-    stack::peek(emu);
-    let pcl = stack::pop(emu);
-    let pch = stack::pop(emu);
-    emu.cpu.pc = pcl as u16 | (pch as u16) << 8;
-    cpu::eat_byte(emu);
+pub fn rts(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => {
+            bus::read(emu, emu.cpu.pc);
+        }
+        2 => stack::peek(emu),
+        3 => emu.cpu.data = stack::pop(emu),
+        4 => {
+            let pch = stack::pop(emu);
+            emu.cpu.pc = emu.cpu.data as u16 | (pch as u16) << 8;
+        }
+        _ => {
+            cpu::eat_byte(emu);
+            return true;
+        }
+    }
+    false
 }
 
 pub fn sax(emu: &mut Emu) {
@@ -383,12 +492,12 @@ pub fn sax(emu: &mut Emu) {
 }
 
 pub fn sbc(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     add(emu, data ^ 0xFF);
 }
 
 pub fn sbx(emu: &mut Emu) {
-    let data = bus::read(emu, emu.cpu.addr);
+    let data = read_operand(emu);
     let (res, carry) = (emu.cpu.a & emu.cpu.x).overflowing_sub(data);
     emu.cpu.x = res;
     emu.cpu.p.set_c(!carry);
@@ -422,26 +531,22 @@ pub fn shy(emu: &mut Emu) {
     sh_inner(emu, emu.cpu.y);
 }
 
-pub fn slo(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn slo(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<7>();
-    data <<= 1;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = data << 1;
     emu.cpu.a |= data;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
+    data
 }
 
-pub fn sre(emu: &mut Emu) {
-    let mut data = bus::read(emu, emu.cpu.addr);
-    bus::write(emu, emu.cpu.addr, data);
+pub fn sre(emu: &mut Emu, data: u8) -> u8 {
     let carry = data.bit::<0>();
-    data >>= 1;
-    bus::write(emu, emu.cpu.addr, data);
+    let data = data >> 1;
     emu.cpu.a ^= data;
     emu.cpu.p.set_c(carry);
     emu.cpu.p.set_z_and_n(emu.cpu.a);
+    data
 }
 
 pub fn sta(emu: &mut Emu) {
@@ -507,22 +612,33 @@ fn add(emu: &mut Emu, val: u8) {
     emu.cpu.p.set_z_and_n(emu.cpu.a);
 }
 
-fn branch(emu: &mut Emu, cond: bool) {
-    let offset = cpu::eat_byte(emu) as i8 as i16;
-    if cond {
-        bus::read(emu, emu.cpu.pc);
-
-        let prev_pc = emu.cpu.pc;
-        emu.cpu.pc = emu.cpu.pc.wrapping_add_signed(offset);
-
-        if prev_pc & 0xFF00 != emu.cpu.pc & 0xFF00 {
-            bus::read(
-                emu,
-                (prev_pc as u8).wrapping_add(offset as u8) as u16
-                    | (prev_pc & 0xFF00),
-            );
+fn branch(emu: &mut Emu, cycle: u8, cond: bool) -> bool {
+    match cycle {
+        1 => {
+            emu.cpu.data = cpu::eat_byte(emu);
+            return !cond;
+        }
+        2 => {
+            bus::read(emu, emu.cpu.pc);
+            let offset = emu.cpu.data as i8 as i16;
+            // Keep the old PC to fix up the high byte.
+            emu.cpu.addr = emu.cpu.pc;
+            emu.cpu.pc = emu.cpu.pc.wrapping_add_signed(offset);
+            return emu.cpu.addr & 0xFF00 == emu.cpu.pc & 0xFF00;
+        }
+        _ => {
+            // The high byte isn't fixed up until after this read.
+            bus::read(emu, emu.cpu.addr & 0xFF00 | emu.cpu.pc & 0x00FF);
         }
     }
+    true
+}
+
+/// Pulls P from the stack. The B and U flags don't exist in P, so they keep
+/// their values.
+fn pull_status(emu: &mut Emu) {
+    emu.cpu.p =
+        Status(stack::pop(emu)).with_b(emu.cpu.p.b()).with_u(emu.cpu.p.u());
 }
 
 fn compare(emu: &mut Emu, reg: u8, data: u8) {
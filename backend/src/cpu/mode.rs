@@ -1,88 +1,134 @@
+//! Addressing modes. Each one runs a cycle of its own at a time, starting
+//! with the cycle after the opcode fetch, and leaves the effective address
+//! in `addr`. A mode returns true instead of running a cycle once the address
+//! is ready, so the instruction's operation can use that cycle.
+
 use crate::{
+    cdl,
     cpu::{self, bus},
     emu::Emu,
 };
 
-pub fn abs(emu: &mut Emu) {
-    emu.cpu.addr = cpu::eat_word(emu);
-}
-
-pub fn abx<const WRITE: bool>(emu: &mut Emu) {
-    let (low, carry) = cpu::eat_byte(emu).overflowing_add(emu.cpu.x);
-    let high = cpu::eat_byte(emu);
-    emu.cpu.addr = low as u16 | (high.wrapping_add(carry as u8) as u16) << 8;
-    emu.cpu.carry = carry;
+/// Runs `cycle` of an addressing mode, or returns true if the address is
+/// ready.
+pub type Mode = fn(&mut Emu, u8) -> bool;
 
-    if WRITE || carry {
-        bus::read(emu, low as u16 | (high as u16) << 8);
+pub fn abs(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        2 => emu.cpu.addr |= (cpu::eat_byte(emu) as u16) << 8,
+        _ => return true,
     }
+    false
 }
 
-pub fn aby<const WRITE: bool>(emu: &mut Emu) {
-    let (low, carry) = cpu::eat_byte(emu).overflowing_add(emu.cpu.y);
-    let high = cpu::eat_byte(emu);
-    emu.cpu.addr = low as u16 | (high.wrapping_add(carry as u8) as u16) << 8;
-    emu.cpu.carry = carry;
+pub fn abx<const WRITE: bool>(emu: &mut Emu, cycle: u8) -> bool {
+    indexed::<WRITE>(emu, cycle, emu.cpu.x)
+}
 
-    if WRITE || carry {
-        bus::read(emu, low as u16 | (high as u16) << 8);
-    }
+pub fn aby<const WRITE: bool>(emu: &mut Emu, cycle: u8) -> bool {
+    indexed::<WRITE>(emu, cycle, emu.cpu.y)
 }
 
-pub fn imm(emu: &mut Emu) {
+pub fn imm(emu: &mut Emu, _: u8) -> bool {
+    cdl::log_immediate(emu, emu.cpu.pc);
     emu.cpu.addr = emu.cpu.pc;
     emu.cpu.pc = emu.cpu.pc.wrapping_add(1);
+    true
 }
 
 // Only used for NOP.
-pub fn imp(emu: &mut Emu) {
+pub fn imp(emu: &mut Emu, _: u8) -> bool {
     emu.cpu.addr = emu.cpu.pc;
+    true
+}
+
+pub fn idx(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        2 => {
+            bus::read(emu, emu.cpu.addr);
+            emu.cpu.addr = (emu.cpu.addr as u8).wrapping_add(emu.cpu.x) as u16;
+        }
+        3 => emu.cpu.data = bus::read(emu, emu.cpu.addr),
+        4 => {
+            let ptr = (emu.cpu.addr as u8).wrapping_add(1);
+            let high = bus::read(emu, ptr as u16);
+            emu.cpu.addr = emu.cpu.data as u16 | (high as u16) << 8;
+            cdl::set_indirect(emu);
+        }
+        _ => return true,
+    }
+    false
 }
 
-pub fn ind(emu: &mut Emu) {
-    let ptr_low = cpu::eat_byte(emu);
-    let ptr_high = cpu::eat_byte(emu);
-    let low = bus::read(emu, ptr_low as u16 | (ptr_high as u16) << 8);
-    let high = bus::read(
-        emu,
-        ptr_low.wrapping_add(1) as u16 | (ptr_high as u16) << 8,
-    );
-    emu.cpu.addr = low as u16 | (high as u16) << 8;
+pub fn idy<const WRITE: bool>(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        2 => {
+            let (low, carry) =
+                bus::read(emu, emu.cpu.addr).overflowing_add(emu.cpu.y);
+            emu.cpu.data = low;
+            emu.cpu.carry = carry;
+        }
+        3 => {
+            let ptr = (emu.cpu.addr as u8).wrapping_add(1);
+            let high = bus::read(emu, ptr as u16);
+            emu.cpu.addr = emu.cpu.data as u16 | (high as u16) << 8;
+            cdl::set_indirect(emu);
+        }
+        4 if WRITE || emu.cpu.carry => fix_page(emu),
+        _ => return true,
+    }
+    false
 }
 
-pub fn idx(emu: &mut Emu) {
-    let ptr = cpu::eat_byte(emu);
-    bus::read(emu, ptr as u16);
-    let ptr = ptr.wrapping_add(emu.cpu.x);
-    let low = bus::read(emu, ptr as u16);
-    let high = bus::read(emu, ptr.wrapping_add(1) as u16);
-    emu.cpu.addr = low as u16 | (high as u16) << 8;
+pub fn zpg(emu: &mut Emu, cycle: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        _ => return true,
+    }
+    false
 }
 
-pub fn idy<const WRITE: bool>(emu: &mut Emu) {
-    let ptr = cpu::eat_byte(emu);
-    let (low, carry) = bus::read(emu, ptr as u16).overflowing_add(emu.cpu.y);
-    let high = bus::read(emu, ptr.wrapping_add(1) as u16);
-    emu.cpu.addr = low as u16 | (high.wrapping_add(carry as u8) as u16) << 8;
-    emu.cpu.carry = carry;
+pub fn zpx(emu: &mut Emu, cycle: u8) -> bool {
+    zero_page_indexed(emu, cycle, emu.cpu.x)
+}
 
-    if WRITE || carry {
-        bus::read(emu, low as u16 | (high as u16) << 8);
-    }
+pub fn zpy(emu: &mut Emu, cycle: u8) -> bool {
+    zero_page_indexed(emu, cycle, emu.cpu.y)
 }
 
-pub fn zpg(emu: &mut Emu) {
-    emu.cpu.addr = cpu::eat_byte(emu) as u16;
+fn indexed<const WRITE: bool>(emu: &mut Emu, cycle: u8, index: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        2 => {
+            let (low, carry) = (emu.cpu.addr as u8).overflowing_add(index);
+            let high = cpu::eat_byte(emu);
+            emu.cpu.addr = low as u16 | (high as u16) << 8;
+            emu.cpu.carry = carry;
+        }
+        3 if WRITE || emu.cpu.carry => fix_page(emu),
+        _ => return true,
+    }
+    false
 }
 
-pub fn zpx(emu: &mut Emu) {
-    let addr = cpu::eat_byte(emu);
-    bus::read(emu, addr as u16);
-    emu.cpu.addr = addr.wrapping_add(emu.cpu.x) as u16;
+/// Reads from the address before the carry from the low byte is added to
+/// the high byte, and then adds it.
+fn fix_page(emu: &mut Emu) {
+    bus::read(emu, emu.cpu.addr);
+    emu.cpu.addr = emu.cpu.addr.wrapping_add((emu.cpu.carry as u16) << 8);
 }
 
-pub fn zpy(emu: &mut Emu) {
-    let addr = cpu::eat_byte(emu);
-    bus::read(emu, addr as u16);
-    emu.cpu.addr = addr.wrapping_add(emu.cpu.y) as u16;
+fn zero_page_indexed(emu: &mut Emu, cycle: u8, index: u8) -> bool {
+    match cycle {
+        1 => emu.cpu.addr = cpu::eat_byte(emu) as u16,
+        2 => {
+            bus::read(emu, emu.cpu.addr);
+            emu.cpu.addr = (emu.cpu.addr as u8).wrapping_add(index) as u16;
+        }
+        _ => return true,
+    }
+    false
 }
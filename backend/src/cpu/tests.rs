@@ -1,14 +1,48 @@
 mod bus;
+mod interrupt;
+mod jam;
 mod klaus;
 mod processor;
 
-use crate::{apu::Apu, cpu::Cpu, nrom::Nrom, scheduler::Scheduler, Emu};
+use crate::{
+    apu::Apu,
+    cart::Cart,
+    config::Config,
+    controller::Controllers,
+    cpu::{self, Cpu},
+    debugger::Debugger,
+    nrom::{Mirroring, Nrom},
+    ppu::Ppu,
+    region::Region,
+    scheduler::Scheduler,
+    symbols::Symbols,
+    Emu,
+};
 
 fn make_emu() -> Emu {
     Emu {
         cpu: Cpu::new(),
-        nrom: Nrom { prg_ram: Box::new([]), prg_rom: Box::new([]) },
+        cart: Cart::Nrom(Nrom {
+            prg_ram: Box::new([]),
+            prg_rom: Box::new([]),
+            chr_rom: Box::new([]),
+            chr_ram: Box::new([]),
+            mirroring: Mirroring::Horizontal,
+            region: Region::Ntsc,
+        }),
         scheduler: Scheduler::new(),
         apu: Apu::new(),
+        ppu: Ppu::new(),
+        controllers: Controllers::new(),
+        debugger: Debugger::new(),
+        cdl: None,
+        symbols: Symbols::new(),
+        config: Config::default(),
     }
 }
+
+/// Runs the rest of the current instruction, or the next one if none is
+/// partway done.
+fn step(emu: &mut Emu) {
+    while !cpu::step_cycle(emu) {}
+}
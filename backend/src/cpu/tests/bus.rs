@@ -1,6 +1,6 @@
 #![cfg_attr(test, allow(dead_code))]
 
-use crate::emu::Emu;
+use crate::{config::RamInit, emu::Emu};
 
 /// The size of the CPU's address space in bytes.
 const ADDR_SPACE_SIZE: u32 = 0x10000;
@@ -34,3 +34,23 @@ pub fn write(emu: &mut Emu, addr: u16, data: u8) {
 pub fn peek(_: &mut Emu, _: u16) -> Option<u8> {
     unimplemented!("CPU tests don't use peek")
 }
+
+pub fn poke(_: &mut Emu, _: u16, _: u8) -> bool {
+    unimplemented!("CPU tests don't use poke")
+}
+
+pub fn oam_dma(_: &mut Emu, _: u8) {
+    unimplemented!("CPU tests don't use OAM DMA")
+}
+
+pub fn clear_ram(_: &mut Emu) {
+    unimplemented!("CPU tests don't use clear_ram")
+}
+
+pub fn init_ram(_: &mut Emu, _: RamInit) {
+    unimplemented!("CPU tests don't use init_ram")
+}
+
+pub fn ram(_: &Emu) -> &[u8] {
+    unimplemented!("CPU tests don't use ram")
+}
@@ -0,0 +1,86 @@
+use crate::{
+    cpu::{self, tests, IrqSource, Status},
+    Emu,
+};
+
+/// Makes an emulator that runs `code` at $0200 with `p` in P, and with the
+/// NMI handler at $0300 and the IRQ handler at $0400.
+fn make_emu(code: &[u8], p: u8) -> Emu {
+    let mut emu = tests::make_emu();
+    emu.cpu.pc = 0x0200;
+    emu.cpu.s = 0xFD;
+    emu.cpu.p = Status(p);
+    emu.cpu.bus.ram[0x0200..0x0200 + code.len()].copy_from_slice(code);
+    emu.cpu.bus.ram[0xFFFA..0xFFFC].copy_from_slice(&[0x00, 0x03]);
+    emu.cpu.bus.ram[0xFFFE..].copy_from_slice(&[0x00, 0x04]);
+    emu
+}
+
+#[test]
+fn irq_sequence() {
+    let mut emu = make_emu(&[0xEA, 0xEA], 0x20);
+    cpu::set_irq(&mut emu, IrqSource::Apu, true);
+    tests::step(&mut emu);
+    assert_eq!(emu.cpu.pc, 0x0201);
+
+    emu.cpu.bus.cycles.clear();
+    tests::step(&mut emu);
+    assert_eq!(
+        emu.cpu.bus.cycles,
+        [
+            (0x0201, 0xEA, "read"),
+            (0x0201, 0xEA, "read"),
+            (0x01FD, 0x02, "write"),
+            (0x01FC, 0x01, "write"),
+            // B is clear.
+            (0x01FB, 0x20, "write"),
+            (0xFFFE, 0x00, "read"),
+            (0xFFFF, 0x04, "read"),
+        ]
+    );
+    assert_eq!(emu.cpu.pc, 0x0400);
+    assert!(emu.cpu.p.i());
+
+    // The I flag masks it now.
+    emu.cpu.bus.ram[0x0400] = 0xEA;
+    tests::step(&mut emu);
+    assert_eq!(emu.cpu.pc, 0x0401);
+}
+
+#[test]
+fn cli_latency() {
+    let mut emu = make_emu(&[0x58, 0xEA, 0xEA], 0x24);
+    cpu::set_irq(&mut emu, IrqSource::Apu, true);
+    tests::step(&mut emu);
+    // CLI clears I on its last cycle, after IRQs were polled, so the next
+    // instruction runs first.
+    tests::step(&mut emu);
+    assert_eq!(emu.cpu.pc, 0x0202);
+    tests::step(&mut emu);
+    assert_eq!(emu.cpu.pc, 0x0400);
+}
+
+#[test]
+fn nmi_sequence() {
+    let mut emu = make_emu(&[0xEA], 0x24);
+    cpu::set_nmi(&mut emu);
+    tests::step(&mut emu);
+    assert_eq!(emu.cpu.pc, 0x0300);
+    assert!(!emu.cpu.nmi);
+    assert_eq!(emu.cpu.bus.ram[0x01FB], 0x24);
+}
+
+#[test]
+fn nmi_hijacks_brk() {
+    let mut emu = make_emu(&[0x00, 0x00], 0x20);
+    for _ in 0..4 {
+        cpu::step_cycle(&mut emu);
+    }
+    cpu::set_nmi(&mut emu);
+    tests::step(&mut emu);
+    assert_eq!(emu.cpu.pc, 0x0300);
+    assert!(!emu.cpu.nmi);
+    // It still pushes BRK's return address and B flag.
+    assert_eq!(emu.cpu.bus.ram[0x01FC], 0x02);
+    assert_eq!(emu.cpu.bus.ram[0x01FB], 0x30);
+}
@@ -0,0 +1,25 @@
+use crate::cpu::{self, tests};
+
+#[test]
+fn jam_locks_the_cpu_until_reset() {
+    let mut emu = tests::make_emu();
+    emu.cpu.pc = 0x0200;
+    emu.cpu.bus.ram[0x0200] = 0x02;
+    emu.cpu.bus.ram[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x03]);
+
+    tests::step(&mut emu);
+    assert_eq!(cpu::jammed_at(&emu), Some(0x0200));
+
+    emu.cpu.bus.cycles.clear();
+    tests::step(&mut emu);
+    tests::step(&mut emu);
+    assert_eq!(
+        emu.cpu.bus.cycles,
+        [(0xFFFF, 0x00, "read"), (0xFFFF, 0x00, "read")]
+    );
+    assert_eq!(cpu::jammed_at(&emu), Some(0x0200));
+
+    cpu::reset(&mut emu);
+    assert_eq!(cpu::jammed_at(&emu), None);
+    assert_eq!(emu.cpu.pc, 0x0300);
+}
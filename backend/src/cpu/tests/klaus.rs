@@ -1,6 +1,6 @@
 use std::fs;
 
-use crate::{cpu, cpu::tests};
+use crate::cpu::tests;
 
 const ZERO_PAGE_ADDR: u16 = 0x000A;
 const CODE_ADDR: u16 = 0x0400;
@@ -12,12 +12,12 @@ fn functional() {
     let mut emu = tests::make_emu();
     emu.cpu.bus.ram[(ZERO_PAGE_ADDR as usize)..].copy_from_slice(&rom);
 
-    cpu::step(&mut emu);
+    tests::step(&mut emu);
     emu.cpu.pc = CODE_ADDR;
     let mut prev_pc = emu.cpu.pc;
 
     loop {
-        cpu::step(&mut emu);
+        tests::step(&mut emu);
 
         if prev_pc == emu.cpu.pc {
             if emu.cpu.pc == SUCCESS_ADDR {
@@ -2,7 +2,7 @@ use std::{fs, ptr};
 
 use serde::Deserialize;
 
-use crate::cpu::{self, tests, Status};
+use crate::cpu::{tests, Status};
 
 macro_rules! processor_test {
     ($name:ident, $opc:expr) => {
@@ -61,7 +61,7 @@ fn run(opc: u8) {
         }
         emu.cpu.bus.cycles.clear();
 
-        cpu::step(&mut emu);
+        tests::step(&mut emu);
 
         assert_eq!(emu.cpu.a, test.end.a);
         assert_eq!(emu.cpu.x, test.end.x);
@@ -76,7 +76,8 @@ fn run(opc: u8) {
     }
 }
 
-// JAM, ANE (8B), and LXA (AB) are excluded.
+// JAM is excluded. The tests use 0xEE as the ANE (8B) and LXA (AB) magic
+// constant, which is the default.
 processor_test!(opc_00, 0x00);
 processor_test!(opc_01, 0x01);
 processor_test!(opc_03, 0x03);
@@ -208,6 +209,7 @@ processor_test!(opc_87, 0x87);
 processor_test!(opc_88, 0x88);
 processor_test!(opc_89, 0x89);
 processor_test!(opc_8a, 0x8A);
+processor_test!(opc_8b, 0x8B);
 processor_test!(opc_8c, 0x8C);
 processor_test!(opc_8d, 0x8D);
 processor_test!(opc_8e, 0x8E);
@@ -238,6 +240,7 @@ processor_test!(opc_a7, 0xA7);
 processor_test!(opc_a8, 0xA8);
 processor_test!(opc_a9, 0xA9);
 processor_test!(opc_aa, 0xAA);
+processor_test!(opc_ab, 0xAB);
 processor_test!(opc_ac, 0xAC);
 processor_test!(opc_ad, 0xAD);
 processor_test!(opc_ae, 0xAE);
@@ -0,0 +1,38 @@
+use crate::{disasm, emu::Emu, ppu, scheduler};
+
+/// Passes a trace line for the instruction at PC to the tracer.
+pub fn log(emu: &mut Emu) {
+    let line = line(emu);
+    if let Some(tracer) = &mut emu.cpu.tracer {
+        tracer(&line);
+    }
+}
+
+/// Returns a line in the format of nestest.log for the instruction at PC,
+/// e.g.,
+///
+/// ```text
+/// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+/// ```
+pub fn line(emu: &mut Emu) -> String {
+    let disassembly = disasm::disassemble(emu, emu.cpu.pc);
+    let hex: Vec<String> =
+        disassembly.bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+
+    let cycles = scheduler::ticks(emu);
+    let (_, scanline, dot) = ppu::position(emu);
+
+    format!(
+        "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} \
+         PPU:{scanline:>3},{dot:>3} CYC:{cycles}",
+        disassembly.addr,
+        hex.join(" "),
+        if disassembly.instruction.official { ' ' } else { '*' },
+        disassembly.to_string(),
+        emu.cpu.a,
+        emu.cpu.x,
+        emu.cpu.y,
+        emu.cpu.p.0,
+        emu.cpu.s,
+    )
+}
@@ -0,0 +1,290 @@
+use std::ops::RangeInclusive;
+
+use crate::{cpu, emu::Status, ppu, scheduler, Emu};
+
+/// The opcode of JSR.
+const JSR: u8 = 0x20;
+/// The opcode of RTI.
+const RTI: u8 = 0x40;
+/// The opcode of RTS.
+const RTS: u8 = 0x60;
+
+/// A kind of memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// An opcode fetch at the start of an instruction.
+    Execute,
+}
+
+/// Breaks on accesses to a range of addresses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// Only break if this value is read or written (or executed, for
+    /// opcodes).
+    pub value: Option<u8>,
+}
+
+impl Watchpoint {
+    fn matches(&self, addr: u16, data: u8, access: Access) -> bool {
+        let watched = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        };
+        watched
+            && self.range.contains(&addr)
+            && self.value.is_none_or(|value| value == data)
+    }
+}
+
+/// Why a debugger run stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakReason {
+    /// PC reached a breakpoint. The instruction there hasn't run.
+    Breakpoint(u16),
+    /// The watchpoint at `index` was hit. Read and write watchpoints break
+    /// after the instruction that hit them.
+    Watchpoint { index: usize, addr: u16, data: u8, access: Access },
+    /// The step finished.
+    Step,
+    /// A new frame started.
+    Frame,
+    /// The scanline was reached.
+    Scanline,
+    /// The CPU jammed.
+    Jammed,
+    /// The run used up its cycles.
+    CycleLimit,
+}
+
+pub struct Debugger {
+    breakpoints: Vec<u16>,
+    watchpoints: Vec<Watchpoint>,
+    /// True if there are read or write watchpoints. This is the only thing
+    /// the bus checks when the debugger isn't used.
+    watching: bool,
+    /// The first watchpoint hit since the last instruction.
+    hit: Option<BreakReason>,
+    /// The PC that the last run stopped on because of a breakpoint or an
+    /// execute watchpoint.
+    stopped_at: Option<u16>,
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            watching: false,
+            hit: None,
+            stopped_at: None,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        if !self.breakpoints.contains(&addr) {
+            self.breakpoints.push(addr);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.retain(|&breakpoint| breakpoint != addr);
+    }
+
+    pub fn breakpoints(&self) -> &[u16] {
+        &self.breakpoints
+    }
+
+    /// Adds a watchpoint and returns its index.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        self.watchpoints.push(watchpoint);
+        self.update_watching();
+        self.watchpoints.len() - 1
+    }
+
+    /// Removes the watchpoint at `index`. The indices of the watchpoints after
+    /// it shift down by one.
+    pub fn remove_watchpoint(&mut self, index: usize) -> Watchpoint {
+        let watchpoint = self.watchpoints.remove(index);
+        self.update_watching();
+        watchpoint
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Removes all breakpoints and watchpoints.
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.update_watching();
+    }
+
+    fn update_watching(&mut self) {
+        self.watching = self.watchpoints.iter().any(|wp| wp.read || wp.write);
+    }
+}
+
+/// Checks a bus access against the watchpoints.
+#[inline]
+pub fn check_access(emu: &mut Emu, addr: u16, data: u8, access: Access) {
+    if emu.debugger.watching {
+        check_watchpoints(emu, addr, data, access);
+    }
+}
+
+#[cold]
+fn check_watchpoints(emu: &mut Emu, addr: u16, data: u8, access: Access) {
+    if emu.debugger.hit.is_some() {
+        return;
+    }
+    if let Some(index) = emu
+        .debugger
+        .watchpoints
+        .iter()
+        .position(|wp| wp.matches(addr, data, access))
+    {
+        emu.debugger.hit =
+            Some(BreakReason::Watchpoint { index, addr, data, access });
+    }
+}
+
+/// Returns why the instruction at PC shouldn't run, if there's a reason.
+fn check_execute(emu: &mut Emu) -> Option<BreakReason> {
+    let pc = cpu::state(emu).pc;
+    if emu.debugger.breakpoints.contains(&pc) {
+        return Some(BreakReason::Breakpoint(pc));
+    }
+
+    let opc = cpu::peek(emu, pc).unwrap_or(0);
+    emu.debugger
+        .watchpoints
+        .iter()
+        .position(|wp| wp.matches(pc, opc, Access::Execute))
+        .map(|index| BreakReason::Watchpoint {
+            index,
+            addr: pc,
+            data: opc,
+            access: Access::Execute,
+        })
+}
+
+/// Runs instructions until `done` returns a reason to stop, a breakpoint or
+/// watchpoint is hit, the CPU jams, or `max_cycles` have run. If the last
+/// run stopped on a breakpoint at PC, it isn't checked again so that a run
+/// can continue from one. Other runs, e.g., one frame after another, check
+/// the first instruction too.
+fn run(
+    emu: &mut Emu,
+    max_cycles: u64,
+    mut done: impl FnMut(&mut Emu) -> Option<BreakReason>,
+) -> BreakReason {
+    let start = scheduler::ticks(emu);
+    emu.debugger.hit = None;
+    let mut resuming =
+        emu.debugger.stopped_at.take() == Some(cpu::state(emu).pc);
+    loop {
+        if !resuming {
+            if let Some(reason) = check_execute(emu) {
+                emu.debugger.stopped_at = Some(cpu::state(emu).pc);
+                return reason;
+            }
+        }
+        resuming = false;
+
+        emu.step();
+        if let Some(reason) = emu.debugger.hit.take() {
+            return reason;
+        }
+        if let Status::Jammed { .. } = emu.status() {
+            return BreakReason::Jammed;
+        }
+        if let Some(reason) = done(emu) {
+            return reason;
+        }
+        if scheduler::ticks(emu) - start >= max_cycles {
+            return BreakReason::CycleLimit;
+        }
+    }
+}
+
+pub fn run_until_break(emu: &mut Emu, max_cycles: u64) -> BreakReason {
+    run(emu, max_cycles, |_| None)
+}
+
+pub fn step_into(emu: &mut Emu) -> BreakReason {
+    run(emu, u64::MAX, |_| Some(BreakReason::Step))
+}
+
+/// Steps over JSRs, i.e., runs until the subroutine returns.
+pub fn step_over(emu: &mut Emu) -> BreakReason {
+    let state = cpu::state(emu);
+    if cpu::peek(emu, state.pc) != Some(JSR) {
+        return step_into(emu);
+    }
+
+    let ret = state.pc.wrapping_add(3);
+    run(emu, u64::MAX, |emu| {
+        let now = cpu::state(emu);
+        (now.pc == ret && now.s == state.s).then_some(BreakReason::Step)
+    })
+}
+
+/// Runs until the current subroutine returns.
+pub fn step_out(emu: &mut Emu) -> BreakReason {
+    let s = cpu::state(emu).s;
+    let mut returning = returns_from(emu, s);
+    run(emu, u64::MAX, |emu| {
+        if returning {
+            return Some(BreakReason::Step);
+        }
+        returning = returns_from(emu, s);
+        None
+    })
+}
+
+/// Returns true if the instruction at PC returns from the subroutine whose
+/// stack frame starts at `s`.
+fn returns_from(emu: &mut Emu, s: u8) -> bool {
+    let state = cpu::state(emu);
+    let opc = cpu::peek(emu, state.pc);
+    (opc == Some(RTS) || opc == Some(RTI)) && state.s >= s
+}
+
+/// Runs until the next frame starts.
+pub fn run_to_frame(emu: &mut Emu) -> BreakReason {
+    let (frame, _, _) = ppu::position(emu);
+    run(emu, u64::MAX, |emu| {
+        (ppu::position(emu).0 != frame).then_some(BreakReason::Frame)
+    })
+}
+
+/// Runs until `scanline` starts. Every scanline starts within a frame and a
+/// scanline, so the run stops there with `CycleLimit` if `scanline` is past
+/// the end of the frame.
+pub fn run_to_scanline(emu: &mut Emu, scanline: u16) -> BreakReason {
+    let region = scheduler::region(emu);
+    let dots = (region.scanlines() as u64 + 1) * ppu::DOTS_PER_SCANLINE;
+    let max_cycles =
+        (dots * region.ppu_divider()).div_ceil(region.cpu_divider());
+    let mut prev = ppu::position(emu).1;
+    run(emu, max_cycles, |emu| {
+        let (_, now, _) = ppu::position(emu);
+        let reached = now == scanline && prev != scanline;
+        prev = now;
+        reached.then_some(BreakReason::Scanline)
+    })
+}
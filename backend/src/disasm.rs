@@ -0,0 +1,532 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use crate::{cpu, emu::Emu, symbols};
+
+/// An addressing mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Implied.
+    Imp,
+    /// Accumulator.
+    Acc,
+    /// Immediate.
+    Imm,
+    /// Zero page.
+    Zpg,
+    /// Zero page indexed by X.
+    Zpx,
+    /// Zero page indexed by Y.
+    Zpy,
+    /// Absolute.
+    Abs,
+    /// Absolute indexed by X.
+    Abx,
+    /// Absolute indexed by Y.
+    Aby,
+    /// Indirect (only used by JMP).
+    Ind,
+    /// Indexed indirect, i.e., (zp,X).
+    Idx,
+    /// Indirect indexed, i.e., (zp),Y.
+    Idy,
+    /// Relative (only used by branches).
+    Rel,
+}
+
+impl Mode {
+    /// Returns the size in bytes of an instruction with this mode, including
+    /// the opcode.
+    pub fn size(self) -> u16 {
+        match self {
+            Mode::Imp | Mode::Acc => 1,
+            Mode::Imm
+            | Mode::Zpg
+            | Mode::Zpx
+            | Mode::Zpy
+            | Mode::Idx
+            | Mode::Idy
+            | Mode::Rel => 2,
+            Mode::Abs | Mode::Abx | Mode::Aby | Mode::Ind => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// The mnemonic in uppercase. Unofficial opcodes use the names from
+    /// nestest.log (e.g., ISB instead of ISC).
+    pub mnemonic: &'static str,
+    pub mode: Mode,
+    /// False for unofficial opcodes.
+    pub official: bool,
+}
+
+/// Decodes `opc`.
+pub fn decode(opc: u8) -> Instruction {
+    use Mode::*;
+
+    #[rustfmt::skip]
+    let (mnemonic, mode) = match opc {
+        0x00 => ("BRK", Imp),
+        0x01 => ("ORA", Idx),
+        0x02 => ("JAM", Imp),
+        0x03 => ("SLO", Idx),
+        0x04 => ("NOP", Zpg),
+        0x05 => ("ORA", Zpg),
+        0x06 => ("ASL", Zpg),
+        0x07 => ("SLO", Zpg),
+        0x08 => ("PHP", Imp),
+        0x09 => ("ORA", Imm),
+        0x0A => ("ASL", Acc),
+        0x0B => ("ANC", Imm),
+        0x0C => ("NOP", Abs),
+        0x0D => ("ORA", Abs),
+        0x0E => ("ASL", Abs),
+        0x0F => ("SLO", Abs),
+        0x10 => ("BPL", Rel),
+        0x11 => ("ORA", Idy),
+        0x12 => ("JAM", Imp),
+        0x13 => ("SLO", Idy),
+        0x14 => ("NOP", Zpx),
+        0x15 => ("ORA", Zpx),
+        0x16 => ("ASL", Zpx),
+        0x17 => ("SLO", Zpx),
+        0x18 => ("CLC", Imp),
+        0x19 => ("ORA", Aby),
+        0x1A => ("NOP", Imp),
+        0x1B => ("SLO", Aby),
+        0x1C => ("NOP", Abx),
+        0x1D => ("ORA", Abx),
+        0x1E => ("ASL", Abx),
+        0x1F => ("SLO", Abx),
+        0x20 => ("JSR", Abs),
+        0x21 => ("AND", Idx),
+        0x22 => ("JAM", Imp),
+        0x23 => ("RLA", Idx),
+        0x24 => ("BIT", Zpg),
+        0x25 => ("AND", Zpg),
+        0x26 => ("ROL", Zpg),
+        0x27 => ("RLA", Zpg),
+        0x28 => ("PLP", Imp),
+        0x29 => ("AND", Imm),
+        0x2A => ("ROL", Acc),
+        0x2B => ("ANC", Imm),
+        0x2C => ("BIT", Abs),
+        0x2D => ("AND", Abs),
+        0x2E => ("ROL", Abs),
+        0x2F => ("RLA", Abs),
+        0x30 => ("BMI", Rel),
+        0x31 => ("AND", Idy),
+        0x32 => ("JAM", Imp),
+        0x33 => ("RLA", Idy),
+        0x34 => ("NOP", Zpx),
+        0x35 => ("AND", Zpx),
+        0x36 => ("ROL", Zpx),
+        0x37 => ("RLA", Zpx),
+        0x38 => ("SEC", Imp),
+        0x39 => ("AND", Aby),
+        0x3A => ("NOP", Imp),
+        0x3B => ("RLA", Aby),
+        0x3C => ("NOP", Abx),
+        0x3D => ("AND", Abx),
+        0x3E => ("ROL", Abx),
+        0x3F => ("RLA", Abx),
+        0x40 => ("RTI", Imp),
+        0x41 => ("EOR", Idx),
+        0x42 => ("JAM", Imp),
+        0x43 => ("SRE", Idx),
+        0x44 => ("NOP", Zpg),
+        0x45 => ("EOR", Zpg),
+        0x46 => ("LSR", Zpg),
+        0x47 => ("SRE", Zpg),
+        0x48 => ("PHA", Imp),
+        0x49 => ("EOR", Imm),
+        0x4A => ("LSR", Acc),
+        0x4B => ("ALR", Imm),
+        0x4C => ("JMP", Abs),
+        0x4D => ("EOR", Abs),
+        0x4E => ("LSR", Abs),
+        0x4F => ("SRE", Abs),
+        0x50 => ("BVC", Rel),
+        0x51 => ("EOR", Idy),
+        0x52 => ("JAM", Imp),
+        0x53 => ("SRE", Idy),
+        0x54 => ("NOP", Zpx),
+        0x55 => ("EOR", Zpx),
+        0x56 => ("LSR", Zpx),
+        0x57 => ("SRE", Zpx),
+        0x58 => ("CLI", Imp),
+        0x59 => ("EOR", Aby),
+        0x5A => ("NOP", Imp),
+        0x5B => ("SRE", Aby),
+        0x5C => ("NOP", Abx),
+        0x5D => ("EOR", Abx),
+        0x5E => ("LSR", Abx),
+        0x5F => ("SRE", Abx),
+        0x60 => ("RTS", Imp),
+        0x61 => ("ADC", Idx),
+        0x62 => ("JAM", Imp),
+        0x63 => ("RRA", Idx),
+        0x64 => ("NOP", Zpg),
+        0x65 => ("ADC", Zpg),
+        0x66 => ("ROR", Zpg),
+        0x67 => ("RRA", Zpg),
+        0x68 => ("PLA", Imp),
+        0x69 => ("ADC", Imm),
+        0x6A => ("ROR", Acc),
+        0x6B => ("ARR", Imm),
+        0x6C => ("JMP", Ind),
+        0x6D => ("ADC", Abs),
+        0x6E => ("ROR", Abs),
+        0x6F => ("RRA", Abs),
+        0x70 => ("BVS", Rel),
+        0x71 => ("ADC", Idy),
+        0x72 => ("JAM", Imp),
+        0x73 => ("RRA", Idy),
+        0x74 => ("NOP", Zpx),
+        0x75 => ("ADC", Zpx),
+        0x76 => ("ROR", Zpx),
+        0x77 => ("RRA", Zpx),
+        0x78 => ("SEI", Imp),
+        0x79 => ("ADC", Aby),
+        0x7A => ("NOP", Imp),
+        0x7B => ("RRA", Aby),
+        0x7C => ("NOP", Abx),
+        0x7D => ("ADC", Abx),
+        0x7E => ("ROR", Abx),
+        0x7F => ("RRA", Abx),
+        0x80 => ("NOP", Imm),
+        0x81 => ("STA", Idx),
+        0x82 => ("NOP", Imm),
+        0x83 => ("SAX", Idx),
+        0x84 => ("STY", Zpg),
+        0x85 => ("STA", Zpg),
+        0x86 => ("STX", Zpg),
+        0x87 => ("SAX", Zpg),
+        0x88 => ("DEY", Imp),
+        0x89 => ("NOP", Imm),
+        0x8A => ("TXA", Imp),
+        0x8B => ("ANE", Imm),
+        0x8C => ("STY", Abs),
+        0x8D => ("STA", Abs),
+        0x8E => ("STX", Abs),
+        0x8F => ("SAX", Abs),
+        0x90 => ("BCC", Rel),
+        0x91 => ("STA", Idy),
+        0x92 => ("JAM", Imp),
+        0x93 => ("SHA", Idy),
+        0x94 => ("STY", Zpx),
+        0x95 => ("STA", Zpx),
+        0x96 => ("STX", Zpy),
+        0x97 => ("SAX", Zpy),
+        0x98 => ("TYA", Imp),
+        0x99 => ("STA", Aby),
+        0x9A => ("TXS", Imp),
+        0x9B => ("TAS", Aby),
+        0x9C => ("SHY", Abx),
+        0x9D => ("STA", Abx),
+        0x9E => ("SHX", Aby),
+        0x9F => ("SHA", Aby),
+        0xA0 => ("LDY", Imm),
+        0xA1 => ("LDA", Idx),
+        0xA2 => ("LDX", Imm),
+        0xA3 => ("LAX", Idx),
+        0xA4 => ("LDY", Zpg),
+        0xA5 => ("LDA", Zpg),
+        0xA6 => ("LDX", Zpg),
+        0xA7 => ("LAX", Zpg),
+        0xA8 => ("TAY", Imp),
+        0xA9 => ("LDA", Imm),
+        0xAA => ("TAX", Imp),
+        0xAB => ("LXA", Imm),
+        0xAC => ("LDY", Abs),
+        0xAD => ("LDA", Abs),
+        0xAE => ("LDX", Abs),
+        0xAF => ("LAX", Abs),
+        0xB0 => ("BCS", Rel),
+        0xB1 => ("LDA", Idy),
+        0xB2 => ("JAM", Imp),
+        0xB3 => ("LAX", Idy),
+        0xB4 => ("LDY", Zpx),
+        0xB5 => ("LDA", Zpx),
+        0xB6 => ("LDX", Zpy),
+        0xB7 => ("LAX", Zpy),
+        0xB8 => ("CLV", Imp),
+        0xB9 => ("LDA", Aby),
+        0xBA => ("TSX", Imp),
+        0xBB => ("LAS", Aby),
+        0xBC => ("LDY", Abx),
+        0xBD => ("LDA", Abx),
+        0xBE => ("LDX", Aby),
+        0xBF => ("LAX", Aby),
+        0xC0 => ("CPY", Imm),
+        0xC1 => ("CMP", Idx),
+        0xC2 => ("NOP", Imm),
+        0xC3 => ("DCP", Idx),
+        0xC4 => ("CPY", Zpg),
+        0xC5 => ("CMP", Zpg),
+        0xC6 => ("DEC", Zpg),
+        0xC7 => ("DCP", Zpg),
+        0xC8 => ("INY", Imp),
+        0xC9 => ("CMP", Imm),
+        0xCA => ("DEX", Imp),
+        0xCB => ("SBX", Imm),
+        0xCC => ("CPY", Abs),
+        0xCD => ("CMP", Abs),
+        0xCE => ("DEC", Abs),
+        0xCF => ("DCP", Abs),
+        0xD0 => ("BNE", Rel),
+        0xD1 => ("CMP", Idy),
+        0xD2 => ("JAM", Imp),
+        0xD3 => ("DCP", Idy),
+        0xD4 => ("NOP", Zpx),
+        0xD5 => ("CMP", Zpx),
+        0xD6 => ("DEC", Zpx),
+        0xD7 => ("DCP", Zpx),
+        0xD8 => ("CLD", Imp),
+        0xD9 => ("CMP", Aby),
+        0xDA => ("NOP", Imp),
+        0xDB => ("DCP", Aby),
+        0xDC => ("NOP", Abx),
+        0xDD => ("CMP", Abx),
+        0xDE => ("DEC", Abx),
+        0xDF => ("DCP", Abx),
+        0xE0 => ("CPX", Imm),
+        0xE1 => ("SBC", Idx),
+        0xE2 => ("NOP", Imm),
+        0xE3 => ("ISB", Idx),
+        0xE4 => ("CPX", Zpg),
+        0xE5 => ("SBC", Zpg),
+        0xE6 => ("INC", Zpg),
+        0xE7 => ("ISB", Zpg),
+        0xE8 => ("INX", Imp),
+        0xE9 => ("SBC", Imm),
+        0xEA => ("NOP", Imp),
+        0xEB => ("SBC", Imm),
+        0xEC => ("CPX", Abs),
+        0xED => ("SBC", Abs),
+        0xEE => ("INC", Abs),
+        0xEF => ("ISB", Abs),
+        0xF0 => ("BEQ", Rel),
+        0xF1 => ("SBC", Idy),
+        0xF2 => ("JAM", Imp),
+        0xF3 => ("ISB", Idy),
+        0xF4 => ("NOP", Zpx),
+        0xF5 => ("SBC", Zpx),
+        0xF6 => ("INC", Zpx),
+        0xF7 => ("ISB", Zpx),
+        0xF8 => ("SED", Imp),
+        0xF9 => ("SBC", Aby),
+        0xFA => ("NOP", Imp),
+        0xFB => ("ISB", Aby),
+        0xFC => ("NOP", Abx),
+        0xFD => ("SBC", Abx),
+        0xFE => ("INC", Abx),
+        0xFF => ("ISB", Abx),
+    };
+    let official = match mnemonic {
+        "ALR" | "ANC" | "ANE" | "ARR" | "DCP" | "ISB" | "JAM" | "LAS"
+        | "LAX" | "LXA" | "RLA" | "RRA" | "SAX" | "SBX" | "SHA" | "SHX"
+        | "SHY" | "SLO" | "SRE" | "TAS" => false,
+        "NOP" => opc == 0xEA,
+        "SBC" => opc != 0xEB,
+        _ => true,
+    };
+
+    Instruction { mnemonic, mode, official }
+}
+
+/// Formats the operand of an instruction at `pc` with mode `mode`, e.g.,
+/// `($80,X)`. `operand` is the bytes after the opcode. Branch targets are
+/// formatted as absolute addresses.
+pub fn format_operand(mode: Mode, pc: u16, operand: &[u8]) -> String {
+    let byte = || operand[0];
+    let word = || u16::from_le_bytes([operand[0], operand[1]]);
+    match mode {
+        Mode::Imp => String::new(),
+        Mode::Acc => "A".to_string(),
+        Mode::Imm => format!("#${:02X}", byte()),
+        Mode::Zpg => format!("${:02X}", byte()),
+        Mode::Zpx => format!("${:02X},X", byte()),
+        Mode::Zpy => format!("${:02X},Y", byte()),
+        Mode::Abs => format!("${:04X}", word()),
+        Mode::Abx => format!("${:04X},X", word()),
+        Mode::Aby => format!("${:04X},Y", word()),
+        Mode::Ind => format!("(${:04X})", word()),
+        Mode::Idx => format!("(${:02X},X)", byte()),
+        Mode::Idy => format!("(${:02X}),Y", byte()),
+        Mode::Rel => format!("${:04X}", branch_target(pc, byte())),
+    }
+}
+
+/// Formats an operand like `format_operand`, but with `label` in place of
+/// the address.
+fn format_labeled_operand(mode: Mode, label: &str) -> String {
+    match mode {
+        Mode::Imp | Mode::Acc | Mode::Imm => {
+            unreachable!("{mode:?} operands don't have addresses")
+        }
+        Mode::Zpg | Mode::Abs | Mode::Rel => label.to_string(),
+        Mode::Zpx | Mode::Abx => format!("{label},X"),
+        Mode::Zpy | Mode::Aby => format!("{label},Y"),
+        Mode::Ind => format!("({label})"),
+        Mode::Idx => format!("({label},X)"),
+        Mode::Idy => format!("({label}),Y"),
+    }
+}
+
+/// Returns the address in an operand, if it has one.
+fn operand_addr(mode: Mode, pc: u16, operand: &[u8]) -> Option<u16> {
+    match mode {
+        Mode::Imp | Mode::Acc | Mode::Imm => None,
+        Mode::Zpg | Mode::Zpx | Mode::Zpy | Mode::Idx | Mode::Idy => {
+            Some(operand[0] as u16)
+        }
+        Mode::Abs | Mode::Abx | Mode::Aby | Mode::Ind => {
+            Some(u16::from_le_bytes([operand[0], operand[1]]))
+        }
+        Mode::Rel => Some(branch_target(pc, operand[0])),
+    }
+}
+
+/// A disassembled instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disassembly {
+    /// The address of the opcode.
+    pub addr: u16,
+    /// The opcode and operand bytes.
+    pub bytes: Vec<u8>,
+    pub instruction: Instruction,
+    /// The formatted operand, e.g., `($80,X)`. Addresses are replaced with
+    /// their labels if symbols are loaded.
+    pub operand: String,
+    /// The address the instruction reads or writes, if it's known from the
+    /// operand and the current registers.
+    pub effective_addr: Option<u16>,
+    /// The addresses and values used by the instruction in the format of
+    /// nestest.log, e.g., `@ 80 = 0200 = 5A`.
+    pub annotation: String,
+}
+
+impl Disassembly {
+    /// Returns the size of the instruction in bytes.
+    pub fn size(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+}
+
+impl fmt::Display for Disassembly {
+    /// Formats the instruction like `LDA ($80,X) @ 80 = 0200 = 5A`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.instruction.mnemonic)?;
+        if !self.operand.is_empty() {
+            write!(f, " {}", self.operand)?;
+        }
+        if !self.annotation.is_empty() {
+            write!(f, " {}", self.annotation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassembles the instruction at `addr`. Memory is read with `peek`, so
+/// there are no side effects. Bytes that can't be peeked at (e.g., PPU and
+/// APU registers) are treated as $FF, like in nestest.log. The annotation
+/// uses the current X and Y registers, so it's only meaningful for the
+/// instruction at PC.
+pub fn disassemble(emu: &mut Emu, addr: u16) -> Disassembly {
+    let instruction = decode(peek(emu, addr));
+    let bytes: Vec<u8> = (0..instruction.mode.size())
+        .map(|i| peek(emu, addr.wrapping_add(i)))
+        .collect();
+    let operand = &bytes[1..];
+    let (effective_addr, annotation) = annotate(emu, instruction, operand);
+    let mode = instruction.mode;
+    let label = operand_addr(mode, addr, operand)
+        .and_then(|target| symbols::label(emu, target));
+
+    Disassembly {
+        addr,
+        instruction,
+        operand: match label {
+            Some(label) => format_labeled_operand(mode, label),
+            None => format_operand(mode, addr, operand),
+        },
+        effective_addr,
+        annotation,
+        bytes,
+    }
+}
+
+/// Returns the effective address and annotation of an instruction.
+fn annotate(
+    emu: &mut Emu,
+    instruction: Instruction,
+    operand: &[u8],
+) -> (Option<u16>, String) {
+    let Instruction { mnemonic, mode, .. } = instruction;
+    let state = cpu::state(emu);
+    let byte = || operand[0];
+    let word = || u16::from_le_bytes([operand[0], operand[1]]);
+    match mode {
+        Mode::Imp | Mode::Acc | Mode::Imm | Mode::Rel => (None, String::new()),
+        Mode::Abs if mnemonic == "JMP" || mnemonic == "JSR" => {
+            (None, String::new())
+        }
+        Mode::Zpg | Mode::Abs => {
+            let addr = if mode == Mode::Zpg { byte() as u16 } else { word() };
+            (Some(addr), format!("= {:02X}", peek(emu, addr)))
+        }
+        Mode::Zpx | Mode::Zpy => {
+            let index = if mode == Mode::Zpx { state.x } else { state.y };
+            let addr = byte().wrapping_add(index);
+            let data = peek(emu, addr as u16);
+            (Some(addr as u16), format!("@ {addr:02X} = {data:02X}"))
+        }
+        Mode::Abx | Mode::Aby => {
+            let index = if mode == Mode::Abx { state.x } else { state.y };
+            let addr = word().wrapping_add(index as u16);
+            (Some(addr), format!("@ {addr:04X} = {:02X}", peek(emu, addr)))
+        }
+        Mode::Ind => {
+            // JMP doesn't carry into the high byte of the pointer.
+            let ptr = word();
+            let low = peek(emu, ptr);
+            let high =
+                peek(emu, ptr & 0xFF00 | (ptr as u8).wrapping_add(1) as u16);
+            let addr = u16::from_le_bytes([low, high]);
+            (Some(addr), format!("= {addr:04X}"))
+        }
+        Mode::Idx => {
+            let ptr = byte().wrapping_add(state.x);
+            let addr = peek_zp_word(emu, ptr);
+            let data = peek(emu, addr);
+            (Some(addr), format!("@ {ptr:02X} = {addr:04X} = {data:02X}"))
+        }
+        Mode::Idy => {
+            let base = peek_zp_word(emu, byte());
+            let addr = base.wrapping_add(state.y as u16);
+            let data = peek(emu, addr);
+            (Some(addr), format!("= {base:04X} @ {addr:04X} = {data:02X}"))
+        }
+    }
+}
+
+fn peek(emu: &mut Emu, addr: u16) -> u8 {
+    cpu::peek(emu, addr).unwrap_or(0xFF)
+}
+
+/// Peeks at the word at `addr` in the zero page, wrapping around within it.
+fn peek_zp_word(emu: &mut Emu, addr: u8) -> u16 {
+    let low = peek(emu, addr as u16);
+    let high = peek(emu, addr.wrapping_add(1) as u16);
+    u16::from_le_bytes([low, high])
+}
+
+/// Returns the target of a branch at `pc` with offset `offset`.
+pub fn branch_target(pc: u16, offset: u8) -> u16 {
+    pc.wrapping_add(2).wrapping_add(offset as i8 as u16)
+}
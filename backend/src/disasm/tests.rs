@@ -0,0 +1,28 @@
+use crate::disasm::{self, Mode};
+
+#[test]
+fn decode_all_opcodes() {
+    let instructions: Vec<_> = (0..=0xFF).map(disasm::decode).collect();
+    let official = instructions.iter().filter(|i| i.official).count();
+    assert_eq!(official, 151);
+    assert!(instructions.iter().all(|i| i.mnemonic.len() == 3));
+    assert_eq!(
+        instructions.iter().filter(|i| i.mnemonic == "JAM").count(),
+        12
+    );
+}
+
+#[test]
+fn format_operand() {
+    assert_eq!(disasm::format_operand(Mode::Imp, 0x8000, &[]), "");
+    assert_eq!(disasm::format_operand(Mode::Acc, 0x8000, &[]), "A");
+    assert_eq!(disasm::format_operand(Mode::Imm, 0x8000, &[0x10]), "#$10");
+    assert_eq!(disasm::format_operand(Mode::Idx, 0x8000, &[0x80]), "($80,X)");
+    assert_eq!(
+        disasm::format_operand(Mode::Aby, 0x8000, &[0x00, 0x03]),
+        "$0300,Y"
+    );
+    // Branches are relative to the next instruction.
+    assert_eq!(disasm::format_operand(Mode::Rel, 0x8000, &[0xFE]), "$8000");
+    assert_eq!(disasm::format_operand(Mode::Rel, 0x8000, &[0x10]), "$8012");
+}
@@ -1,43 +1,416 @@
-use std::mem::MaybeUninit;
+use std::{io, mem::MaybeUninit, path::Path};
 
 use crate::{
-    apu::{self, Apu},
-    cpu::{self, Cpu},
-    nrom::Nrom,
+    apu::{self, Apu, Channel},
+    cart::{self, Cart},
+    cdl::{self, Cdl},
+    config::Config,
+    controller::{self, Controllers},
+    cpu::{self, Cpu, CpuState, Tracer},
+    debugger::{self, BreakReason, Debugger},
+    disasm::{self, Disassembly},
+    nsf,
+    ppu::{self, Ppu},
+    region::Region,
     scheduler::{self, EventKind, Scheduler},
+    symbols::{self, Symbols},
+    wav::Recorder,
 };
 
+/// Whether the emulator is running normally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    /// The CPU executed the JAM opcode at `pc` and is locked up until it's
+    /// reset. Stepping still advances time.
+    Jammed {
+        pc: u16,
+    },
+}
+
 pub struct Emu {
     pub(crate) cpu: Cpu,
-    pub(crate) nrom: Nrom,
+    pub(crate) cart: Cart,
     pub(crate) scheduler: Scheduler,
     pub(crate) apu: Apu,
+    pub(crate) ppu: Ppu,
+    pub(crate) controllers: Controllers,
+    pub(crate) debugger: Debugger,
+    pub(crate) cdl: Option<Cdl>,
+    pub(crate) symbols: Symbols,
+    pub(crate) config: Config,
 }
 
 impl Emu {
+    /// Loads `rom` with memory zeroed at power on. Panics if `rom` is
+    /// malformed. Use `try_new` for files that might be.
     pub fn new(rom: &[u8]) -> Emu {
+        Emu::with_config(rom, Config::default())
+    }
+
+    /// Loads `rom` with the power-on settings in `config`. Panics if `rom` is
+    /// malformed.
+    pub fn with_config(rom: &[u8], config: Config) -> Emu {
+        match Emu::try_with_config(rom, config) {
+            Ok(emu) => emu,
+            Err(err) => panic!("failed to load the ROM: {err}"),
+        }
+    }
+
+    /// Loads `rom` with memory zeroed at power on. Returns an error if `rom`
+    /// isn't a valid iNES, NSF, or NSFe file.
+    pub fn try_new(rom: &[u8]) -> io::Result<Emu> {
+        Emu::try_with_config(rom, Config::default())
+    }
+
+    /// Loads `rom` with the power-on settings in `config`. Returns an error
+    /// if `rom` isn't a valid iNES, NSF, or NSFe file.
+    pub fn try_with_config(rom: &[u8], config: Config) -> io::Result<Emu> {
         let mut emu = Emu {
             cpu: Cpu::new(),
-            nrom: Nrom::new(rom),
+            cart: Cart::new(rom)?,
             scheduler: Scheduler::new(),
             apu: Apu::new(),
+            ppu: Ppu::new(),
+            controllers: Controllers::new(),
+            debugger: Debugger::new(),
+            cdl: None,
+            symbols: Symbols::new(),
+            config,
         };
+        let region = config.region.unwrap_or_else(|| cart::region(&emu));
+        scheduler::set_region(&mut emu, region);
+        apu::set_region(&mut emu, region);
+        for channel in cart::expansion_channels(&emu) {
+            apu::add_expansion(&mut emu, channel);
+        }
+        cpu::init_ram(&mut emu, config.cpu_ram);
+        cart::init_prg_ram(&mut emu, config.prg_ram);
+        ppu::init_oam(&mut emu, config.oam);
+        ppu::init_vram(&mut emu, config.vram);
+        ppu::init_palette(&mut emu, config.palette);
+        ppu::start_frame(&mut emu);
 
-        scheduler::queue(&mut emu, EventKind::Reset, 0);
+        match emu.cart {
+            Cart::Nsf(_) => {
+                let song = nsf::first_song(&emu);
+                nsf::start_song(&mut emu, song);
+            }
+            _ => scheduler::queue(&mut emu, EventKind::Reset, 0),
+        }
 
-        emu
+        Ok(emu)
     }
 
+    /// Steps the emulator by one CPU instruction. If an instruction is
+    /// partway done from cycle stepping, it's finished instead.
     pub fn step(&mut self) {
-        scheduler::handle_events(self);
-        cpu::step(self);
+        while !self.step_cycle() {}
+    }
+
+    /// Steps the emulator by one CPU cycle. Returns true if the cycle
+    /// finished an instruction. Events that are due when an instruction
+    /// starts (e.g., a reset or an OAM DMA) run before its first cycle, and
+    /// a DMC DMA runs with the read it halts, so their cycles can't be
+    /// stepped through one at a time.
+    pub fn step_cycle(&mut self) -> bool {
+        if !cpu::in_instruction(self) && scheduler::is_due(self) {
+            scheduler::handle_events(self);
+        }
+        cpu::step_cycle(self)
+    }
+
+    /// Steps the emulator by one master clock cycle. The CPU runs a cycle
+    /// once enough master clock cycles have passed, e.g., every 12 on NTSC.
+    /// Returns true if that cycle finished an instruction.
+    pub fn step_tick(&mut self) -> bool {
+        scheduler::tick_master_clock(self) && self.step_cycle()
+    }
+
+    /// Presses the reset button before the next instruction. RAM keeps its
+    /// contents. NSFs don't have a reset handler, so they restart the first
+    /// song right away instead.
+    pub fn reset(&mut self) {
+        match self.cart {
+            Cart::Nsf(_) => nsf::start_song(self, nsf::first_song(self)),
+            _ => scheduler::queue(self, EventKind::SoftReset, 0),
+        }
+    }
+
+    /// Turns the power off and on again before the next instruction. Memory
+    /// is filled with its power-on contents from the config again. NSFs
+    /// restart the first song.
+    pub fn power_cycle(&mut self) {
+        scheduler::queue(self, EventKind::PowerCycle, 0);
+    }
+
+    /// Returns the console region being emulated.
+    pub fn region(&self) -> Region {
+        scheduler::region(self)
+    }
+
+    /// Returns the number of master clock cycles since power on.
+    pub fn master_clock(&self) -> u64 {
+        scheduler::master_clock(self)
+    }
+
+    /// Returns the power-on settings the emulator was made with.
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Returns the number of songs in the loaded NSF, or 0 if it isn't one.
+    pub fn songs(&self) -> u8 {
+        match self.cart {
+            Cart::Nsf(_) => nsf::songs(self),
+            _ => 0,
+        }
+    }
+
+    /// Restarts the loaded NSF at `song` (zero-based). Returns false if an
+    /// NSF isn't loaded or it doesn't have that many songs.
+    pub fn start_song(&mut self, song: u8) -> bool {
+        if song >= self.songs() {
+            return false;
+        }
+        nsf::start_song(self, song);
+        true
+    }
+
+    /// Sets the constant that the unstable ANE ($8B) and LXA ($AB) opcodes OR
+    /// into A. It depends on the CPU revision, and the default is 0xEE. A
+    /// power cycle puts it back to the default.
+    pub fn set_magic(&mut self, magic: u8) {
+        cpu::set_magic(self, magic);
+    }
+
+    pub fn status(&self) -> Status {
+        match cpu::jammed_at(self) {
+            Some(pc) => Status::Jammed { pc },
+            None => Status::Running,
+        }
+    }
+
+    /// Returns the CPU's registers, cycle count, and pending interrupts.
+    pub fn cpu_state(&self) -> CpuState {
+        cpu::state(self)
+    }
+
+    /// Sets the CPU's registers. Panics if an instruction is partway done.
+    pub fn set_cpu_state(&mut self, state: &CpuState) {
+        cpu::set_state(self, state);
+    }
+
+    /// Sets a function that's called with a line in the format of
+    /// nestest.log before each instruction. Pass `None` to stop tracing.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        cpu::set_tracer(self, tracer);
+    }
+
+    /// Disassembles the instruction at `addr` without side effects.
+    pub fn disassemble(&mut self, addr: u16) -> Disassembly {
+        disasm::disassemble(self, addr)
+    }
+
+    /// Returns the debugger's breakpoints and watchpoints.
+    pub fn debugger(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, the CPU jams, or at
+    /// least `max_cycles` CPU cycles have run.
+    pub fn run_until_break(&mut self, max_cycles: u64) -> BreakReason {
+        debugger::run_until_break(self, max_cycles)
+    }
+
+    /// Runs one instruction.
+    pub fn step_into(&mut self) -> BreakReason {
+        debugger::step_into(self)
+    }
+
+    /// Runs one instruction, or a whole subroutine if the instruction is a
+    /// JSR.
+    pub fn step_over(&mut self) -> BreakReason {
+        debugger::step_over(self)
+    }
+
+    /// Runs until the current subroutine returns.
+    pub fn step_out(&mut self) -> BreakReason {
+        debugger::step_out(self)
+    }
+
+    /// Runs until the next frame starts.
+    pub fn run_to_frame(&mut self) -> BreakReason {
+        debugger::run_to_frame(self)
+    }
+
+    /// Runs until `scanline` starts, or for a frame and a scanline if it's
+    /// past the end of the frame.
+    pub fn run_to_scanline(&mut self, scanline: u16) -> BreakReason {
+        debugger::run_to_scanline(self, scanline)
+    }
+
+    /// Returns the labels used by the disassembler and tracer.
+    pub fn symbols(&mut self) -> &mut Symbols {
+        &mut self.symbols
+    }
+
+    /// Loads the labels in a .dbg, .nl, or .mlb file.
+    pub fn load_symbols(&mut self, path: &Path) -> io::Result<()> {
+        self.symbols.load(path)
+    }
+
+    /// Returns the label of `addr` with the banks that are mapped in now.
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        symbols::label(self, addr)
     }
 
     pub fn peek(&mut self, addr: u16) -> Option<u8> {
         cpu::peek(self, addr)
     }
 
+    /// Writes to memory without side effects. Returns false if `addr` isn't
+    /// backed by memory.
+    pub fn poke(&mut self, addr: u16, data: u8) -> bool {
+        cpu::poke(self, addr, data)
+    }
+
+    /// Returns the CPU's 2 KB of internal RAM.
+    pub fn ram(&self) -> &[u8] {
+        cpu::ram(self)
+    }
+
+    /// Returns the last frame the PPU drew, one palette entry (a color from
+    /// $00-$3F) per pixel in rows of 256.
+    pub fn framebuffer(&self) -> &[u8] {
+        ppu::framebuffer(self)
+    }
+
+    /// Holds `buttons` (e.g., `controller::A | controller::START`) on the
+    /// controller in `port` (0 or 1) and lets go of the rest.
+    pub fn set_buttons(&mut self, port: usize, buttons: u8) {
+        controller::set_buttons(self, port, buttons);
+    }
+
+    /// Reads the byte at `addr` in the PPU's address space ($0000-$3FFF,
+    /// mirrored above that) without side effects.
+    pub fn peek_ppu(&self, addr: u16) -> u8 {
+        ppu::peek(self, addr & 0x3FFF)
+    }
+
+    /// Returns the number of stereo samples that are ready to be filled.
+    pub fn samples(&self) -> usize {
+        apu::samples(self)
+    }
+
+    /// Fills `dst` with interleaved stereo samples, left first. If its length
+    /// is odd, the last element is zeroed, and samples past the ones that
+    /// are ready are silent.
     pub fn fill(&mut self, dst: &mut [MaybeUninit<i16>]) {
         apu::fill(self, dst);
     }
+
+    /// Fills and throws away the samples that are ready, so the buffers
+    /// don't overflow during runs that don't play audio. They're still
+    /// recorded.
+    pub fn discard_samples(&mut self) {
+        apu::discard_samples(self);
+    }
+
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        apu::set_muted(self, channel, muted);
+    }
+
+    /// Scales the output of `channel` by `volume`.
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        apu::set_volume(self, channel, volume);
+    }
+
+    /// Pans `channel` between -1.0 (left) and 1.0 (right).
+    pub fn set_pan(&mut self, channel: Channel, pan: f32) {
+        apu::set_pan(self, channel, pan);
+    }
+
+    /// Starts recording filled samples to the WAV file at `path`. If `stems`
+    /// is true, each channel is also recorded to its own file. A recording
+    /// that's already in progress is finished first.
+    pub fn start_recording(
+        &mut self,
+        path: &Path,
+        stems: bool,
+    ) -> io::Result<()> {
+        self.stop_recording()?;
+        let recorder = Recorder::create(path, stems)?;
+        apu::set_recorder(self, Some(recorder));
+        Ok(())
+    }
+
+    /// Finishes the current recording, if any.
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match apu::set_recorder(self, None) {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        apu::is_recording(self)
+    }
+
+    /// Starts logging which bytes of PRG ROM are code and which are data. A
+    /// log that's already in progress is kept.
+    pub fn start_cdl(&mut self) {
+        cdl::start(self);
+    }
+
+    /// Loads a .cdl file for the current ROM and continues logging into it.
+    pub fn load_cdl(&mut self, path: &Path) -> io::Result<()> {
+        cdl::load(self, path)
+    }
+
+    /// Stops logging and returns the log, if any.
+    pub fn stop_cdl(&mut self) -> Option<Cdl> {
+        self.cdl.take()
+    }
+
+    pub fn cdl(&self) -> Option<&Cdl> {
+        self.cdl.as_ref()
+    }
+
+    /// Returns the unmixed samples of `channel` from the last fill. Muting,
+    /// volume, and panning aren't applied.
+    pub fn waveform(&self, channel: Channel) -> &[i16] {
+        apu::waveform(self, channel)
+    }
+}
+
+/// Handles a press of the reset button.
+pub(crate) fn soft_reset(emu: &mut Emu) {
+    apu::reset(emu);
+    ppu::reset(emu);
+    cpu::reset(emu);
+}
+
+/// Handles turning the power off and on again.
+pub(crate) fn power_cycle(emu: &mut Emu) {
+    let config = emu.config;
+    cpu::init_ram(emu, config.cpu_ram);
+    cart::init_prg_ram(emu, config.prg_ram);
+    ppu::init_oam(emu, config.oam);
+    ppu::init_vram(emu, config.vram);
+    ppu::init_palette(emu, config.palette);
+    apu::power_on(emu);
+    ppu::power_on(emu);
+    // The DMA unit is reset too, and a reset that hasn't happened yet,
+    // including the one queued at power on, is moot.
+    scheduler::cancel(emu, EventKind::OamDma(0));
+    scheduler::cancel(emu, EventKind::Reset);
+    scheduler::cancel(emu, EventKind::SoftReset);
+    cpu::power_on(emu);
+    match emu.cart {
+        // Starting the song resets the NSF's banks and PRG RAM and
+        // reschedules PLAY.
+        Cart::Nsf(_) => nsf::start_song(emu, nsf::first_song(emu)),
+        _ => cpu::reset(emu),
+    }
 }
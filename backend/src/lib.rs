@@ -1,7 +1,22 @@
 mod apu;
+mod cart;
+pub mod cdl;
+pub mod config;
+pub mod controller;
 mod cpu;
+pub mod debugger;
+pub mod disasm;
 mod emu;
 mod nrom;
+mod nsf;
+pub mod palette;
+mod ppu;
+pub mod region;
 mod scheduler;
+pub mod symbols;
+mod wav;
 
-pub use emu::Emu;
+pub use apu::{Channel, SAMPLE_RATE};
+pub use cpu::{CpuState, Tracer};
+pub use emu::{Emu, Status};
+pub use wav::WavWriter;
@@ -1,42 +1,128 @@
 #![cfg_attr(test, allow(dead_code))]
 
-use crate::Emu;
+use std::io;
+
+use crate::region::Region;
 
 const HEADER_SIZE: u8 = 16;
 const PRG_ROM_BANK_SIZE: u16 = 16384;
+const CHR_ROM_BANK_SIZE: u16 = 8192;
 /// The size of PRG RAM in bytes.
 const PRG_RAM_SIZE: u16 = 8192;
+/// The size of CHR RAM in bytes.
+const CHR_RAM_SIZE: u16 = 8192;
+
+/// How the PPU's 2 KB of nametable RAM is mapped into its four nametables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    /// $2000 and $2400 are the first nametable, and $2800 and $2C00 are the
+    /// second.
+    Horizontal,
+    /// $2000 and $2800 are the first nametable, and $2400 and $2C00 are the
+    /// second.
+    Vertical,
+}
 
 pub struct Nrom {
     // These are pub(crate) since the CPU tests need to make an empty version
     // of Nrom.
     pub(crate) prg_ram: Box<[u8]>,
     pub(crate) prg_rom: Box<[u8]>,
+    pub(crate) chr_rom: Box<[u8]>,
+    /// Boards without CHR ROM have 8 KB of CHR RAM instead.
+    pub(crate) chr_ram: Box<[u8]>,
+    pub(crate) mirroring: Mirroring,
+    pub(crate) region: Region,
 }
 
 impl Nrom {
-    pub fn new(rom: &[u8]) -> Nrom {
+    pub fn new(rom: &[u8]) -> io::Result<Nrom> {
+        let invalid =
+            |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        if rom.len() < HEADER_SIZE as usize {
+            return Err(invalid("the iNES header is truncated"));
+        }
         let (header, rom) = rom.split_at(HEADER_SIZE as usize);
-        let prg_rom_size = header[4] as u16 * PRG_ROM_BANK_SIZE;
-        Nrom {
-            prg_ram: vec![0; PRG_RAM_SIZE as usize].into_boxed_slice(),
-            prg_rom: rom[..prg_rom_size as usize].into(),
+        let region = Region::from_ines(header);
+        let prg_rom_size = header[4] as usize * PRG_ROM_BANK_SIZE as usize;
+        let chr_rom_size = header[5] as usize * CHR_ROM_BANK_SIZE as usize;
+        if prg_rom_size == 0 {
+            return Err(invalid("there isn't any PRG ROM"));
+        }
+        if rom.len() < prg_rom_size + chr_rom_size {
+            return Err(invalid("the PRG or CHR ROM is truncated"));
         }
+        let (prg_rom, rom) = rom.split_at(prg_rom_size);
+        let chr_ram_size = match chr_rom_size {
+            0 => CHR_RAM_SIZE as usize,
+            _ => 0,
+        };
+        let mirroring = match header[6] & 1 {
+            0 => Mirroring::Horizontal,
+            _ => Mirroring::Vertical,
+        };
+        Ok(Nrom {
+            prg_ram: vec![0; PRG_RAM_SIZE as usize].into_boxed_slice(),
+            prg_rom: prg_rom.into(),
+            chr_rom: rom[..chr_rom_size].into(),
+            chr_ram: vec![0; chr_ram_size].into_boxed_slice(),
+            mirroring,
+            region,
+        })
     }
 }
 
-pub fn read_prg_ram(emu: &Emu, addr: u16) -> u8 {
-    emu.nrom.prg_ram[(addr - 0x6000) as usize]
+pub fn read_prg_ram(nrom: &Nrom, addr: u16) -> u8 {
+    nrom.prg_ram[(addr - 0x6000) as usize]
+}
+
+pub fn write_prg_ram(nrom: &mut Nrom, addr: u16, data: u8) {
+    nrom.prg_ram[(addr - 0x6000) as usize] = data;
 }
 
-pub fn write_prg_ram(emu: &mut Emu, addr: u16, data: u8) {
-    emu.nrom.prg_ram[(addr - 0x6000) as usize] = data;
+pub fn read_prg_rom(nrom: &Nrom, addr: u16) -> u8 {
+    nrom.prg_rom[prg_rom_offset(nrom, addr)]
 }
 
-pub fn read_prg_rom(emu: &Emu, addr: u16) -> u8 {
-    emu.nrom.prg_rom[(addr - 0x8000) as usize % emu.nrom.prg_rom.len()]
+pub fn write_prg_rom(nrom: &mut Nrom, addr: u16, data: u8) {
+    let offset = prg_rom_offset(nrom, addr);
+    nrom.prg_rom[offset] = data;
+}
+
+/// Returns the offset into PRG ROM that `addr` is mapped to.
+pub fn prg_rom_offset(nrom: &Nrom, addr: u16) -> usize {
+    (addr - 0x8000) as usize % nrom.prg_rom.len()
+}
+
+/// Returns the offset into CHR ROM that `addr` is mapped to, if the
+/// cartridge has CHR ROM.
+pub fn chr_rom_offset(nrom: &Nrom, addr: u16) -> Option<usize> {
+    (!nrom.chr_rom.is_empty()).then_some(addr as usize)
+}
+
+pub fn read_chr(nrom: &Nrom, addr: u16) -> u8 {
+    if nrom.chr_rom.is_empty() {
+        nrom.chr_ram[addr as usize]
+    } else {
+        nrom.chr_rom[addr as usize]
+    }
+}
+
+/// Writes to CHR RAM. Writes to CHR ROM are ignored.
+pub fn write_chr(nrom: &mut Nrom, addr: u16, data: u8) {
+    if nrom.chr_rom.is_empty() {
+        nrom.chr_ram[addr as usize] = data;
+    }
 }
 
-pub fn write_prg_rom(emu: &mut Emu, addr: u16, data: u8) {
-    emu.nrom.prg_rom[(addr - 0x8000) as usize % emu.nrom.prg_rom.len()] = data;
+/// Returns the offset into nametable RAM that `addr` ($2000-$2FFF) is
+/// mapped to.
+pub fn nametable_offset(nrom: &Nrom, addr: u16) -> usize {
+    let addr = addr as usize & 0x0FFF;
+    let table = match nrom.mirroring {
+        Mirroring::Horizontal => addr >> 11,
+        Mirroring::Vertical => addr >> 10 & 1,
+    };
+    table << 10 | addr & 0x03FF
 }
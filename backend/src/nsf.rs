@@ -0,0 +1,395 @@
+#![cfg_attr(test, allow(dead_code))]
+
+use std::{error::Error, io};
+
+use crate::{
+    apu::{self, Channel},
+    cart::Cart,
+    cpu,
+    region::Region,
+    scheduler::{self, EventKind},
+    Emu,
+};
+
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+const NSF_HEADER_SIZE: usize = 0x80;
+/// The size of an NSFe INFO chunk without the optional song count and
+/// starting song.
+const NSFE_INFO_SIZE: usize = 8;
+
+/// The size of a bank in bytes.
+const BANK_SIZE: usize = 4096;
+/// The size of PRG RAM in bytes.
+const PRG_RAM_SIZE: usize = 8192;
+
+/// The address of the driver's idle loop. The CPU spins here whenever INIT or
+/// PLAY isn't running.
+pub const DRIVER_ADDR: u16 = 0x4100;
+/// The driver's idle loop, `JMP DRIVER_ADDR`.
+const DRIVER: [u8; 3] = [0x4C, DRIVER_ADDR as u8, (DRIVER_ADDR >> 8) as u8];
+
+/// The PLAY rate used if the file doesn't specify one, in microseconds.
+const DEFAULT_SPEED: u16 = 16639;
+/// The PAL PLAY rate used if the file doesn't specify one, in microseconds.
+const DEFAULT_PAL_SPEED: u16 = 19997;
+
+const VRC6: u8 = 1 << 0;
+const NAMCO: u8 = 1 << 4;
+const FME7: u8 = 1 << 5;
+/// The expansion audio chips that files can use but aren't emulated.
+const UNSUPPORTED_EXPANSIONS: [(u8, &str); 3] =
+    [(1 << 1, "VRC7"), (1 << 2, "FDS"), (1 << 3, "MMC5")];
+
+pub struct Nsf {
+    /// The program data, padded so it starts on a bank boundary.
+    prg: Box<[u8]>,
+    prg_ram: Box<[u8]>,
+    /// The bank mapped into each 4 KB window of $8000-$FFFF.
+    banks: [u8; 8],
+    initial_banks: [u8; 8],
+    init_addr: u16,
+    play_addr: u16,
+    songs: u8,
+    /// The song to start with (zero-based).
+    first_song: u8,
+    /// The PLAY rate in microseconds.
+    speed: u16,
+    /// The PLAY rate on PAL consoles in microseconds.
+    pal_speed: u16,
+    region: Region,
+    /// The expansion audio chips used by the file.
+    expansion: u8,
+}
+
+impl Nsf {
+    pub fn new(rom: &[u8]) -> io::Result<Nsf> {
+        if rom.starts_with(NSFE_MAGIC) {
+            Nsf::from_nsfe(&rom[NSFE_MAGIC.len()..])
+        } else {
+            Nsf::from_nsf(rom)
+        }
+    }
+
+    fn from_nsf(rom: &[u8]) -> io::Result<Nsf> {
+        if rom.len() < NSF_HEADER_SIZE {
+            return Err(invalid("the NSF header is truncated"));
+        }
+        let (header, data) = rom.split_at(NSF_HEADER_SIZE);
+        let word = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+        Nsf::from_parts(
+            data,
+            word(0x08),
+            word(0x0A),
+            word(0x0C),
+            header[0x70..0x78].try_into().unwrap(),
+            header[0x06],
+            // The starting song is one-based in NSF files.
+            header[0x07].saturating_sub(1),
+            word(0x6E),
+            word(0x78),
+            Region::from_nsf(header[0x7A]),
+            header[0x7B],
+        )
+    }
+
+    fn from_nsfe(mut rom: &[u8]) -> io::Result<Nsf> {
+        let mut info = None;
+        let mut data: &[u8] = &[];
+        let mut banks = [0; 8];
+        let mut speed = 0;
+        let mut pal_speed = 0;
+        while rom.len() >= 8 {
+            let len = u32::from_le_bytes(rom[0..4].try_into().unwrap());
+            let id = &rom[4..8];
+            let name = String::from_utf8_lossy(id);
+            let Some(chunk) = rom[8..].get(..len as usize) else {
+                return Err(invalid(format!("the {name} chunk is truncated")));
+            };
+            rom = &rom[8 + chunk.len()..];
+            match id {
+                b"INFO" if chunk.len() < NSFE_INFO_SIZE => {
+                    return Err(invalid(format!(
+                        "the INFO chunk is {} bytes, but it has to be at \
+                         least {NSFE_INFO_SIZE}",
+                        chunk.len()
+                    )));
+                }
+                b"INFO" => info = Some(chunk),
+                b"DATA" => data = chunk,
+                // Banks that are left out are 0, and extra bytes are
+                // ignored.
+                b"BANK" => {
+                    for (bank, &data) in banks.iter_mut().zip(chunk) {
+                        *bank = data;
+                    }
+                }
+                b"RATE" => {
+                    let [low, high, ..] = *chunk else {
+                        return Err(invalid("the RATE chunk is truncated"));
+                    };
+                    speed = u16::from_le_bytes([low, high]);
+                    if let Some(rate) = chunk.get(2..4) {
+                        pal_speed = u16::from_le_bytes([rate[0], rate[1]]);
+                    }
+                }
+                b"NEND" => break,
+                // Chunks that start with an uppercase letter are required.
+                id if id[0].is_ascii_uppercase() => {
+                    return Err(invalid(format!(
+                        "the {name} chunk isn't supported"
+                    )));
+                }
+                _ => (),
+            }
+        }
+
+        let info = info.ok_or_else(|| invalid("there's no INFO chunk"))?;
+        let word = |i: usize| u16::from_le_bytes([info[i], info[i + 1]]);
+        Nsf::from_parts(
+            data,
+            word(0),
+            word(2),
+            word(4),
+            banks,
+            info.get(8).copied().unwrap_or(1),
+            info.get(9).copied().unwrap_or(0),
+            speed,
+            pal_speed,
+            Region::from_nsf(info[6]),
+            info[7],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_parts(
+        data: &[u8],
+        load_addr: u16,
+        init_addr: u16,
+        play_addr: u16,
+        banks: [u8; 8],
+        songs: u8,
+        first_song: u8,
+        speed: u16,
+        pal_speed: u16,
+        region: Region,
+        expansion: u8,
+    ) -> io::Result<Nsf> {
+        if songs == 0 {
+            return Err(invalid("there aren't any songs"));
+        }
+        let unsupported: Vec<_> = UNSUPPORTED_EXPANSIONS
+            .iter()
+            .filter(|(chip, _)| expansion & chip != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        if !unsupported.is_empty() {
+            return Err(invalid(format!(
+                "{} expansion audio isn't supported",
+                unsupported.join(" and ")
+            )));
+        }
+
+        let bankswitched = banks.iter().any(|&bank| bank != 0);
+        // Files that don't bankswitch are loaded at the load address with
+        // banks 0-7 mapped in order. Files that do are only aligned within a
+        // bank.
+        let (padding, banks) = if bankswitched {
+            (load_addr as usize & (BANK_SIZE - 1), banks)
+        } else if load_addr >= 0x8000 {
+            (load_addr as usize - 0x8000, [0, 1, 2, 3, 4, 5, 6, 7])
+        } else {
+            return Err(invalid(format!(
+                "the load address ${load_addr:04X} is below $8000"
+            )));
+        };
+        let mut prg = vec![0; padding];
+        prg.extend_from_slice(data);
+        prg.resize(prg.len().max(1).next_multiple_of(BANK_SIZE), 0);
+
+        Ok(Nsf {
+            prg: prg.into(),
+            prg_ram: vec![0; PRG_RAM_SIZE].into(),
+            banks,
+            initial_banks: banks,
+            init_addr,
+            play_addr,
+            songs,
+            // Start with the first song if the file's is out of range.
+            first_song: if first_song < songs { first_song } else { 0 },
+            speed: if speed == 0 { DEFAULT_SPEED } else { speed },
+            pal_speed: if pal_speed == 0 {
+                DEFAULT_PAL_SPEED
+            } else {
+                pal_speed
+            },
+            region,
+            expansion,
+        })
+    }
+}
+
+fn invalid(message: impl Into<Box<dyn Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Returns true if `rom` is an NSF or NSFe file.
+pub fn is_nsf(rom: &[u8]) -> bool {
+    rom.starts_with(NSF_MAGIC) || rom.starts_with(NSFE_MAGIC)
+}
+
+fn nsf(emu: &Emu) -> &Nsf {
+    match &emu.cart {
+        Cart::Nsf(nsf) => nsf,
+        _ => unreachable!("the cartridge isn't an NSF"),
+    }
+}
+
+fn nsf_mut(emu: &mut Emu) -> &mut Nsf {
+    match &mut emu.cart {
+        Cart::Nsf(nsf) => nsf,
+        _ => unreachable!("the cartridge isn't an NSF"),
+    }
+}
+
+pub fn songs(emu: &Emu) -> u8 {
+    nsf(emu).songs
+}
+
+/// Returns the region that the file was made for.
+pub fn region(emu: &Emu) -> Region {
+    nsf(emu).region
+}
+
+/// Returns the expansion audio channels that the file uses.
+pub fn expansion_channels(emu: &Emu) -> Vec<Channel> {
+    let expansion = nsf(emu).expansion;
+    [
+        (VRC6, Channel::Vrc6),
+        (NAMCO, Channel::Namco163),
+        (FME7, Channel::Sunsoft5b),
+    ]
+    .into_iter()
+    .filter(|&(chip, _)| expansion & chip != 0)
+    .map(|(_, channel)| channel)
+    .collect()
+}
+
+pub fn first_song(emu: &Emu) -> u8 {
+    nsf(emu).first_song
+}
+
+/// Resets the sound hardware and calls INIT for `song` (zero-based), which
+/// has to be less than the number of songs. PLAY is called once INIT returns.
+pub fn start_song(emu: &mut Emu, song: u8) {
+    let nsf = nsf_mut(emu);
+    debug_assert!(song < nsf.songs, "song {song} is out of range");
+    nsf.banks = nsf.initial_banks;
+    nsf.prg_ram.fill(0);
+    let init_addr = nsf.init_addr;
+
+    cpu::clear_ram(emu);
+    apu::write(emu, 0x4015, 0x00);
+    for addr in 0x4000..=0x4013 {
+        apu::write(emu, addr, 0x00);
+    }
+    apu::write(emu, 0x4015, 0x0F);
+    apu::write(emu, 0x4017, 0x40);
+    apu::reset_expansions(emu);
+
+    // X is 1 for 50 Hz consoles.
+    let pal = scheduler::region(emu) != Region::Ntsc;
+    cpu::call(emu, init_addr, DRIVER_ADDR, song, pal as u8);
+    scheduler::reschedule(emu, EventKind::NsfPlay, play_period(emu));
+}
+
+/// Calls PLAY and queues the next call.
+pub fn play(emu: &mut Emu) {
+    scheduler::queue(emu, EventKind::NsfPlay, play_period(emu));
+    // Skip this call if INIT or the last PLAY hasn't returned yet.
+    if cpu::pc(emu) == DRIVER_ADDR {
+        let play_addr = nsf(emu).play_addr;
+        cpu::call(emu, play_addr, DRIVER_ADDR, 0, 0);
+    }
+}
+
+/// Returns the number of CPU cycles between calls to PLAY.
+fn play_period(emu: &Emu) -> u64 {
+    let region = scheduler::region(emu);
+    let speed = match region {
+        Region::Ntsc => nsf(emu).speed,
+        Region::Pal | Region::Dendy => nsf(emu).pal_speed,
+    };
+    speed as u64 * region.cpu_clock_rate() as u64 / 1_000_000
+}
+
+pub fn read(emu: &mut Emu, addr: u16) -> Option<u8> {
+    peek(emu, addr)
+}
+
+pub fn write(emu: &mut Emu, addr: u16, data: u8) {
+    let expansion = nsf(emu).expansion;
+    match addr {
+        0x4800..=0x4FFF if expansion & NAMCO != 0 => {
+            apu::write_namco_data(emu, data)
+        }
+        0x5FF8..=0x5FFF => nsf_mut(emu).banks[addr as usize - 0x5FF8] = data,
+        0x6000..=0x7FFF => nsf_mut(emu).prg_ram[addr as usize - 0x6000] = data,
+        0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002
+            if expansion & VRC6 != 0 =>
+        {
+            apu::write_vrc6(emu, addr, data)
+        }
+        // The Namco 163's address port overlaps the Sunsoft 5B's data port.
+        // Files that use both chips expect it to go to the Namco 163.
+        0xF800..=0xFFFF if expansion & NAMCO != 0 => {
+            apu::write_namco_addr(emu, data)
+        }
+        0xC000..=0xDFFF if expansion & FME7 != 0 => {
+            apu::write_fme7_latch(emu, data)
+        }
+        0xE000..=0xFFFF if expansion & FME7 != 0 => {
+            apu::write_fme7_data(emu, data)
+        }
+        _ => (),
+    }
+}
+
+pub fn peek(emu: &Emu, addr: u16) -> Option<u8> {
+    let nsf = nsf(emu);
+    match addr {
+        0x4100..=0x4102 => Some(DRIVER[addr as usize - 0x4100]),
+        0x6000..=0x7FFF => Some(nsf.prg_ram[addr as usize - 0x6000]),
+        0x8000..=0xFFFF => Some(nsf.prg[bank_offset(nsf, addr)]),
+        _ => None,
+    }
+}
+
+pub fn poke(emu: &mut Emu, addr: u16, data: u8) -> bool {
+    let nsf = nsf_mut(emu);
+    match addr {
+        0x6000..=0x7FFF => nsf.prg_ram[addr as usize - 0x6000] = data,
+        0x8000..=0xFFFF => {
+            let index = bank_offset(nsf, addr);
+            nsf.prg[index] = data;
+        }
+        _ => return false,
+    }
+    true
+}
+
+pub fn prg_size(emu: &Emu) -> usize {
+    nsf(emu).prg.len()
+}
+
+/// Returns the index into the program data that `addr` ($8000-$FFFF) is
+/// mapped to.
+pub fn prg_index(emu: &Emu, addr: u16) -> usize {
+    bank_offset(nsf(emu), addr)
+}
+
+fn bank_offset(nsf: &Nsf, addr: u16) -> usize {
+    let window = (addr as usize - 0x8000) / BANK_SIZE;
+    let bank = nsf.banks[window] as usize % (nsf.prg.len() / BANK_SIZE);
+    bank * BANK_SIZE + (addr as usize & (BANK_SIZE - 1))
+}
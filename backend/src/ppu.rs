@@ -0,0 +1,319 @@
+#![cfg_attr(test, allow(dead_code))]
+
+use crate::{
+    cart, cdl,
+    config::RamInit,
+    cpu,
+    scheduler::{self, EventKind},
+    Emu,
+};
+
+/// The size of OAM in bytes.
+const OAM_SIZE: u16 = 256;
+/// The size of nametable RAM in bytes.
+const VRAM_SIZE: u16 = 0x0800;
+/// The size of palette RAM in bytes.
+const PALETTE_SIZE: u16 = 32;
+/// The size of a frame in pixels.
+const WIDTH: usize = 256;
+const HEIGHT: usize = 240;
+/// The number of dots per scanline.
+pub const DOTS_PER_SCANLINE: u64 = 341;
+/// The number of CPU cycles it takes for the PPU's I/O latch to decay to 0,
+/// about 600 ms.
+const LATCH_DECAY_CYCLES: u64 = 1_073_864;
+
+pub struct Ppu {
+    /// $2000
+    ctrl: u8,
+    /// $2001
+    mask: u8,
+    /// Object attribute memory.
+    oam: Box<[u8; OAM_SIZE as usize]>,
+    /// $2003
+    oam_addr: u8,
+    /// Nametable RAM. The cartridge decides how it's mapped into $2000-$2FFF.
+    vram: Box<[u8; VRAM_SIZE as usize]>,
+    palette: [u8; PALETTE_SIZE as usize],
+    /// The current VRAM address that $2007 accesses.
+    addr: u16,
+    /// The VRAM address that's copied to `addr` on the second write to
+    /// $2006.
+    tmp_addr: u16,
+    /// Whether the next write to $2006 is the second one.
+    write_toggle: bool,
+    /// Reads from $2007 return the byte that the last read fetched.
+    read_buffer: u8,
+    /// The PPU's own data bus. Reads of write-only registers return it.
+    latch: u8,
+    /// The cycle that the latch was last refreshed on.
+    latch_cycle: u64,
+    /// The last frame that was drawn, as palette entries.
+    framebuffer: Box<[u8; WIDTH * HEIGHT]>,
+}
+
+impl Ppu {
+    pub fn new() -> Ppu {
+        Ppu {
+            ctrl: 0,
+            mask: 0,
+            oam: vec![0; OAM_SIZE as usize].try_into().unwrap(),
+            oam_addr: 0,
+            vram: vec![0; VRAM_SIZE as usize].try_into().unwrap(),
+            palette: [0; PALETTE_SIZE as usize],
+            addr: 0,
+            tmp_addr: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            latch: 0,
+            latch_cycle: 0,
+            framebuffer: vec![0; WIDTH * HEIGHT].try_into().unwrap(),
+        }
+    }
+}
+
+/// Fills OAM with its power-on contents.
+pub fn init_oam(emu: &mut Emu, init: RamInit) {
+    init.fill(&mut emu.ppu.oam[..]);
+}
+
+/// Fills nametable RAM with its power-on contents.
+pub fn init_vram(emu: &mut Emu, init: RamInit) {
+    init.fill(&mut emu.ppu.vram[..]);
+}
+
+/// Fills palette RAM with its power-on contents.
+pub fn init_palette(emu: &mut Emu, init: RamInit) {
+    init.fill(&mut emu.ppu.palette);
+    for entry in &mut emu.ppu.palette {
+        *entry &= 0x3F;
+    }
+}
+
+/// Puts the PPU's registers back in their power-on state.
+pub fn power_on(emu: &mut Emu) {
+    reset(emu);
+    emu.ppu.oam_addr = 0;
+    emu.ppu.addr = 0;
+    emu.ppu.latch = 0;
+}
+
+/// Clears the registers that the reset button clears: PPUCTRL, PPUMASK,
+/// PPUSCROLL, the write toggle, and the read buffer. OAMADDR and PPUADDR are
+/// kept.
+pub fn reset(emu: &mut Emu) {
+    emu.ppu.ctrl = 0;
+    emu.ppu.mask = 0;
+    emu.ppu.tmp_addr = 0;
+    emu.ppu.write_toggle = false;
+    emu.ppu.read_buffer = 0;
+}
+
+pub fn read(emu: &mut Emu, addr: u16) -> u8 {
+    // 0x2008-0x3FFF are mirrors of 0x2000-0x2007.
+    match addr & 0x2007 {
+        // TODO: Return the vblank, sprite 0 hit, and sprite overflow flags.
+        0x2002 => {
+            emu.ppu.write_toggle = false;
+            latch(emu)
+        }
+        0x2004 => {
+            let data = emu.ppu.oam[emu.ppu.oam_addr as usize];
+            refresh_latch(emu, data);
+            data
+        }
+        0x2007 => {
+            let addr = emu.ppu.addr & 0x3FFF;
+            let data = if addr >= 0x3F00 {
+                // Palette reads aren't buffered, and the top two bits are
+                // open bus. The buffer gets the nametable byte underneath.
+                emu.ppu.read_buffer = peek(emu, addr - 0x1000);
+                peek(emu, addr) | latch(emu) & 0xC0
+            } else {
+                let data = emu.ppu.read_buffer;
+                emu.ppu.read_buffer = peek(emu, addr);
+                cdl::log_chr(emu, addr, cdl::READ);
+                data
+            };
+            increment_addr(emu);
+            refresh_latch(emu, data);
+            data
+        }
+        _ => latch(emu),
+    }
+}
+
+pub fn write(emu: &mut Emu, addr: u16, data: u8) {
+    refresh_latch(emu, data);
+    // 0x2008-0x3FFF are mirrors of 0x2000-0x2007.
+    match addr & 0x2007 {
+        // TODO: Enabling NMI during vblank pulls the NMI line right away.
+        0x2000 => emu.ppu.ctrl = data,
+        0x2001 => emu.ppu.mask = data,
+        0x2003 => emu.ppu.oam_addr = data,
+        0x2004 => {
+            emu.ppu.oam[emu.ppu.oam_addr as usize] = data;
+            emu.ppu.oam_addr = emu.ppu.oam_addr.wrapping_add(1);
+        }
+        0x2006 => {
+            if emu.ppu.write_toggle {
+                emu.ppu.tmp_addr = emu.ppu.tmp_addr & 0x7F00 | data as u16;
+                emu.ppu.addr = emu.ppu.tmp_addr;
+            } else {
+                // The first write clears bit 14 too.
+                let high = (data as u16 & 0x3F) << 8;
+                emu.ppu.tmp_addr = emu.ppu.tmp_addr & 0x00FF | high;
+            }
+            emu.ppu.write_toggle = !emu.ppu.write_toggle;
+        }
+        0x2007 => {
+            write_vram(emu, emu.ppu.addr & 0x3FFF, data);
+            increment_addr(emu);
+        }
+        _ => (),
+    }
+}
+
+/// Moves the VRAM address to the next byte, or the next row of a nametable
+/// if PPUCTRL asks for it.
+fn increment_addr(emu: &mut Emu) {
+    let step = if emu.ppu.ctrl & 0x04 != 0 { 32 } else { 1 };
+    emu.ppu.addr = emu.ppu.addr.wrapping_add(step) & 0x7FFF;
+}
+
+/// Reads the byte at `addr` ($0000-$3FFF) on the PPU's bus without side
+/// effects.
+pub fn peek(emu: &Emu, addr: u16) -> u8 {
+    match addr {
+        0x0000..=0x1FFF => cart::read_chr(emu, addr),
+        // 0x3000-0x3EFF are mirrors of 0x2000-0x2EFF.
+        0x2000..=0x3EFF => emu.ppu.vram[cart::nametable_offset(emu, addr)],
+        _ => emu.ppu.palette[palette_index(addr)],
+    }
+}
+
+fn write_vram(emu: &mut Emu, addr: u16, data: u8) {
+    match addr {
+        0x0000..=0x1FFF => cart::write_chr(emu, addr, data),
+        0x2000..=0x3EFF => {
+            let offset = cart::nametable_offset(emu, addr);
+            emu.ppu.vram[offset] = data;
+        }
+        // Palette entries are only 6 bits.
+        _ => emu.ppu.palette[palette_index(addr)] = data & 0x3F,
+    }
+}
+
+/// Returns the index into palette RAM of `addr` ($3F00-$3FFF). The
+/// backdrop entries of the sprite palettes are mirrors of the background
+/// palettes' ones.
+fn palette_index(addr: u16) -> usize {
+    let index = addr as usize % PALETTE_SIZE as usize;
+    if index & 0x13 == 0x10 {
+        index & !0x10
+    } else {
+        index
+    }
+}
+
+/// Returns the I/O latch, which decays to 0 if it isn't refreshed.
+// TODO: Each bit decays on its own, and only bits that are driven are
+// refreshed, e.g., $2002 refreshes bits 5-7.
+fn latch(emu: &Emu) -> u8 {
+    let elapsed = scheduler::ticks(emu) - emu.ppu.latch_cycle;
+    if elapsed < LATCH_DECAY_CYCLES {
+        emu.ppu.latch
+    } else {
+        0
+    }
+}
+
+fn refresh_latch(emu: &mut Emu, data: u8) {
+    emu.ppu.latch = data;
+    emu.ppu.latch_cycle = scheduler::ticks(emu);
+}
+
+/// Returns the current frame, scanline, and dot.
+pub fn position(emu: &Emu) -> (u64, u16, u16) {
+    // The PPU isn't clocked yet, so its position is derived from the master
+    // clock. This is accurate as long as rendering is off (there's no skipped
+    // dot on odd frames).
+    let region = scheduler::region(emu);
+    let dots = scheduler::master_clock(emu) / region.ppu_divider();
+    let scanlines = region.scanlines() as u64;
+    let frame = dots / (DOTS_PER_SCANLINE * scanlines);
+    let scanline = dots / DOTS_PER_SCANLINE % scanlines;
+    let dot = dots % DOTS_PER_SCANLINE;
+    (frame, scanline as u16, dot as u16)
+}
+
+/// Queues the start of vblank and the end of the frame that's starting. This
+/// is called at power on and then at the end of every frame.
+pub fn start_frame(emu: &mut Emu) {
+    let region = scheduler::region(emu);
+    let divider = region.ppu_divider();
+    let frame = DOTS_PER_SCANLINE * region.scanlines() as u64 * divider;
+    // The vblank flag is set on the second dot of the scanline after the
+    // post-render scanline, and vblank lasts until the pre-render scanline.
+    let first_scanline = region.scanlines() - region.vblank_scanlines() - 1;
+    let vblank = (DOTS_PER_SCANLINE * first_scanline as u64 + 1) * divider;
+
+    let start = scheduler::master_clock(emu) / frame * frame;
+    scheduler::queue_at(emu, EventKind::Nmi, start + vblank);
+    scheduler::queue_at(emu, EventKind::FrameEnd, start + frame);
+}
+
+/// Finishes drawing the frame at the start of vblank, and pulls the NMI
+/// line if PPUCTRL enables it.
+pub fn start_vblank(emu: &mut Emu) {
+    draw_frame(emu);
+    if emu.ppu.ctrl & 0x80 != 0 {
+        cpu::set_nmi(emu);
+    }
+}
+
+pub fn framebuffer(emu: &Emu) -> &[u8] {
+    &emu.ppu.framebuffer[..]
+}
+
+/// Draws the background from the nametable that PPUCTRL selects, or the
+/// backdrop color if PPUMASK hides it.
+// TODO: This is a stand-in until the PPU renders dot by dot. It draws the
+// whole frame at once with the state at the start of vblank, and it doesn't
+// scroll, draw sprites, or apply PPUMASK's other bits.
+fn draw_frame(emu: &mut Emu) {
+    let backdrop = emu.ppu.palette[0];
+    if emu.ppu.mask & 0x08 == 0 {
+        emu.ppu.framebuffer.fill(backdrop);
+        return;
+    }
+
+    let nametable = 0x2000 | (emu.ppu.ctrl as u16 & 0x03) << 10;
+    let pattern_table = (emu.ppu.ctrl as u16 & 0x10) << 8;
+    for y in 0..HEIGHT {
+        let row = (y / 8) as u16;
+        for column in 0..(WIDTH / 8) as u16 {
+            let tile = peek(emu, nametable + row * 32 + column) as u16;
+            let attr_addr = nametable + 0x03C0 + row / 4 * 8 + column / 4;
+            let shift = (row & 2) << 1 | column & 2;
+            let palette = peek(emu, attr_addr) >> shift & 0x03;
+            let addr = pattern_table | tile << 4 | (y % 8) as u16;
+            let (low, high) = (peek(emu, addr), peek(emu, addr + 8));
+            cdl::log_chr(emu, addr, cdl::RENDERED);
+            cdl::log_chr(emu, addr + 8, cdl::RENDERED);
+
+            let start = y * WIDTH + column as usize * 8;
+            for (i, pixel) in
+                emu.ppu.framebuffer[start..start + 8].iter_mut().enumerate()
+            {
+                let bit = 7 - i;
+                let color = (high >> bit & 1) << 1 | low >> bit & 1;
+                *pixel = if color == 0 {
+                    backdrop
+                } else {
+                    emu.ppu.palette[(palette << 2 | color) as usize]
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! The timing of each console region. Everything is clocked by dividing a
+//! master clock, and each region divides a different master clock by
+//! different amounts.
+
+/// A console region.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Region {
+    /// North America and Japan.
+    #[default]
+    Ntsc,
+    /// Europe and Australia.
+    Pal,
+    /// The Dendy and other famiclones, which mix PAL's frame rate with NTSC's
+    /// CPU to PPU ratio.
+    Dendy,
+}
+
+impl Region {
+    /// Returns the region in an iNES or NES 2.0 header.
+    pub fn from_ines(header: &[u8]) -> Region {
+        let nes2 = header[7] & 0x0C == 0x08;
+        if nes2 {
+            // Multiple-region ROMs run as NTSC.
+            match header[12] & 0b11 {
+                1 => Region::Pal,
+                3 => Region::Dendy,
+                _ => Region::Ntsc,
+            }
+        } else if header[9] & 1 != 0 {
+            Region::Pal
+        } else {
+            Region::Ntsc
+        }
+    }
+
+    /// Returns the region in the flags of an NSF header or NSFe INFO chunk.
+    pub fn from_nsf(flags: u8) -> Region {
+        // Bit 1 means the file supports both regions.
+        if flags & 0b11 == 0b01 {
+            Region::Pal
+        } else {
+            Region::Ntsc
+        }
+    }
+
+    /// Returns the master clock rate in Hz.
+    pub fn master_clock_rate(self) -> u64 {
+        match self {
+            Region::Ntsc => 21_477_272,
+            Region::Pal | Region::Dendy => 26_601_712,
+        }
+    }
+
+    /// Returns the number of master clock cycles per CPU cycle.
+    pub fn cpu_divider(self) -> u64 {
+        match self {
+            Region::Ntsc => 12,
+            Region::Pal => 16,
+            Region::Dendy => 15,
+        }
+    }
+
+    /// Returns the number of master clock cycles per PPU dot.
+    pub fn ppu_divider(self) -> u64 {
+        match self {
+            Region::Ntsc => 4,
+            Region::Pal | Region::Dendy => 5,
+        }
+    }
+
+    /// Returns the CPU (and APU) clock rate in Hz, rounded to the nearest
+    /// whole number.
+    pub fn cpu_clock_rate(self) -> u32 {
+        let divider = self.cpu_divider();
+        ((self.master_clock_rate() + divider / 2) / divider) as u32
+    }
+
+    /// Returns the number of scanlines per frame.
+    pub fn scanlines(self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// Returns the number of scanlines of vblank, i.e., between the vblank
+    /// flag being set and the pre-render scanline.
+    pub fn vblank_scanlines(self) -> u16 {
+        match self {
+            Region::Ntsc => 20,
+            Region::Pal => 70,
+            Region::Dendy => 20,
+        }
+    }
+
+    /// Returns true if the APU uses PAL's noise and DMC rates and frame
+    /// counter timing. The Dendy uses NTSC's.
+    pub fn has_pal_apu(self) -> bool {
+        self == Region::Pal
+    }
+}
@@ -2,61 +2,245 @@
 // the files.
 #![cfg_attr(test, allow(dead_code))]
 
-use crate::{cpu, Emu};
+#[cfg(test)]
+mod tests;
 
+use std::{cmp::Ordering, collections::BinaryHeap, mem};
+
+use crate::{
+    apu,
+    cpu::{self, IrqSource},
+    emu, nsf, ppu,
+    region::Region,
+    Emu,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EventKind {
+    /// The reset sequence at power on.
     Reset,
-    Unreachable,
+    /// The reset button was pressed.
+    SoftReset,
+    /// The power was turned off and on again.
+    PowerCycle,
+    /// An OAM DMA from the given page.
+    OamDma(u8),
+    /// A call to an NSF's PLAY routine.
+    NsfPlay,
+    /// Vblank starts. The PPU finishes the frame and pulls the CPU's NMI line
+    /// if it's enabled.
+    Nmi,
+    /// The IRQ line from the given source might change, e.g., the APU's
+    /// frame counter is about to set its IRQ flag.
+    Irq(IrqSource),
+    /// The PPU finished a frame, and the next one starts.
+    FrameEnd,
+}
+
+impl EventKind {
+    /// Returns true if `self` and `other` are the same kind of event,
+    /// ignoring their data.
+    fn is(self, other: EventKind) -> bool {
+        mem::discriminant(&self) == mem::discriminant(&other)
+    }
 }
 
 struct Event {
     kind: EventKind,
-    tick: u64,
+    /// The master clock cycle the event is due on.
+    clock: u64,
+    /// The number of events queued before this one. It breaks ties so that
+    /// events that are due on the same tick come out in the order they were
+    /// queued.
+    seq: u64,
+}
+
+impl Event {
+    fn key(&self) -> (u64, u64) {
+        (self.clock, self.seq)
+    }
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        self.key() == other.key()
+    }
 }
 
-// TODO: Store events unsorted and use absolute time for events (the current
-// already does that). Keep the soonest event cached (for fast checking). Then
-// all the operations become linear scans (can use swap_remove for deque).
-// Potential optimization: keep the local copy of the number of cycles until
-// the next event in the CPU loop so you don't have keep comparing to the scheduler time (then
-// the local copy needs to be updated whenever there's a reschedule).
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Event) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    // BinaryHeap is a max-heap, so the soonest event has to compare as the
+    // greatest.
+    fn cmp(&self, other: &Event) -> Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+/// Events are kept in a binary heap with the soonest on top, so queueing and
+/// popping are O(log n). They're due on master clock cycles, so events from
+/// the PPU can land on the dot they happen on instead of being rounded to a
+/// CPU cycle. The soonest one is cached so that checking for due events is a
+/// single comparison.
 pub struct Scheduler {
-    events: Vec<Event>,
+    events: BinaryHeap<Event>,
+    /// The number of events queued since power on.
+    seq: u64,
+    /// The master clock cycle of the soonest event, or `u64::MAX` if there
+    /// aren't any.
+    next: u64,
+    /// The number of CPU cycles since power on.
     ticks: u64,
+    /// The number of master clock cycles since power on.
+    master_clock: u64,
+    /// The number of master clock cycles that have passed toward the next
+    /// CPU cycle when stepping by master clock cycles.
+    phase: u64,
+    region: Region,
 }
 
 impl Scheduler {
     pub fn new() -> Scheduler {
         Scheduler {
-            events: vec![Event {
-                kind: EventKind::Unreachable,
-                tick: u64::MAX,
-            }],
+            events: BinaryHeap::new(),
+            seq: 0,
+            next: u64::MAX,
             ticks: 0,
+            master_clock: 0,
+            phase: 0,
+            region: Region::Ntsc,
         }
     }
+
+    /// Queues an event for master clock cycle `clock`.
+    fn push(&mut self, kind: EventKind, clock: u64) {
+        self.events.push(Event { kind, clock, seq: self.seq });
+        self.seq += 1;
+        self.next = self.next.min(clock);
+    }
+
+    /// Removes and returns the soonest event if it's due on or before
+    /// `clock`. Events that are due on the same cycle come out in the order
+    /// they were queued.
+    fn pop(&mut self, clock: u64) -> Option<EventKind> {
+        if self.next > clock {
+            return None;
+        }
+        let kind = self.events.pop()?.kind;
+        self.update_next();
+        Some(kind)
+    }
+
+    /// Removes the events of the same kind as `kind`.
+    fn cancel(&mut self, kind: EventKind) {
+        self.events.retain(|event| !event.kind.is(kind));
+        self.update_next();
+    }
+
+    fn update_next(&mut self) {
+        self.next = self.events.peek().map_or(u64::MAX, |event| event.clock);
+    }
 }
 
 pub fn tick(emu: &mut Emu) {
-    emu.scheduler.ticks += 1;
+    let scheduler = &mut emu.scheduler;
+    scheduler.ticks += 1;
+    // Master clock cycles that were stepped through already count toward
+    // this CPU cycle.
+    scheduler.master_clock += scheduler.region.cpu_divider() - scheduler.phase;
+    scheduler.phase = 0;
 }
 
+/// Advances the master clock by one cycle. Returns true if a CPU cycle's
+/// worth of master clock cycles has passed, i.e., the CPU should run a cycle.
+pub fn tick_master_clock(emu: &mut Emu) -> bool {
+    let scheduler = &mut emu.scheduler;
+    scheduler.master_clock += 1;
+    scheduler.phase += 1;
+    scheduler.phase == scheduler.region.cpu_divider()
+}
+
+/// Returns the number of CPU cycles since power on.
+pub fn ticks(emu: &Emu) -> u64 {
+    emu.scheduler.ticks
+}
+
+/// Returns the number of master clock cycles since power on.
+pub fn master_clock(emu: &Emu) -> u64 {
+    emu.scheduler.master_clock
+}
+
+pub fn region(emu: &Emu) -> Region {
+    emu.scheduler.region
+}
+
+/// Sets the region, which sets how many master clock cycles each CPU cycle
+/// takes.
+pub fn set_region(emu: &mut Emu, region: Region) {
+    emu.scheduler.region = region;
+}
+
+/// Returns the master clock cycle that the next CPU cycle starts on. When
+/// stepping by master clock cycles, the master clock has already counted the
+/// next CPU cycle's own cycles by the time it runs.
+fn now(emu: &Emu) -> u64 {
+    emu.scheduler.master_clock - emu.scheduler.phase
+}
+
+/// Returns true if an event is due.
+#[inline]
+pub fn is_due(emu: &Emu) -> bool {
+    emu.scheduler.next <= now(emu)
+}
+
+/// Queues an event `offset` CPU cycles from now.
 pub fn queue(emu: &mut Emu, kind: EventKind, offset: u64) {
-    let tick = emu.scheduler.ticks + offset;
-    for i in 0..emu.scheduler.events.len() {
-        if tick <= emu.scheduler.events[i].tick {
-            emu.scheduler.events.insert(i, Event { kind, tick });
-            return;
-        }
-    }
+    let clock = now(emu) + offset * emu.scheduler.region.cpu_divider();
+    emu.scheduler.push(kind, clock);
+}
+
+/// Queues an event for master clock cycle `clock`. It's handled before the
+/// first instruction that starts on or after it.
+pub fn queue_at(emu: &mut Emu, kind: EventKind, clock: u64) {
+    emu.scheduler.push(kind, clock);
+}
+
+/// Removes the queued events of the same kind as `kind`. The data of `kind`
+/// (e.g., the page of an OAM DMA) is ignored.
+pub fn cancel(emu: &mut Emu, kind: EventKind) {
+    emu.scheduler.cancel(kind);
+}
+
+/// Replaces the queued events of the same kind as `kind` with one that's
+/// `offset` CPU cycles from now.
+pub fn reschedule(emu: &mut Emu, kind: EventKind, offset: u64) {
+    cancel(emu, kind);
+    queue(emu, kind, offset);
 }
 
 pub fn handle_events(emu: &mut Emu) {
-    while emu.scheduler.events[0].tick <= emu.scheduler.ticks {
-        match emu.scheduler.events[0].kind {
-            EventKind::Reset => cpu::reset(emu),
-            EventKind::Unreachable => unreachable!(),
-        }
-        emu.scheduler.events.remove(0);
+    // Remove each event before handling it since handlers can queue new
+    // events.
+    while let Some(kind) = emu.scheduler.pop(now(emu)) {
+        handle(emu, kind);
+    }
+}
+
+fn handle(emu: &mut Emu, kind: EventKind) {
+    match kind {
+        EventKind::Reset => cpu::reset(emu),
+        EventKind::SoftReset => emu::soft_reset(emu),
+        EventKind::PowerCycle => emu::power_cycle(emu),
+        EventKind::OamDma(page) => cpu::oam_dma(emu, page),
+        EventKind::NsfPlay => nsf::play(emu),
+        EventKind::Nmi => ppu::start_vblank(emu),
+        EventKind::Irq(IrqSource::Apu) => apu::update_irq(emu),
+        EventKind::FrameEnd => ppu::start_frame(emu),
     }
 }
@@ -0,0 +1,56 @@
+use super::{EventKind, Scheduler};
+
+#[test]
+fn pop_in_tick_order() {
+    let mut scheduler = Scheduler::new();
+    scheduler.push(EventKind::NsfPlay, 30);
+    scheduler.push(EventKind::OamDma(2), 10);
+    scheduler.push(EventKind::Reset, 20);
+
+    assert_eq!(scheduler.pop(9), None);
+    assert_eq!(scheduler.pop(25), Some(EventKind::OamDma(2)));
+    assert_eq!(scheduler.pop(25), Some(EventKind::Reset));
+    assert_eq!(scheduler.pop(25), None);
+    assert_eq!(scheduler.next, 30);
+    assert_eq!(scheduler.pop(30), Some(EventKind::NsfPlay));
+    assert_eq!(scheduler.next, u64::MAX);
+}
+
+#[test]
+fn ties_pop_in_queue_order() {
+    let mut scheduler = Scheduler::new();
+    scheduler.push(EventKind::OamDma(1), 5);
+    scheduler.push(EventKind::NsfPlay, 5);
+    scheduler.push(EventKind::OamDma(2), 5);
+
+    assert_eq!(scheduler.pop(5), Some(EventKind::OamDma(1)));
+    assert_eq!(scheduler.pop(5), Some(EventKind::NsfPlay));
+    assert_eq!(scheduler.pop(5), Some(EventKind::OamDma(2)));
+}
+
+#[test]
+fn cancel_by_kind() {
+    let mut scheduler = Scheduler::new();
+    scheduler.push(EventKind::OamDma(1), 5);
+    scheduler.push(EventKind::NsfPlay, 10);
+    scheduler.push(EventKind::OamDma(2), 15);
+
+    // The data doesn't have to match.
+    scheduler.cancel(EventKind::OamDma(0));
+    assert_eq!(scheduler.next, 10);
+    assert_eq!(scheduler.pop(20), Some(EventKind::NsfPlay));
+    assert_eq!(scheduler.pop(20), None);
+}
+
+#[test]
+fn many_events() {
+    let mut scheduler = Scheduler::new();
+    for tick in (0..100).rev() {
+        scheduler.push(EventKind::OamDma(tick as u8), tick);
+    }
+    for tick in 0..100 {
+        assert_eq!(scheduler.next, tick);
+        assert_eq!(scheduler.pop(tick), Some(EventKind::OamDma(tick as u8)));
+    }
+    assert_eq!(scheduler.pop(u64::MAX), None);
+}
@@ -0,0 +1,216 @@
+//! Labels loaded from ca65/ld65 .dbg files, FCEUX .nl files, and Mesen .mlb
+//! files. Labels in PRG ROM are keyed by their offset into it, so they're
+//! only used when their bank is mapped in.
+
+#![cfg_attr(test, allow(dead_code))]
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::{cart, Emu};
+
+/// The size of the iNES header in bytes. Offsets in .dbg files are into the
+/// whole .nes file.
+const INES_HEADER_SIZE: usize = 16;
+/// The size of the banks that FCEUX's .nl files are split into.
+const NL_BANK_SIZE: usize = 0x4000;
+
+#[derive(Default)]
+pub struct Symbols {
+    /// Labels of PRG ROM offsets.
+    prg: HashMap<usize, String>,
+    /// Labels of CPU addresses outside of PRG ROM, e.g., RAM and registers.
+    cpu: HashMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Symbols {
+        Symbols::default()
+    }
+
+    /// Loads the symbol file at `path`. The format is picked by the
+    /// extension. FCEUX's .nl files hold the labels of one bank, which is
+    /// taken from the file name, e.g., `game.nes.1.nl` or `game.nes.ram.nl`.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let text = fs::read_to_string(path)?;
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        match extension {
+            Some("dbg") => self.parse_dbg(&text),
+            Some("mlb") => self.parse_mlb(&text),
+            Some("nl") => {
+                let stem = path.file_stem().and_then(|stem| stem.to_str());
+                let bank = stem
+                    .and_then(|stem| stem.rsplit('.').next())
+                    .and_then(|bank| bank.parse().ok());
+                self.parse_nl(&text, bank);
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "expected a .dbg, .nl, or .mlb file",
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Labels the byte at `offset` in PRG ROM.
+    pub fn add_prg_label(&mut self, offset: usize, label: &str) {
+        self.prg.insert(offset, label.to_string());
+    }
+
+    /// Labels `addr` wherever it isn't mapped to PRG ROM.
+    pub fn add_cpu_label(&mut self, addr: u16, label: &str) {
+        self.cpu.insert(addr, label.to_string());
+    }
+
+    /// Parses the labels in a ca65/ld65 debug file. Equates aren't loaded
+    /// since most of them are constants, not addresses.
+    pub fn parse_dbg(&mut self, text: &str) {
+        // Maps segment IDs to their start address and offset in the file.
+        let mut segments = HashMap::new();
+        let mut labels = Vec::new();
+        for line in text.lines() {
+            let Some((kind, fields)) = line.split_once('\t') else {
+                continue;
+            };
+            let fields = parse_dbg_fields(fields);
+            let field = |key| fields.get(key).copied();
+            match kind {
+                "seg" => {
+                    let (Some(id), Some(start)) =
+                        (field("id"), field("start"))
+                    else {
+                        continue;
+                    };
+                    let start = parse_int(start);
+                    let ooffs = field("ooffs").and_then(parse_int);
+                    segments.insert(id, (start, ooffs));
+                }
+                "sym" if field("type") == Some("lab") => {
+                    let (Some(name), Some(val)) =
+                        (field("name"), field("val"))
+                    else {
+                        continue;
+                    };
+                    labels.push((name, parse_int(val), field("seg")));
+                }
+                _ => (),
+            }
+        }
+
+        for (name, val, seg) in labels {
+            let Some(val) = val else { continue };
+            // Segments that are written to the .nes file are in PRG ROM.
+            let offset = match seg.and_then(|seg| segments.get(seg)) {
+                Some(&(Some(start), Some(ooffs))) => {
+                    (ooffs + val).checked_sub(start + INES_HEADER_SIZE)
+                }
+                _ => None,
+            };
+            match offset {
+                Some(offset) => self.add_prg_label(offset, name),
+                None => self.add_cpu_label(val as u16, name),
+            }
+        }
+    }
+
+    /// Parses the labels in an FCEUX name list. `bank` is the 16 KB bank of
+    /// PRG ROM that the file is for, or `None` for RAM.
+    pub fn parse_nl(&mut self, text: &str, bank: Option<usize>) {
+        for line in text.lines() {
+            let mut fields = line.split('#');
+            let (Some(addr), Some(label)) = (fields.next(), fields.next())
+            else {
+                continue;
+            };
+            // Arrays have their size after a slash, e.g., `$0300/10`.
+            let addr = addr.split('/').next().unwrap();
+            let Some(addr) = addr
+                .strip_prefix('$')
+                .and_then(|addr| u16::from_str_radix(addr, 16).ok())
+            else {
+                continue;
+            };
+            if label.is_empty() {
+                continue;
+            }
+            match bank {
+                Some(bank) => self.add_prg_label(
+                    bank * NL_BANK_SIZE + addr as usize % NL_BANK_SIZE,
+                    label,
+                ),
+                None => self.add_cpu_label(addr, label),
+            }
+        }
+    }
+
+    /// Parses the labels in a Mesen label file, e.g., `P:0123:Reset`.
+    pub fn parse_mlb(&mut self, text: &str) {
+        for line in text.lines() {
+            let mut fields = line.split(':');
+            let (Some(kind), Some(addr), Some(label)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            // Labels can span a range, e.g., `R:0300-030F:Buffer`.
+            let addr = addr.split('-').next().unwrap();
+            let Ok(addr) = usize::from_str_radix(addr, 16) else {
+                continue;
+            };
+            if label.is_empty() {
+                continue;
+            }
+            // Mesen 2 spells out the memory types.
+            match kind {
+                "P" | "NesPrgRom" => self.add_prg_label(addr, label),
+                "R" | "NesInternalRam" | "G" | "NesMemory" => {
+                    self.add_cpu_label(addr as u16, label)
+                }
+                "S" | "W" | "NesSaveRam" | "NesWorkRam" => self
+                    .add_cpu_label(0x6000u16.wrapping_add(addr as u16), label),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Parses the comma-separated `key=value` fields of a line in a .dbg file.
+/// Values can be quoted.
+fn parse_dbg_fields(fields: &str) -> HashMap<&str, &str> {
+    let mut map = HashMap::new();
+    let mut rest = fields;
+    while let Some((key, value)) = rest.split_once('=') {
+        let (value, next) = match value.strip_prefix('"') {
+            Some(value) => {
+                let end = value.find('"').unwrap_or(value.len());
+                let next = value[end..].find(',').map(|i| end + i + 1);
+                (&value[..end], next.map_or("", |i| &value[i..]))
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        map.insert(key, value);
+        rest = next;
+    }
+    map
+}
+
+/// Parses a decimal or `0x`-prefixed hex number.
+fn parse_int(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Returns the label of `addr` with the banks that are mapped in now.
+pub fn label(emu: &Emu, addr: u16) -> Option<&str> {
+    let symbols = &emu.symbols;
+    cart::prg_rom_offset(emu, addr)
+        .and_then(|offset| symbols.prg.get(&offset))
+        .or_else(|| symbols.cpu.get(&addr))
+        .map(String::as_str)
+}
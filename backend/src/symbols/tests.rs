@@ -0,0 +1,51 @@
+use crate::symbols::Symbols;
+
+#[test]
+fn dbg() {
+    let mut symbols = Symbols::new();
+    symbols.parse_dbg(
+        "version\tmajor=2,minor=0\n\
+         seg\tid=0,name=\"CODE\",start=0x00C000,size=0x0100,addrsize=absolute,type=ro,oname=\"game.nes\",ooffs=16400\n\
+         seg\tid=1,name=\"BSS\",start=0x000300,size=0x0010,addrsize=absolute,type=rw\n\
+         sym\tid=0,name=\"UpdatePlayer\",addrsize=absolute,scope=0,def=1,ref=2,val=0xC010,seg=0,type=lab\n\
+         sym\tid=1,name=\"player_x\",addrsize=absolute,scope=0,def=3,val=0x300,seg=1,type=lab\n\
+         sym\tid=2,name=\"SPEED\",addrsize=zeropage,scope=0,def=4,val=0x3,type=equ\n",
+    );
+
+    // The CODE segment is at $4000 in PRG ROM.
+    assert_eq!(symbols.prg[&0x4010], "UpdatePlayer");
+    assert_eq!(symbols.cpu[&0x0300], "player_x");
+    assert!(!symbols.cpu.contains_key(&0x0003));
+}
+
+#[test]
+fn nl() {
+    let mut symbols = Symbols::new();
+    symbols.parse_nl("$C000#Reset#The reset handler\n$C004##\n", Some(1));
+    symbols.parse_nl("$0300/10#buffer#\n$2000#PPUCTRL#\n", None);
+
+    assert_eq!(symbols.prg[&0x4000], "Reset");
+    assert!(!symbols.prg.contains_key(&0x4004));
+    assert_eq!(symbols.cpu[&0x0300], "buffer");
+    assert_eq!(symbols.cpu[&0x2000], "PPUCTRL");
+}
+
+#[test]
+fn mlb() {
+    let mut symbols = Symbols::new();
+    symbols.parse_mlb(
+        "P:0010:UpdatePlayer:Moves the player\n\
+         R:0300-030F:buffer\n\
+         G:2000:PPUCTRL\n\
+         W:0010:save_slot\n\
+         NesPrgRom:4000:Reset\n\
+         P:0020::A comment without a label\n",
+    );
+
+    assert_eq!(symbols.prg[&0x0010], "UpdatePlayer");
+    assert_eq!(symbols.prg[&0x4000], "Reset");
+    assert!(!symbols.prg.contains_key(&0x0020));
+    assert_eq!(symbols.cpu[&0x0300], "buffer");
+    assert_eq!(symbols.cpu[&0x2000], "PPUCTRL");
+    assert_eq!(symbols.cpu[&0x6010], "save_slot");
+}
@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::apu::{Channel, SAMPLE_RATE};
+
+/// The size of the RIFF and format chunks plus the data chunk's header.
+const HEADER_SIZE: u32 = 44;
+const BITS_PER_SAMPLE: u16 = 16;
+/// The most sample data a file can hold. The RIFF chunk's size is a 32-bit
+/// number that includes the rest of the header.
+const MAX_DATA_SIZE: u32 = u32::MAX - (HEADER_SIZE - 8);
+
+/// Writes 16-bit PCM samples to a WAV file. The header's sizes are filled in
+/// by `finish`.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    /// The size of the sample data in bytes.
+    data_size: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(
+        mut writer: W,
+        channels: u16,
+        sample_rate: u32,
+    ) -> io::Result<WavWriter<W>> {
+        let block_align = channels * BITS_PER_SAMPLE / 8;
+        writer.write_all(b"RIFF")?;
+        // The RIFF chunk's size is written by finish.
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        // PCM
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&channels.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        // The data chunk's size is written by finish.
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(WavWriter { writer, data_size: 0 })
+    }
+
+    /// Writes interleaved samples. Returns an error without writing any of
+    /// them if the file would grow past 4 GiB, about 6.7 hours of stereo
+    /// audio.
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let data_size = u32::try_from(2 * samples.len())
+            .ok()
+            .and_then(|size| self.data_size.checked_add(size))
+            .filter(|&size| size <= MAX_DATA_SIZE)
+            .ok_or_else(|| {
+                io::Error::other("WAV files can't be larger than 4 GiB")
+            })?;
+
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_size = data_size;
+        Ok(())
+    }
+
+    /// Fills in the header's sizes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_sizes()?;
+        Ok(self.writer)
+    }
+
+    /// Fills in the header's sizes for the samples written so far.
+    fn write_sizes(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(HEADER_SIZE as u64 - 4))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+/// Records the emulator's audio output to a stereo WAV file and, optionally,
+/// each channel's unmixed output to a mono WAV file next to it.
+pub struct Recorder {
+    mix: WavWriter<BufWriter<File>>,
+    stems: Vec<WavWriter<BufWriter<File>>>,
+    /// The first error hit while recording. Samples are recorded as they're
+    /// filled, which can't fail, so errors are held until the recording is
+    /// finished. Nothing more is recorded after one.
+    error: Option<io::Error>,
+    /// True once `finish` has filled in the headers.
+    finished: bool,
+}
+
+impl Recorder {
+    /// Creates a recorder that writes to `path`. If `stems` is true, the
+    /// channels are also written to files named after `path` and the
+    /// channel, e.g., song-pulse1.wav.
+    pub fn create(path: &Path, stems: bool) -> io::Result<Recorder> {
+        let create = |path: &Path, channels| {
+            let file = BufWriter::new(File::create(path)?);
+            WavWriter::new(file, channels, SAMPLE_RATE as u32)
+        };
+
+        let mix = create(path, 2)?;
+        let stems = if stems {
+            Channel::ALL
+                .iter()
+                .map(|channel| create(&stem_path(path, *channel), 1))
+                .collect::<io::Result<_>>()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Recorder { mix, stems, error: None, finished: false })
+    }
+
+    /// Records interleaved stereo samples and the per-channel samples they
+    /// were mixed from.
+    pub(crate) fn write(&mut self, mix: &[i16], waveforms: &[Vec<i16>]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let result = self.mix.write(mix).and_then(|()| {
+            self.stems
+                .iter_mut()
+                .zip(waveforms)
+                .try_for_each(|(stem, waveform)| stem.write(waveform))
+        });
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
+
+    /// Finishes the files, returning the first error hit while recording.
+    /// The files are finished even if there was one so that the audio before
+    /// it (e.g., up to the size limit) can still be played.
+    pub fn finish(mut self) -> io::Result<()> {
+        let result = self.write_sizes();
+        self.finished = true;
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    fn write_sizes(&mut self) -> io::Result<()> {
+        self.mix.write_sizes()?;
+        self.stems.iter_mut().try_for_each(WavWriter::write_sizes)
+    }
+}
+
+impl Drop for Recorder {
+    /// Finishes the files if `finish` wasn't called, e.g., because the
+    /// emulator was dropped while recording. Errors are ignored.
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.write_sizes();
+        }
+    }
+}
+
+fn stem_path(path: &Path, channel: Channel) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}-{}.wav", channel.name()))
+}
@@ -0,0 +1,54 @@
+use std::{env, fs, io::Cursor, process};
+
+use crate::wav::{Recorder, WavWriter, MAX_DATA_SIZE};
+
+#[test]
+fn header() {
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), 2, 44100).unwrap();
+    wav.write(&[1, -1, 0x1234, -0x1234]).unwrap();
+    let bytes = wav.finish().unwrap().into_inner();
+
+    assert_eq!(bytes.len(), 44 + 8);
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(bytes[4..8], 44u32.to_le_bytes());
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    // PCM with two channels.
+    assert_eq!(bytes[20..24], [1, 0, 2, 0]);
+    assert_eq!(bytes[24..28], 44100u32.to_le_bytes());
+    assert_eq!(bytes[28..32], (44100u32 * 4).to_le_bytes());
+    // Four byte blocks of 16-bit samples.
+    assert_eq!(bytes[32..36], [4, 0, 16, 0]);
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(bytes[40..44], 8u32.to_le_bytes());
+    assert_eq!(bytes[44..], [0x01, 0x00, 0xFF, 0xFF, 0x34, 0x12, 0xCC, 0xED]);
+}
+
+#[test]
+fn size_limit() {
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), 2, 44100).unwrap();
+    // Pretend the file is almost full instead of writing 4 GiB.
+    wav.data_size = MAX_DATA_SIZE - 4;
+    wav.write(&[1, 2]).unwrap();
+    assert!(wav.write(&[3]).is_err());
+    let bytes = wav.finish().unwrap().into_inner();
+
+    assert_eq!(bytes[4..8], u32::MAX.to_le_bytes());
+    assert_eq!(bytes[40..44], MAX_DATA_SIZE.to_le_bytes());
+    assert_eq!(bytes[44..], [0x01, 0x00, 0x02, 0x00]);
+}
+
+#[test]
+fn recorder_finishes_on_drop() {
+    let path = env::temp_dir().join(format!(
+        "dunes_recorder_finishes_on_drop_{}.wav",
+        process::id()
+    ));
+    let mut recorder = Recorder::create(&path, false).unwrap();
+    recorder.write(&[1, 2, 3, 4], &[]);
+    drop(recorder);
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(bytes[4..8], 44u32.to_le_bytes());
+    assert_eq!(bytes[40..44], 8u32.to_le_bytes());
+}
@@ -1,12 +1,15 @@
 mod apu;
+mod cpu;
 mod instr;
+mod ppu;
 
 use std::fs;
 
-use backend::Emu;
+use crate::harness::{self, Outcome};
 
 macro_rules! blargg_test {
-    ($name:ident, $path:expr) => {
+    ($(#[$attr:meta])* $name:ident, $path:expr) => {
+        $(#[$attr])*
         #[test]
         fn $name() {
             crate::blargg::run($path);
@@ -15,37 +18,11 @@ macro_rules! blargg_test {
 }
 use blargg_test;
 
+/// Runs the ROM at `path` under `../roms` and asserts that it passed. It
+/// fails if the ROM doesn't report a result within 30 seconds of emulated
+/// time. A status of 0 passes even if the text doesn't say "Passed".
 fn run(path: &str) {
-    const STATUS_ADDR: u16 = 0x6000;
-    const OUTPUT_ADDR: u16 = 0x6004;
-    const RUNNING_STATUS: u8 = 0x80;
-
     let rom = fs::read(format!("../roms/{path}")).unwrap();
-    let mut emu = Emu::new(&rom);
-
-    // Run the reset sequence.
-    emu.step();
-
-    let mut status = emu.peek(STATUS_ADDR).unwrap();
-    while status != RUNNING_STATUS {
-        emu.step();
-        status = emu.peek(STATUS_ADDR).unwrap();
-    }
-
-    while status == RUNNING_STATUS {
-        emu.step();
-        status = emu.peek(STATUS_ADDR).unwrap();
-    }
-
-    let mut output = Vec::new();
-    let mut addr = OUTPUT_ADDR;
-    let mut byte = emu.peek(addr).unwrap();
-    while byte != b'\0' {
-        output.push(byte);
-        addr += 1;
-        byte = emu.peek(addr).unwrap();
-    }
-
-    let output = String::from_utf8_lossy(&output);
-    assert!(output.contains("Passed"), "{}", output);
+    let (outcome, _) = harness::run(&rom, harness::DEFAULT_TIMEOUT_FRAMES);
+    assert_eq!(outcome, Outcome::Passed);
 }
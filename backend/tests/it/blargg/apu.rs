@@ -6,5 +6,18 @@ blargg_test!(irq_flag, "apu_test/3-irq_flag.nes");
 blargg_test!(jitter, "apu_test/4-jitter.nes");
 // blargg_test!(len_timing, "apu_test/5-len_timing.nes");
 // blargg_test!(irq_flag_timing, "apu_test/6-irq_flag_timing.nes");
-// blargg_test!(dmc_basics, "apu_test/7-dmc_basics.nes");
-// blargg_test!(dmc_rates, "apu_test/8-dmc_rates.nes");
+blargg_test!(dmc_basics, "apu_test/7-dmc_basics.nes");
+blargg_test!(dmc_rates, "apu_test/8-dmc_rates.nes");
+
+// A DMC DMA repeats the read it halts, and an OAM DMA has to realign around
+// it.
+blargg_test!(dma_2007_read, "dmc_dma_during_read4/dma_2007_read.nes");
+blargg_test!(dma_2007_write, "dmc_dma_during_read4/dma_2007_write.nes");
+blargg_test!(dma_4016_read, "dmc_dma_during_read4/dma_4016_read.nes");
+blargg_test!(double_2007_read, "dmc_dma_during_read4/double_2007_read.nes");
+blargg_test!(read_write_2007, "dmc_dma_during_read4/read_write_2007.nes");
+blargg_test!(sprdma_and_dmc_dma, "sprdma_and_dmc_dma/sprdma_and_dmc_dma.nes");
+blargg_test!(
+    sprdma_and_dmc_dma_512,
+    "sprdma_and_dmc_dma/sprdma_and_dmc_dma_512.nes"
+);
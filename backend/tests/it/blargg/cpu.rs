@@ -0,0 +1,37 @@
+use crate::blargg::blargg_test;
+
+blargg_test!(exec_space_apu, "cpu_exec_space/test_cpu_exec_space_apu.nes");
+blargg_test!(
+    #[ignore = "needs PPU"]
+    exec_space_ppuio,
+    "cpu_exec_space/test_cpu_exec_space_ppuio.nes"
+);
+// Dummy reads are counted through the side effects of reading $2002 and
+// $2007.
+blargg_test!(
+    #[ignore = "needs PPU"]
+    dummy_reads,
+    "cpu_dummy_reads/cpu_dummy_reads.nes"
+);
+
+// The NMI suites sync to vblank by polling $2002, which doesn't return the
+// vblank flag yet.
+blargg_test!(cli_latency, "cpu_interrupts_v2/rom_singles/1-cli_latency.nes");
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_and_brk,
+    "cpu_interrupts_v2/rom_singles/2-nmi_and_brk.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_and_irq,
+    "cpu_interrupts_v2/rom_singles/3-nmi_and_irq.nes"
+);
+blargg_test!(irq_and_dma, "cpu_interrupts_v2/rom_singles/4-irq_and_dma.nes");
+// Taken branches that don't cross a page don't poll for IRQs on their last
+// cycle, which isn't emulated yet.
+blargg_test!(
+    #[ignore = "needs the branch IRQ delay"]
+    branch_delays_irq,
+    "cpu_interrupts_v2/rom_singles/5-branch_delays_irq.nes"
+);
@@ -0,0 +1,194 @@
+use crate::blargg::blargg_test;
+
+// These need the PPU's timing, status flags, and sprites, which don't exist
+// yet. Take the ignores off as they're filled in.
+
+blargg_test!(
+    #[ignore = "needs PPU"]
+    vbl_basics,
+    "ppu_vbl_nmi/rom_singles/01-vbl_basics.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    vbl_set_time,
+    "ppu_vbl_nmi/rom_singles/02-vbl_set_time.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    vbl_clear_time,
+    "ppu_vbl_nmi/rom_singles/03-vbl_clear_time.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_control,
+    "ppu_vbl_nmi/rom_singles/04-nmi_control.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_timing,
+    "ppu_vbl_nmi/rom_singles/05-nmi_timing.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    suppression,
+    "ppu_vbl_nmi/rom_singles/06-suppression.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_on_timing,
+    "ppu_vbl_nmi/rom_singles/07-nmi_on_timing.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_off_timing,
+    "ppu_vbl_nmi/rom_singles/08-nmi_off_timing.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    even_odd_frames,
+    "ppu_vbl_nmi/rom_singles/09-even_odd_frames.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    even_odd_timing,
+    "ppu_vbl_nmi/rom_singles/10-even_odd_timing.nes"
+);
+
+blargg_test!(
+    #[ignore = "needs PPU"]
+    frame_basics,
+    "vbl_nmi_timing/1.frame_basics.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    vbl_timing,
+    "vbl_nmi_timing/2.vbl_timing.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    old_even_odd_frames,
+    "vbl_nmi_timing/3.even_odd_frames.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    vbl_clear_timing,
+    "vbl_nmi_timing/4.vbl_clear_timing.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_suppression,
+    "vbl_nmi_timing/5.nmi_suppression.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    nmi_disable,
+    "vbl_nmi_timing/6.nmi_disable.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    old_nmi_timing,
+    "vbl_nmi_timing/7.nmi_timing.nes"
+);
+
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_basics,
+    "sprite_hit_tests_2005.10.05/01.basics.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_alignment,
+    "sprite_hit_tests_2005.10.05/02.alignment.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_corners,
+    "sprite_hit_tests_2005.10.05/03.corners.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_flip,
+    "sprite_hit_tests_2005.10.05/04.flip.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_left_clip,
+    "sprite_hit_tests_2005.10.05/05.left_clip.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_right_edge,
+    "sprite_hit_tests_2005.10.05/06.right_edge.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_screen_bottom,
+    "sprite_hit_tests_2005.10.05/07.screen_bottom.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_double_height,
+    "sprite_hit_tests_2005.10.05/08.double_height.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_timing_basics,
+    "sprite_hit_tests_2005.10.05/09.timing_basics.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_timing_order,
+    "sprite_hit_tests_2005.10.05/10.timing_order.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_hit_edge_timing,
+    "sprite_hit_tests_2005.10.05/11.edge_timing.nes"
+);
+
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_overflow_basics,
+    "sprite_overflow_tests/1.Basics.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_overflow_details,
+    "sprite_overflow_tests/2.Details.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_overflow_timing,
+    "sprite_overflow_tests/3.Timing.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_overflow_obscure,
+    "sprite_overflow_tests/4.Obscure.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    sprite_overflow_emulator,
+    "sprite_overflow_tests/5.Emulator.nes"
+);
+
+blargg_test!(
+    #[ignore = "needs PPU"]
+    ppu_open_bus,
+    "ppu_open_bus/ppu_open_bus.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    read_buffer,
+    "ppu_read_buffer/test_ppu_read_buffer.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    oam_read,
+    "oam_read/oam_read.nes"
+);
+blargg_test!(
+    #[ignore = "needs PPU"]
+    oam_stress,
+    "oam_stress/oam_stress.nes"
+);
@@ -0,0 +1,119 @@
+use std::{env, fs, io};
+
+use backend::{
+    cdl::{CODE, DATA, INDIRECT_CODE, INDIRECT_DATA, READ, RENDERED, WINDOW},
+    Emu,
+};
+
+use crate::make_rom;
+
+fn make_code() -> Vec<u8> {
+    let mut code = vec![
+        0xA9, 0x00, // LDA #$00
+        0x85, 0x00, // STA $00
+        0xA9, 0xC0, // LDA #$C0
+        0x85, 0x01, // STA $01
+        0xA0, 0x20, // LDY #$20
+        0xB1, 0x00, // LDA ($00),Y
+        0xAD, 0x30, 0x80, // LDA $8030
+        0x6C, 0x40, 0x80, // JMP ($8040)
+    ];
+    code.resize(0x50, 0xEA);
+    code[0x40..0x42].copy_from_slice(&[0x50, 0x80]);
+    code.extend_from_slice(&[0x4C, 0x50, 0x80]); // JMP $8050
+    code
+}
+
+fn run_logged() -> Emu {
+    let mut emu = Emu::new(&make_rom(&make_code()));
+    emu.start_cdl();
+    for _ in 0..10 {
+        emu.step();
+    }
+    assert_eq!(emu.cpu_state().pc, 0x8050);
+    emu
+}
+
+#[test]
+fn marks_code_and_data() {
+    let emu = run_logged();
+    let cdl = emu.cdl().unwrap();
+    assert_eq!(cdl.prg().len(), 0x4000);
+    assert!(cdl.chr().is_empty());
+
+    // Opcodes and operands.
+    assert_eq!(cdl.prg()[0x00], CODE);
+    // Immediate operands aren't data.
+    assert_eq!(cdl.prg()[0x01], CODE);
+    assert_eq!(cdl.prg()[0x0E], CODE);
+    // $C020 is a mirror of $8020, read through the third window.
+    assert_eq!(cdl.prg()[0x20], DATA | INDIRECT_DATA | 2 << 2);
+    assert_eq!(cdl.prg()[0x30], DATA);
+    // The pointer and target of JMP ($8040).
+    assert_eq!(cdl.prg()[0x40], DATA);
+    assert_eq!(cdl.prg()[0x41], DATA);
+    assert_eq!(cdl.prg()[0x50], CODE | INDIRECT_CODE);
+    // Unused.
+    assert_eq!(cdl.prg()[0x60], 0);
+    assert!(cdl.prg().iter().all(|&flags| flags & WINDOW != 3 << 2));
+}
+
+#[test]
+fn save_and_load() {
+    let path = env::temp_dir().join("dunes_save_and_load.cdl");
+    let emu = run_logged();
+    emu.cdl().unwrap().save(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), emu.cdl().unwrap().prg());
+
+    let mut emu = Emu::new(&make_rom(&make_code()));
+    emu.load_cdl(&path).unwrap();
+    assert_eq!(emu.cdl().unwrap().prg()[0x50], CODE | INDIRECT_CODE);
+
+    // The log for this ROM is still 16 KB.
+    fs::write(&path, [0; 0x8000]).unwrap();
+    let err = emu.load_cdl(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn cycle_stepping() {
+    let mut emu = Emu::new(&make_rom(&make_code()));
+    emu.start_cdl();
+    let mut instructions = 0;
+    while instructions < 10 {
+        instructions += emu.step_cycle() as u32;
+    }
+    assert_eq!(emu.cdl().unwrap().prg(), run_logged().cdl().unwrap().prg());
+}
+
+#[test]
+fn marks_chr() {
+    let code = [
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x30, // LDA #$30
+        0x8D, 0x06, 0x20, // STA $2006
+        0xAD, 0x07, 0x20, // LDA $2007
+        0xAD, 0x07, 0x20, // LDA $2007
+        // Show the background.
+        0xA9, 0x08, // LDA #$08
+        0x8D, 0x01, 0x20, // STA $2001
+    ];
+    // Add 8 KB of CHR ROM.
+    let mut rom = make_rom(&code);
+    rom[5] = 1;
+    rom.resize(rom.len() + 0x2000, 0);
+    let mut emu = Emu::new(&rom);
+    emu.start_cdl();
+    emu.run_to_frame();
+    emu.run_to_frame();
+
+    let chr = emu.cdl().unwrap().chr();
+    assert_eq!(chr.len(), 0x2000);
+    // The nametable is all tile 0.
+    assert!(chr[..0x10].iter().all(|&flags| flags == RENDERED));
+    assert_eq!(chr[0x30], READ);
+    assert_eq!(chr[0x31], READ);
+    assert_eq!(chr[0x32], 0);
+}
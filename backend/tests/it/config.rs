@@ -0,0 +1,59 @@
+use backend::{
+    config::{Config, RamInit},
+    Emu,
+};
+
+use crate::make_rom;
+
+#[test]
+fn power_on_ram() {
+    let config = Config {
+        cpu_ram: RamInit::Ones,
+        prg_ram: RamInit::Pattern,
+        ..Config::default()
+    };
+    let mut emu = Emu::with_config(&make_rom(&[]), config);
+    assert_eq!(emu.peek(0x0000), Some(0xFF));
+    assert_eq!(emu.peek(0x07FF), Some(0xFF));
+    assert_eq!(emu.peek(0x6000), Some(0x00));
+    assert_eq!(emu.peek(0x6004), Some(0xFF));
+}
+
+#[test]
+fn power_on_ppu_ram() {
+    let code = [
+        0xA9, 0x20, // LDA #$20
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x04, // LDA #$04
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x07, 0x20, // STA $2007
+    ];
+    let config = Config {
+        vram: RamInit::Pattern,
+        palette: RamInit::Ones,
+        ..Config::default()
+    };
+    let mut emu = Emu::with_config(&make_rom(&code), config);
+    assert_eq!(emu.peek_ppu(0x2000), 0x00);
+    assert_eq!(emu.peek_ppu(0x2004), 0xFF);
+    // Palette entries are 6 bits.
+    assert_eq!(emu.peek_ppu(0x3F00), 0x3F);
+
+    while emu.cpu_state().pc != 0x8000 + code.len() as u16 {
+        emu.step();
+    }
+    assert_eq!(emu.peek_ppu(0x2004), 0x00);
+    // A power cycle fills it again.
+    emu.power_cycle();
+    emu.step();
+    assert_eq!(emu.peek_ppu(0x2004), 0xFF);
+}
+
+#[test]
+fn default_is_zeroed() {
+    let mut emu = Emu::new(&make_rom(&[]));
+    assert_eq!(emu.config(), Config::default());
+    assert_eq!(emu.peek(0x0000), Some(0x00));
+    assert_eq!(emu.peek(0x6000), Some(0x00));
+}
@@ -0,0 +1,105 @@
+use backend::{CpuState, Emu};
+
+use crate::make_rom;
+
+const CODE: [u8; 11] = [
+    0xA9, 0x42, // LDA #$42
+    0x8D, 0x00, 0x02, // STA $0200
+    0xEE, 0x00, 0x02, // INC $0200
+    0x4C, 0x05, 0x80, // JMP $8005
+];
+
+/// Steps by cycles until an instruction finishes and returns the number of
+/// cycles it took.
+fn step_instruction(emu: &mut Emu) -> usize {
+    let mut cycles = 1;
+    while !emu.step_cycle() {
+        cycles += 1;
+    }
+    cycles
+}
+
+#[test]
+fn step_cycle_stops_mid_instruction() {
+    let mut emu = Emu::new(&make_rom(&CODE));
+
+    // The reset sequence and LDA.
+    step_instruction(&mut emu);
+
+    let state = emu.cpu_state();
+    assert_eq!(state.a, 0x42);
+    assert_eq!(state.pc, 0x8002);
+
+    // STA writes on its last cycle. PC moves past each byte as it's fetched.
+    for i in 1..=3 {
+        assert!(!emu.step_cycle());
+        assert_eq!(emu.peek(0x0200), Some(0x00));
+        assert_eq!(emu.cpu_state().pc, 0x8002 + i as u16);
+        assert_eq!(emu.cpu_state().cycles, state.cycles + i);
+    }
+    assert!(emu.step_cycle());
+    assert_eq!(emu.peek(0x0200), Some(0x42));
+    assert_eq!(emu.cpu_state().pc, 0x8005);
+
+    assert_eq!(step_instruction(&mut emu), 6);
+    assert_eq!(emu.peek(0x0200), Some(0x43));
+    assert_eq!(step_instruction(&mut emu), 3);
+}
+
+#[test]
+fn step_cycle_matches_step() {
+    let mut by_instruction = Emu::new(&make_rom(&CODE));
+    let mut by_cycle = Emu::new(&make_rom(&CODE));
+
+    for _ in 0..100 {
+        by_instruction.step();
+        step_instruction(&mut by_cycle);
+        assert_eq!(by_instruction.peek(0x0200), by_cycle.peek(0x0200));
+    }
+
+    // Step finishes an instruction that's partway done.
+    by_cycle.step_cycle();
+    by_cycle.step();
+    by_instruction.step();
+    assert_eq!(by_instruction.peek(0x0200), by_cycle.peek(0x0200));
+}
+
+#[test]
+fn step_tick() {
+    let mut emu = Emu::new(&make_rom(&CODE));
+    emu.step();
+    let state = emu.cpu_state();
+    let master_clock = emu.master_clock();
+
+    // An NTSC CPU cycle is 12 master clock cycles, and STA $0200 is 4.
+    for i in 1..48 {
+        assert!(!emu.step_tick());
+        assert_eq!(emu.master_clock(), master_clock + i);
+        assert_eq!(emu.cpu_state().cycles, state.cycles + i / 12);
+    }
+    assert!(emu.step_tick());
+    assert_eq!(emu.cpu_state().cycles, state.cycles + 4);
+    assert_eq!(emu.peek(0x0200), Some(0x42));
+
+    // Stepping by a cycle finishes the one that's partway done.
+    for _ in 0..5 {
+        emu.step_tick();
+    }
+    emu.step_cycle();
+    assert_eq!(emu.master_clock(), master_clock + 60);
+    assert_eq!(emu.cpu_state().cycles, state.cycles + 5);
+}
+
+#[test]
+fn set_cpu_state() {
+    let mut emu = Emu::new(&make_rom(&CODE));
+    emu.step();
+
+    // Skip the store and the increment.
+    let state = emu.cpu_state();
+    emu.set_cpu_state(&CpuState { pc: 0x8008, ..state });
+    emu.step();
+    assert_eq!(emu.cpu_state().pc, 0x8005);
+    emu.step();
+    assert_eq!(emu.peek(0x0200), Some(0x01));
+}
@@ -0,0 +1,166 @@
+use backend::{
+    debugger::{Access, BreakReason, Watchpoint},
+    Emu,
+};
+
+use crate::make_rom;
+
+const CODE: [u8; 20] = [
+    0xEA, // NOP
+    0x20, 0x10, 0x80, // JSR $8010
+    0x8D, 0x00, 0x03, // STA $0300
+    0x4C, 0x07, 0x80, // JMP $8007
+    0xEA, 0xEA, 0xEA, 0xEA, 0xEA, 0xEA, // Padding
+    0xA9, 0x07, // LDA #$07
+    0xE8, // INX
+    0x60, // RTS
+];
+
+/// Makes an emulator that has run the reset sequence and the NOP.
+fn make_emu() -> Emu {
+    let mut emu = Emu::new(&make_rom(&CODE));
+    emu.step();
+    assert_eq!(emu.cpu_state().pc, 0x8001);
+    emu
+}
+
+#[test]
+fn breakpoint() {
+    let mut emu = make_emu();
+    emu.debugger().add_breakpoint(0x8004);
+
+    assert_eq!(emu.run_until_break(1000), BreakReason::Breakpoint(0x8004));
+    let state = emu.cpu_state();
+    assert_eq!(state.pc, 0x8004);
+    assert_eq!(state.a, 0x07);
+    assert_eq!(state.x, 0x01);
+
+    // Continuing doesn't break on the same breakpoint again.
+    assert_eq!(emu.run_until_break(1000), BreakReason::CycleLimit);
+}
+
+#[test]
+fn write_watchpoint() {
+    let mut emu = make_emu();
+    let index = emu.debugger().add_watchpoint(Watchpoint {
+        range: 0x0300..=0x03FF,
+        read: false,
+        write: true,
+        execute: false,
+        value: Some(0x07),
+    });
+
+    assert_eq!(
+        emu.run_until_break(1000),
+        BreakReason::Watchpoint {
+            index,
+            addr: 0x0300,
+            data: 0x07,
+            access: Access::Write
+        }
+    );
+    assert_eq!(emu.cpu_state().pc, 0x8007);
+}
+
+#[test]
+fn watchpoint_value_condition() {
+    let mut emu = make_emu();
+    emu.debugger().add_watchpoint(Watchpoint {
+        range: 0x0300..=0x0300,
+        read: false,
+        write: true,
+        execute: false,
+        value: Some(0x08),
+    });
+
+    assert_eq!(emu.run_until_break(1000), BreakReason::CycleLimit);
+}
+
+#[test]
+fn execute_watchpoint() {
+    let mut emu = make_emu();
+    emu.debugger().add_watchpoint(Watchpoint {
+        range: 0x8010..=0x801F,
+        read: false,
+        write: false,
+        execute: true,
+        value: Some(0xE8),
+    });
+
+    assert_eq!(
+        emu.run_until_break(1000),
+        BreakReason::Watchpoint {
+            index: 0,
+            addr: 0x8012,
+            data: 0xE8,
+            access: Access::Execute
+        }
+    );
+    assert_eq!(emu.cpu_state().pc, 0x8012);
+    assert_eq!(emu.cpu_state().x, 0x00);
+}
+
+#[test]
+fn step_into_and_out() {
+    let mut emu = make_emu();
+
+    assert_eq!(emu.step_into(), BreakReason::Step);
+    assert_eq!(emu.cpu_state().pc, 0x8010);
+
+    assert_eq!(emu.step_out(), BreakReason::Step);
+    let state = emu.cpu_state();
+    assert_eq!(state.pc, 0x8004);
+    assert_eq!(state.x, 0x01);
+}
+
+#[test]
+fn step_over() {
+    let mut emu = make_emu();
+
+    assert_eq!(emu.step_over(), BreakReason::Step);
+    let state = emu.cpu_state();
+    assert_eq!(state.pc, 0x8004);
+    assert_eq!(state.x, 0x01);
+
+    assert_eq!(emu.step_over(), BreakReason::Step);
+    assert_eq!(emu.cpu_state().pc, 0x8007);
+}
+
+#[test]
+fn run_to_frame() {
+    let mut emu = make_emu();
+
+    assert_eq!(emu.run_to_frame(), BreakReason::Frame);
+    // A frame is 89342 PPU dots, or about 29781 CPU cycles.
+    let cycles = emu.cpu_state().cycles;
+    assert!((29781..29781 + 7).contains(&cycles));
+}
+
+#[test]
+fn breakpoint_at_start_of_run() {
+    let mut emu = make_emu();
+    assert_eq!(emu.run_to_frame(), BreakReason::Frame);
+
+    // A run that didn't stop on a breakpoint still checks its first
+    // instruction.
+    let pc = emu.cpu_state().pc;
+    emu.debugger().add_breakpoint(pc);
+    assert_eq!(emu.run_to_frame(), BreakReason::Breakpoint(pc));
+    assert_eq!(emu.cpu_state().pc, pc);
+}
+
+#[test]
+fn run_to_scanline() {
+    let mut emu = make_emu();
+
+    assert_eq!(emu.run_to_scanline(100), BreakReason::Scanline);
+    // Scanline 100 starts 34100 PPU dots into the frame, after about 11366
+    // CPU cycles.
+    let cycles = emu.cpu_state().cycles;
+    assert!((11366..11366 + 7).contains(&cycles));
+
+    // NTSC frames end after scanline 261.
+    assert_eq!(emu.run_to_scanline(262), BreakReason::CycleLimit);
+    let elapsed = emu.cpu_state().cycles - cycles;
+    assert!((29781 + 113..29781 + 113 + 7).contains(&elapsed));
+}
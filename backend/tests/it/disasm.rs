@@ -0,0 +1,68 @@
+use std::{cell::RefCell, rc::Rc};
+
+use backend::Emu;
+
+use crate::make_rom;
+
+const CODE: [u8; 12] = [
+    0xA2, 0x02, // LDX #$02
+    0xB5, 0x10, // LDA $10,X
+    0x6C, 0xFF, 0x02, // JMP ($02FF)
+    0x1A, // *NOP
+    0x90, 0xFE, // BCC $8008
+    0x0A, // ASL A
+    0xDF, // *DCP $EAEA,X (the operand is the padding after the code)
+];
+
+#[test]
+fn disassemble() {
+    let mut emu = Emu::new(&make_rom(&CODE));
+    // Run the reset sequence and LDX.
+    emu.step();
+
+    let lda = emu.disassemble(0x8002);
+    assert_eq!(lda.bytes, [0xB5, 0x10]);
+    assert_eq!(lda.size(), 2);
+    assert_eq!(lda.operand, "$10,X");
+    assert_eq!(lda.effective_addr, Some(0x12));
+    assert_eq!(lda.to_string(), "LDA $10,X @ 12 = 00");
+
+    // The pointer's high byte is read from $0200, not $0300.
+    let jmp = emu.disassemble(0x8004);
+    assert_eq!(jmp.to_string(), "JMP ($02FF) = 0000");
+
+    let nop = emu.disassemble(0x8007);
+    assert!(!nop.instruction.official);
+    assert_eq!(nop.to_string(), "NOP");
+
+    assert_eq!(emu.disassemble(0x8008).to_string(), "BCC $8008");
+    assert_eq!(emu.disassemble(0x800A).to_string(), "ASL A");
+    assert_eq!(emu.disassemble(0x800B).to_string(), "DCP $EAEA,X @ EAEC = EA");
+}
+
+#[test]
+fn labels() {
+    const CODE: [u8; 6] = [
+        0x20, 0x10, 0x80, // JSR $8010
+        0x8D, 0x00, 0x03, // STA $0300
+    ];
+
+    let mut emu = Emu::new(&make_rom(&CODE));
+    emu.symbols().add_prg_label(0x0010, "UpdatePlayer");
+    emu.symbols().add_cpu_label(0x0300, "player_x");
+
+    // The ROM is mirrored, so both addresses are the same byte of PRG ROM.
+    assert_eq!(emu.label(0x8010), Some("UpdatePlayer"));
+    assert_eq!(emu.label(0xC010), Some("UpdatePlayer"));
+    assert_eq!(emu.label(0x8011), None);
+
+    assert_eq!(emu.disassemble(0x8003).to_string(), "STA player_x = 00");
+
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    emu.set_tracer(Some(Box::new({
+        let lines = lines.clone();
+        move |line| lines.borrow_mut().push(line.to_string())
+    })));
+    emu.step();
+    assert!(lines.borrow()[0].contains("JSR UpdatePlayer "));
+}
@@ -0,0 +1,145 @@
+use backend::{cdl::PCM, Emu};
+
+use crate::make_rom;
+
+/// Makes an emulator that runs `code` with page $02 filled with a pattern.
+fn make_emu(code: &[u8]) -> Emu {
+    let mut emu = Emu::new(&make_rom(code));
+    for i in 0..=0xFF {
+        emu.poke(0x0200 | i, i as u8 ^ 0x5A);
+    }
+    emu
+}
+
+/// Runs `setup` and then an OAM DMA from page $02. Returns the cycle the DMA
+/// started on and the number of cycles it took.
+fn oam_dma_cycles(setup: &[u8]) -> (u64, u64) {
+    let mut code = setup.to_vec();
+    code.extend([
+        0xA9, 0x02, // LDA #$02
+        0x8D, 0x14, 0x40, // STA $4014
+    ]);
+    let mut emu = make_emu(&code);
+    while emu.cpu_state().pc != 0x8000 + code.len() as u16 {
+        emu.step();
+    }
+
+    // The DMA halts the CPU on the next opcode fetch, a 2-cycle NOP.
+    let start = emu.cpu_state().cycles;
+    emu.step();
+    (start, emu.cpu_state().cycles - start - 2)
+}
+
+#[test]
+fn length_depends_on_alignment() {
+    // LDA $00 takes 3 cycles, so it flips whether the DMA is halted on a get
+    // or a put cycle.
+    let (start, cycles) = oam_dma_cycles(&[]);
+    let (other_start, other_cycles) = oam_dma_cycles(&[0xA5, 0x00]);
+    assert_ne!(start % 2, other_start % 2);
+
+    for (start, cycles) in [(start, cycles), (other_start, other_cycles)] {
+        // Even cycles are get cycles. If the DMA is halted on a put cycle, it
+        // can read right away. Otherwise, it waits a cycle to realign.
+        let expected = if start % 2 == 1 { 513 } else { 514 };
+        assert_eq!(cycles, expected, "started on cycle {start}");
+    }
+}
+
+#[test]
+fn copies_page_to_oam() {
+    let code = [
+        // The copy starts at OAMADDR and wraps around.
+        0xA9, 0x04, // LDA #$04
+        0x8D, 0x03, 0x20, // STA $2003
+        0xA9, 0x02, // LDA #$02
+        0x8D, 0x14, 0x40, // STA $4014
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x03, 0x20, // STA $2003
+        0xAD, 0x04, 0x20, // LDA $2004
+        0x85, 0x00, // STA $00
+        0xA9, 0x84, // LDA #$84
+        0x8D, 0x03, 0x20, // STA $2003
+        0xAD, 0x04, 0x20, // LDA $2004
+        0x85, 0x01, // STA $01
+    ];
+    let mut emu = make_emu(&code);
+    while emu.cpu_state().pc != 0x8000 + code.len() as u16 {
+        emu.step();
+    }
+
+    assert_eq!(emu.peek(0x0000), Some(0xFC ^ 0x5A));
+    assert_eq!(emu.peek(0x0001), Some(0x80 ^ 0x5A));
+}
+
+/// Sets the DMC to its fastest rate, with the longest sample at $C000, and
+/// starts it.
+const DMC_SETUP: [u8; 15] = [
+    0xA9, 0x0F, // LDA #$0F
+    0x8D, 0x10, 0x40, // STA $4010
+    0xA9, 0xFF, // LDA #$FF
+    0x8D, 0x13, 0x40, // STA $4013
+    0xA9, 0x10, // LDA #$10
+    0x8D, 0x15, 0x40, // STA $4015
+];
+
+/// Returns the number of sample bytes that the DMC has fetched.
+fn dmc_fetches(emu: &Emu) -> usize {
+    let cdl = emu.cdl().unwrap();
+    cdl.prg().iter().filter(|&&flags| flags & PCM != 0).count()
+}
+
+#[test]
+fn dmc_start_steals_cycles() {
+    // LDA $00 takes 3 cycles, so it flips whether the DMA is halted on a get
+    // or a put cycle.
+    for setup in [&[][..], &[0xA5, 0x00]] {
+        let mut code = setup.to_vec();
+        code.extend(DMC_SETUP);
+        let mut emu = make_emu(&code);
+        emu.start_cdl();
+        while emu.cpu_state().pc != 0x8000 + code.len() as u16 {
+            emu.step();
+        }
+        assert_eq!(dmc_fetches(&emu), 0);
+
+        // The first byte of the sample is fetched on the next read, a 2-cycle
+        // NOP. The DMA halts the CPU, has a dummy cycle, and then waits for a
+        // get cycle.
+        let start = emu.cpu_state().cycles;
+        emu.step();
+        let cycles = emu.cpu_state().cycles - start - 2;
+        let expected = if start % 2 == 1 { 4 } else { 3 };
+        assert_eq!(cycles, expected, "started on cycle {start}");
+        assert_eq!(dmc_fetches(&emu), 1);
+    }
+}
+
+#[test]
+fn dmc_dma_realigns() {
+    for setup in [&[][..], &[0xA5, 0x00]] {
+        let mut code = setup.to_vec();
+        code.extend(DMC_SETUP);
+        code.extend([
+            0xA9, 0x02, // LDA #$02
+            0x8D, 0x14, 0x40, // STA $4014
+        ]);
+        let mut emu = make_emu(&code);
+        emu.start_cdl();
+        while emu.cpu_state().pc != 0x8000 + code.len() as u16 {
+            emu.step();
+        }
+
+        // At the fastest rate, the DMC fetches a byte every 432 cycles, so at
+        // least one fetch lands during the DMA. Each one takes over a get
+        // cycle and costs a put cycle to realign.
+        let fetches = dmc_fetches(&emu);
+        let start = emu.cpu_state().cycles;
+        emu.step();
+        let fetches = (dmc_fetches(&emu) - fetches) as u64;
+        assert!(fetches > 0);
+        let cycles = emu.cpu_state().cycles - start - 2;
+        let expected = if start % 2 == 1 { 513 } else { 514 } + 2 * fetches;
+        assert_eq!(cycles, expected, "started on cycle {start}");
+    }
+}
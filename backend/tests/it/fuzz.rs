@@ -0,0 +1,68 @@
+use backend::{CpuState, Emu, Status};
+
+use crate::make_rom;
+
+/// Where the instructions under test are written.
+const CODE_ADDR: u16 = 0x0300;
+/// The number of instructions run with each address as the operand.
+const RUNS_PER_ADDR: usize = 4;
+/// The opcodes that lock up the CPU.
+const JAMS: [u8; 12] =
+    [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+/// A xorshift64 generator, so every run tests the same instructions.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Runs random opcodes with random registers and every address in the 64 KB
+/// address space as the operand, so every addressing mode reads, writes, and
+/// read-modify-writes all over it. None of them should panic, and only the
+/// JAM opcodes should lock up the CPU.
+#[test]
+fn random_instructions() {
+    let mut emu = Emu::new(&make_rom(&[]));
+    // Run the reset sequence.
+    emu.step();
+
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    let mut tested = [false; 256];
+    for addr in 0..=0xFFFF {
+        for _ in 0..RUNS_PER_ADDR {
+            let [opc, a, x, y, s, p, ..] = rng.next().to_le_bytes();
+            tested[opc as usize] = true;
+            let [low, high] = u16::to_le_bytes(addr);
+            for (i, byte) in [opc, low, high].into_iter().enumerate() {
+                assert!(emu.poke(CODE_ADDR + i as u16, byte));
+            }
+            emu.set_cpu_state(&CpuState {
+                a,
+                x,
+                y,
+                pc: CODE_ADDR,
+                s,
+                p,
+                ..emu.cpu_state()
+            });
+            emu.step();
+
+            // An interrupt can run instead of the instruction, so a JAM
+            // doesn't always jam.
+            if let Status::Jammed { pc } = emu.status() {
+                assert!(JAMS.contains(&opc), "{opc:02X} {addr:04X}");
+                assert_eq!(pc, CODE_ADDR);
+                emu.reset();
+                emu.step();
+                assert_eq!(emu.status(), Status::Running);
+            }
+        }
+    }
+    assert!(tested.iter().all(|&tested| tested));
+}
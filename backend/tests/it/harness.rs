@@ -0,0 +1,298 @@
+//! Runs test ROMs that report their results in memory. Two conventions are
+//! understood:
+//!
+//! - blargg's newer tests write $DE $B0 $61 to $6001-$6003, a status to
+//!   $6000 ($80 while running, $81 to ask for a reset, and then the result
+//!   code), and text to $6004.
+//! - Older tests write a result code to $00F0 and then loop forever.
+//!
+//! A result code of 0 (or 1 at $00F0) means the ROM passed. Older tests
+//! print why they failed on the screen, so that's used as the message.
+//! `screen_text` can also be compared against what a test is expected to
+//! print, and `screenshot` compares what's drawn.
+//!
+//! `directory` runs every ROM under `DUNES_TEST_ROMS` (`../roms` by default)
+//! and prints a table of the results. It's ignored by default since most
+//! ROMs need more of the emulator than is done. Set `DUNES_TEST_REPORT` to
+//! also write the results to a JSON file.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use backend::{Emu, Status};
+use serde::Serialize;
+
+use crate::make_rom;
+
+/// About 30 seconds.
+pub const DEFAULT_TIMEOUT_FRAMES: u64 = 30 * 60;
+
+const STATUS_ADDR: u16 = 0x6000;
+const SIGNATURE_ADDR: u16 = 0x6001;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const OUTPUT_ADDR: u16 = 0x6004;
+const RUNNING_STATUS: u8 = 0x80;
+const RESET_STATUS: u8 = 0x81;
+/// The tests ask for the reset button to be held for at least 100 ms.
+const RESET_DELAY_FRAMES: u64 = 6;
+/// The address of the result code in older tests.
+const RESULT_ADDR: u16 = 0x00F0;
+/// The nametable that tests print to.
+const NAMETABLE_ADDR: u16 = 0x2000;
+const SCREEN_COLUMNS: u16 = 32;
+const SCREEN_ROWS: u16 = 30;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Outcome {
+    Passed,
+    Failed { code: u8, message: String },
+    Timeout,
+}
+
+/// Runs `rom` until it reports a result or `timeout_frames` frames have run.
+/// Returns the outcome and the number of frames it took.
+pub fn run(rom: &[u8], timeout_frames: u64) -> (Outcome, u64) {
+    let mut emu = Emu::new(rom);
+    let mut reset_at = None;
+    for frame in 0..timeout_frames {
+        emu.run_to_frame();
+        emu.discard_samples();
+
+        if let Status::Jammed { pc } = emu.status() {
+            let message = format!("the CPU jammed at ${pc:04X}");
+            return (Outcome::Failed { code: 0, message }, frame);
+        }
+
+        if peek_signature(&mut emu) {
+            match emu.peek(STATUS_ADDR).unwrap() {
+                RUNNING_STATUS => reset_at = None,
+                RESET_STATUS => {
+                    let requested_at = *reset_at.get_or_insert(frame);
+                    if frame - requested_at >= RESET_DELAY_FRAMES {
+                        emu.reset();
+                        reset_at = None;
+                    }
+                }
+                code => return (outcome(code, read_text(&mut emu)), frame),
+            }
+        } else if is_spinning(&mut emu) {
+            match emu.peek(RESULT_ADDR).unwrap() {
+                // The test hasn't finished.
+                0 => (),
+                1 => return (Outcome::Passed, frame),
+                code => {
+                    let message = screen_text(&emu);
+                    return (Outcome::Failed { code, message }, frame);
+                }
+            }
+        }
+    }
+    (Outcome::Timeout, timeout_frames)
+}
+
+fn outcome(code: u8, message: String) -> Outcome {
+    match code {
+        0 => Outcome::Passed,
+        code => Outcome::Failed { code, message },
+    }
+}
+
+fn peek_signature(emu: &mut Emu) -> bool {
+    (0..3).all(|i| emu.peek(SIGNATURE_ADDR + i) == Some(SIGNATURE[i as usize]))
+}
+
+/// Reads the null-terminated text at $6004.
+fn read_text(emu: &mut Emu) -> String {
+    let mut text = Vec::new();
+    let mut addr = OUTPUT_ADDR;
+    while let Some(byte) = emu.peek(addr).filter(|&byte| byte != b'\0') {
+        text.push(byte);
+        addr += 1;
+    }
+    String::from_utf8_lossy(&text).trim().to_string()
+}
+
+/// Runs `rom` for `frames` frames and returns the text on the screen.
+pub fn run_for_text(rom: &[u8], frames: u64) -> String {
+    let mut emu = Emu::new(rom);
+    for _ in 0..frames {
+        emu.run_to_frame();
+        emu.discard_samples();
+    }
+    screen_text(&emu)
+}
+
+/// Returns the text in the first nametable. Test ROMs use a font whose tiles
+/// are numbered by their ASCII codes, and other tiles are read as spaces.
+pub fn screen_text(emu: &Emu) -> String {
+    let mut lines = Vec::new();
+    for row in 0..SCREEN_ROWS {
+        let line: String = (0..SCREEN_COLUMNS)
+            .map(|column| {
+                let addr = NAMETABLE_ADDR + row * SCREEN_COLUMNS + column;
+                match emu.peek_ppu(addr) {
+                    tile @ 0x20..=0x7E => tile as char,
+                    _ => ' ',
+                }
+            })
+            .collect();
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+/// Returns true if the CPU is on a JMP to itself.
+fn is_spinning(emu: &mut Emu) -> bool {
+    let pc = emu.cpu_state().pc;
+    let mut byte = |i| emu.peek(pc.wrapping_add(i));
+    byte(0) == Some(0x4C)
+        && byte(1) == Some(pc as u8)
+        && byte(2) == Some((pc >> 8) as u8)
+}
+
+#[derive(Serialize)]
+struct Report {
+    path: PathBuf,
+    #[serde(flatten)]
+    outcome: Outcome,
+    frames: u64,
+}
+
+/// Returns the paths of the .nes files under `dir`, sorted.
+fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "nes") {
+                roms.push(path);
+            }
+        }
+    }
+    roms.sort();
+    roms
+}
+
+#[test]
+#[ignore]
+fn directory() {
+    let dir = env::var("DUNES_TEST_ROMS").unwrap_or_else(|_| "../roms".into());
+    let timeout_frames = env::var("DUNES_TEST_TIMEOUT")
+        .ok()
+        .map(|seconds| seconds.parse::<u64>().unwrap() * 60)
+        .unwrap_or(DEFAULT_TIMEOUT_FRAMES);
+
+    let mut reports = Vec::new();
+    for path in find_roms(Path::new(&dir)) {
+        let rom = fs::read(&path).unwrap();
+        // Mappers that aren't supported panic.
+        let result = std::panic::catch_unwind(|| run(&rom, timeout_frames));
+        let (outcome, frames) = result.unwrap_or_else(|_| {
+            let message = "the emulator panicked".to_string();
+            (Outcome::Failed { code: 0, message }, 0)
+        });
+        reports.push(Report { path, outcome, frames });
+    }
+
+    let width = reports
+        .iter()
+        .map(|report| report.path.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+    for report in &reports {
+        let (result, message) = match &report.outcome {
+            Outcome::Passed => ("pass", ""),
+            Outcome::Failed { message, .. } => ("FAIL", message.as_str()),
+            Outcome::Timeout => ("timeout", ""),
+        };
+        let message = message.lines().next().unwrap_or("");
+        let path = report.path.display().to_string();
+        println!("{path:width$}  {result:7}  {message}");
+    }
+    let passed = reports
+        .iter()
+        .filter(|report| report.outcome == Outcome::Passed)
+        .count();
+    println!("{passed}/{} passed", reports.len());
+
+    if let Ok(path) = env::var("DUNES_TEST_REPORT") {
+        fs::write(path, serde_json::to_string_pretty(&reports).unwrap())
+            .unwrap();
+    }
+}
+
+/// Makes a ROM that stores each `(addr, data)` pair and then loops forever.
+fn make_store_rom(stores: &[(u16, u8)]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for &(addr, data) in stores {
+        let [low, high] = addr.to_le_bytes();
+        // LDA #data, STA addr
+        code.extend_from_slice(&[0xA9, data, 0x8D, low, high]);
+    }
+    // JMP to itself.
+    let [low, high] = (0x8000 + code.len() as u16).to_le_bytes();
+    code.extend_from_slice(&[0x4C, low, high]);
+    make_rom(&code)
+}
+
+/// Makes a ROM that prints `text` at the start of the third row of the
+/// screen, stores `result` to $00F0, and then loops forever.
+fn make_screen_rom(text: &[u8], result: u8) -> Vec<u8> {
+    let mut stores = vec![(0x2006, 0x20), (0x2006, 0x40)];
+    stores.extend(text.iter().map(|&byte| (0x2007, byte)));
+    stores.push((RESULT_ADDR, result));
+    make_store_rom(&stores)
+}
+
+/// Makes a ROM that reports `status` and `text` the way blargg's newer
+/// tests do.
+fn make_status_rom(status: u8, text: &[u8]) -> Vec<u8> {
+    let mut stores = vec![
+        (STATUS_ADDR, RUNNING_STATUS),
+        (SIGNATURE_ADDR, SIGNATURE[0]),
+        (SIGNATURE_ADDR + 1, SIGNATURE[1]),
+        (SIGNATURE_ADDR + 2, SIGNATURE[2]),
+    ];
+    for (i, &byte) in text.iter().chain(b"\0").enumerate() {
+        stores.push((OUTPUT_ADDR + i as u16, byte));
+    }
+    stores.push((STATUS_ADDR, status));
+    make_store_rom(&stores)
+}
+
+#[test]
+fn status_protocol() {
+    let (outcome, _) = run(&make_status_rom(0, b"Passed\n"), 10);
+    assert_eq!(outcome, Outcome::Passed);
+
+    let (outcome, _) = run(&make_status_rom(2, b"Failed #2\n"), 10);
+    let message = "Failed #2".to_string();
+    assert_eq!(outcome, Outcome::Failed { code: 2, message });
+}
+
+#[test]
+fn result_at_f0() {
+    let (outcome, _) = run(&make_store_rom(&[(RESULT_ADDR, 1)]), 10);
+    assert_eq!(outcome, Outcome::Passed);
+
+    let (outcome, _) = run(&make_screen_rom(b"FAILED: #3", 3), 10);
+    let message = "FAILED: #3".to_string();
+    assert_eq!(outcome, Outcome::Failed { code: 3, message });
+}
+
+#[test]
+fn screen_text_comparison() {
+    let text = run_for_text(&make_screen_rom(b"PASSED", 1), 2);
+    assert_eq!(text, "PASSED");
+}
+
+#[test]
+fn timeout() {
+    assert_eq!(run(&make_store_rom(&[]), 10), (Outcome::Timeout, 10));
+}
@@ -0,0 +1,79 @@
+use backend::{config::Config, region::Region, Emu};
+
+use crate::make_rom;
+
+const CODE: [u8; 8] = [
+    0xA9, 0x80, // LDA #$80
+    0x8D, 0x00, 0x20, // STA $2000
+    0x4C, 0x05, 0x80, // JMP $8005
+];
+
+/// Where the NMI handler that `make_nmi_rom` installs is.
+const NMI_HANDLER: u16 = 0x8040;
+
+/// Makes a ROM that runs `code` and has an NMI handler at `NMI_HANDLER` that
+/// loops forever.
+fn make_nmi_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = make_rom(code);
+    let [low, high] = NMI_HANDLER.to_le_bytes();
+    let offset = 0x10 + (NMI_HANDLER - 0x8000) as usize;
+    rom[offset..offset + 3].copy_from_slice(&[0x4C, low, high]);
+    // The vector at $FFFA.
+    rom[0x10 + 0x3FFA..0x10 + 0x3FFC].copy_from_slice(&[low, high]);
+    rom
+}
+
+/// Runs until at least `cycles` CPU cycles have passed and returns whether an
+/// NMI is pending or has been taken.
+fn nmi_after(emu: &mut Emu, cycles: u64) -> bool {
+    while emu.cpu_state().cycles < cycles {
+        emu.step();
+    }
+    emu.cpu_state().nmi || emu.cpu_state().pc == NMI_HANDLER
+}
+
+#[test]
+fn vblank_nmi() {
+    // Vblank starts on the second dot of scanline 241 on NTSC and PAL and
+    // scanline 291 on the Dendy.
+    for (region, cycles) in
+        [(Region::Ntsc, 27394), (Region::Pal, 25682), (Region::Dendy, 33078)]
+    {
+        let config = Config { region: Some(region), ..Config::default() };
+        let mut emu = Emu::with_config(&make_nmi_rom(&CODE), config);
+        // The NMI is seen before the next instruction, and JMP takes 3
+        // cycles.
+        assert!(!nmi_after(&mut emu, cycles - 3), "{region:?}");
+        assert!(nmi_after(&mut emu, cycles + 3), "{region:?}");
+    }
+}
+
+#[test]
+fn nmi_disabled() {
+    let code = [
+        0x4C, 0x00, 0x80, // JMP $8000
+    ];
+    let mut emu = Emu::new(&make_nmi_rom(&code));
+    // A frame and a half.
+    assert!(!nmi_after(&mut emu, 45_000));
+}
+
+#[test]
+fn nmi_taken() {
+    let mut emu = Emu::new(&make_nmi_rom(&CODE));
+    emu.run_to_frame();
+    while emu.cpu_state().pc != NMI_HANDLER {
+        emu.step();
+    }
+    let state = emu.cpu_state();
+    assert!(!state.nmi);
+    assert_eq!(state.s, 0xFA);
+    assert_eq!(state.p & 0x04, 0x04);
+    // PC is pushed as it was, and P with B clear.
+    let pc = u16::from_le_bytes([
+        emu.peek(0x01FC).unwrap(),
+        emu.peek(0x01FD).unwrap(),
+    ]);
+    assert!((0x8005..0x8008).contains(&pc));
+    assert_eq!(emu.peek(0x01FB).unwrap() & 0x10, 0);
+}
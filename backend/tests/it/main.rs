@@ -1 +1,29 @@
 mod blargg;
+mod cdl;
+mod config;
+mod cycle;
+mod debugger;
+mod disasm;
+mod dma;
+mod fuzz;
+mod harness;
+mod interrupt;
+mod nestest;
+mod nsf;
+mod open_bus;
+mod ppu;
+mod region;
+mod reset;
+mod screenshot;
+
+/// Makes an NROM ROM that runs `code` at $8000.
+fn make_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = b"NES\x1A\x01\x00".to_vec();
+    rom.resize(16, 0);
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..code.len()].copy_from_slice(code);
+    // The reset vector.
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    rom.extend_from_slice(&prg);
+    rom
+}
@@ -0,0 +1,35 @@
+use std::{cell::RefCell, fs, rc::Rc};
+
+use backend::{CpuState, Emu};
+
+#[test]
+fn nestest() {
+    // The reset vector is at the end of the 16 KB of PRG ROM.
+    const RESET_VECTOR: usize = 16 + 0x3FFC;
+
+    let mut rom = fs::read("../roms/nestest/nestest.nes").unwrap();
+    let log = fs::read_to_string("../roms/nestest/nestest.log").unwrap();
+
+    // Start at $C000 to run the tests without a PPU.
+    rom[RESET_VECTOR..RESET_VECTOR + 2].copy_from_slice(&[0x00, 0xC0]);
+    let mut emu = Emu::new(&rom);
+    // nestest.log starts with the B flag clear.
+    emu.set_cpu_state(&CpuState { p: 0x24, ..emu.cpu_state() });
+
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    emu.set_tracer(Some(Box::new({
+        let lines = lines.clone();
+        move |line| lines.borrow_mut().push(line.to_string())
+    })));
+
+    for (i, expected) in log.lines().enumerate() {
+        emu.step();
+        let actual = lines.borrow_mut().pop().unwrap();
+        assert!(
+            actual == expected,
+            "nestest.log diverges at line {}\nexpected: {expected}\n  actual: \
+             {actual}",
+            i + 1
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use backend::Emu;
+
+const LOAD_ADDR: u16 = 0x8000;
+const INIT_ADDR: u16 = 0x8000;
+const PLAY_ADDR: u16 = 0x8004;
+
+/// INIT stores the song number at $6000 and PLAY increments $6001.
+const CODE: [u8; 8] = [
+    0x8D, 0x00, 0x60, // STA $6000
+    0x60, // RTS
+    0xEE, 0x01, 0x60, // INC $6001
+    0x60, // RTS
+];
+
+fn make_nsf(songs: u8) -> Vec<u8> {
+    let mut nsf = vec![0; 0x80];
+    nsf[..5].copy_from_slice(b"NESM\x1A");
+    nsf[0x05] = 1;
+    nsf[0x06] = songs;
+    nsf[0x07] = 1;
+    nsf[0x08..0x0A].copy_from_slice(&LOAD_ADDR.to_le_bytes());
+    nsf[0x0A..0x0C].copy_from_slice(&INIT_ADDR.to_le_bytes());
+    nsf[0x0C..0x0E].copy_from_slice(&PLAY_ADDR.to_le_bytes());
+    nsf[0x6E..0x70].copy_from_slice(&16639u16.to_le_bytes());
+    nsf.extend_from_slice(&CODE);
+    nsf
+}
+
+fn make_info(songs: u8) -> Vec<u8> {
+    let mut info = Vec::new();
+    info.extend_from_slice(&LOAD_ADDR.to_le_bytes());
+    info.extend_from_slice(&INIT_ADDR.to_le_bytes());
+    info.extend_from_slice(&PLAY_ADDR.to_le_bytes());
+    info.extend_from_slice(&[0, 0, songs, 0]);
+    info
+}
+
+/// Makes an NSFe file out of `chunks`, which are given as an ID and data.
+fn make_nsfe_chunks(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let mut nsfe = b"NSFE".to_vec();
+    for (id, chunk) in chunks {
+        nsfe.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        nsfe.extend_from_slice(*id);
+        nsfe.extend_from_slice(chunk);
+    }
+    nsfe
+}
+
+fn make_nsfe(songs: u8) -> Vec<u8> {
+    make_nsfe_chunks(&[
+        (b"INFO", &make_info(songs)),
+        (b"DATA", &CODE),
+        (b"NEND", &[]),
+    ])
+}
+
+/// Loads `rom` and returns the error.
+fn load_error(rom: &[u8]) -> String {
+    match Emu::try_new(rom) {
+        Ok(_) => panic!("the file loaded"),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Steps until PLAY has been called `calls` times.
+fn run_plays(emu: &mut Emu, calls: u8) {
+    // PLAY runs at about 60 Hz and the idle loop takes 3 cycles, so this is
+    // a little over a second.
+    for _ in 0..700_000 {
+        if emu.peek(0x6001).unwrap() == calls {
+            return;
+        }
+        emu.step();
+    }
+    panic!("PLAY wasn't called {calls} times");
+}
+
+#[test]
+fn nsf_calls_init_and_play() {
+    let mut emu = Emu::new(&make_nsf(3));
+    assert_eq!(emu.songs(), 3);
+
+    run_plays(&mut emu, 60);
+    assert_eq!(emu.peek(0x6000), Some(0));
+
+    assert!(emu.start_song(2));
+    assert_eq!(emu.peek(0x6001), Some(0));
+    run_plays(&mut emu, 10);
+    assert_eq!(emu.peek(0x6000), Some(2));
+}
+
+#[test]
+fn nsfe_calls_init_and_play() {
+    let mut emu = Emu::new(&make_nsfe(2));
+    assert_eq!(emu.songs(), 2);
+
+    run_plays(&mut emu, 60);
+    assert_eq!(emu.peek(0x6000), Some(0));
+}
+
+#[test]
+fn power_cycle_restarts_first_song() {
+    let mut emu = Emu::new(&make_nsf(3));
+    assert!(emu.start_song(2));
+    run_plays(&mut emu, 10);
+
+    emu.power_cycle();
+    emu.step();
+    assert_eq!(emu.peek(0x6001), Some(0));
+    run_plays(&mut emu, 10);
+    assert_eq!(emu.peek(0x6000), Some(0));
+}
+
+#[test]
+fn start_song_out_of_range() {
+    let mut emu = Emu::new(&make_nsf(3));
+    assert!(!emu.start_song(3));
+    assert!(emu.start_song(2));
+
+    let mut emu = Emu::new(&crate::make_rom(&[]));
+    assert!(!emu.start_song(0));
+}
+
+#[test]
+fn first_song_out_of_range() {
+    let mut nsf = make_nsf(3);
+    nsf[0x07] = 4;
+    let mut emu = Emu::new(&nsf);
+    run_plays(&mut emu, 1);
+    assert_eq!(emu.peek(0x6000), Some(0));
+}
+
+#[test]
+fn truncated_nsf_header() {
+    let nsf = make_nsf(1);
+    assert_eq!(load_error(&nsf[..0x40]), "the NSF header is truncated");
+}
+
+#[test]
+fn no_songs() {
+    assert_eq!(load_error(&make_nsf(0)), "there aren't any songs");
+}
+
+#[test]
+fn load_address_below_rom() {
+    let mut nsf = make_nsf(1);
+    nsf[0x08..0x0A].copy_from_slice(&0x6000u16.to_le_bytes());
+    assert_eq!(load_error(&nsf), "the load address $6000 is below $8000");
+}
+
+#[test]
+fn unsupported_expansion() {
+    let mut nsf = make_nsf(1);
+    nsf[0x7B] = 0x06;
+    assert_eq!(
+        load_error(&nsf),
+        "VRC7 and FDS expansion audio isn't supported"
+    );
+}
+
+#[test]
+fn truncated_nsfe_chunk() {
+    let mut nsfe = make_nsfe(1);
+    // Cut the file off partway through the DATA chunk.
+    nsfe.truncate(4 + 8 + 10 + 8 + 4);
+    assert_eq!(load_error(&nsfe), "the DATA chunk is truncated");
+}
+
+#[test]
+fn truncated_nsfe_info() {
+    let info = make_info(1);
+    let nsfe = make_nsfe_chunks(&[(b"INFO", &info[..6]), (b"NEND", &[])]);
+    assert_eq!(
+        load_error(&nsfe),
+        "the INFO chunk is 6 bytes, but it has to be at least 8"
+    );
+}
+
+#[test]
+fn missing_nsfe_info() {
+    let nsfe = make_nsfe_chunks(&[(b"DATA", &CODE), (b"NEND", &[])]);
+    assert_eq!(load_error(&nsfe), "there's no INFO chunk");
+}
+
+#[test]
+fn truncated_nsfe_rate() {
+    let nsfe = make_nsfe_chunks(&[
+        (b"INFO", &make_info(1)),
+        (b"RATE", &[0x10]),
+        (b"NEND", &[]),
+    ]);
+    assert_eq!(load_error(&nsfe), "the RATE chunk is truncated");
+}
+
+#[test]
+fn unsupported_nsfe_chunk() {
+    let nsfe = make_nsfe_chunks(&[
+        (b"INFO", &make_info(1)),
+        (b"VRC7", &[0]),
+        (b"NEND", &[]),
+    ]);
+    assert_eq!(load_error(&nsfe), "the VRC7 chunk isn't supported");
+}
+
+#[test]
+fn long_nsfe_bank() {
+    // Bytes past the eight banks are ignored.
+    let nsfe = make_nsfe_chunks(&[
+        (b"INFO", &make_info(1)),
+        (b"DATA", &CODE),
+        (b"BANK", &[0; 12]),
+        (b"NEND", &[]),
+    ]);
+    let mut emu = Emu::new(&nsfe);
+    run_plays(&mut emu, 1);
+}
@@ -0,0 +1,63 @@
+use backend::Emu;
+
+use crate::make_rom;
+
+const CODE: [u8; 39] = [
+    0xA2, 0xFF, // LDX #$FF
+    0x8E, 0x00, 0x20, // STX $2000
+    0xAD, 0x18, 0x40, // LDA $4018
+    0x85, 0x00, // STA $00
+    0xAD, 0x00, 0x50, // LDA $5000
+    0x85, 0x01, // STA $01
+    0xA2, 0x17, // LDX #$17
+    0xBD, 0xFF, 0x3F, // LDA $3FFF,X
+    0x85, 0x02, // STA $02
+    0xAD, 0x02, 0x20, // LDA $2002
+    0x85, 0x03, // STA $03
+    0xA2, 0x20, // LDX #$20
+    0xBD, 0xF5, 0x3F, // LDA $3FF5,X
+    0x85, 0x04, // STA $04
+    0xAD, 0x15, 0x40, // LDA $4015
+    0x85, 0x05, // STA $05
+];
+
+/// Runs `CODE` and returns the bytes it stored in $00-$05.
+fn run() -> [u8; 6] {
+    let mut emu = Emu::new(&make_rom(&CODE));
+    // The reset sequence and 16 instructions.
+    for _ in 0..17 {
+        emu.step();
+    }
+    std::array::from_fn(|i| emu.peek(i as u16).unwrap())
+}
+
+#[test]
+fn unmapped() {
+    let results = run();
+    // The last byte on the bus is the high byte of the address.
+    assert_eq!(results[0], 0x40);
+    assert_eq!(results[1], 0x50);
+}
+
+#[test]
+fn controller_ports() {
+    // The dummy read of $3F16 puts the PPU's latch, $FF, on the bus. Only
+    // bits 5-7 of $4016 are open bus, and the controller drives the rest to
+    // 0 since nothing is pressed.
+    assert_eq!(run()[2], 0xE0);
+}
+
+#[test]
+fn ppu_latch() {
+    // $2002 isn't emulated, so it returns the PPU's I/O latch.
+    assert_eq!(run()[3], 0xFF);
+}
+
+#[test]
+fn apu_status() {
+    let results = run();
+    // The dummy read of $3F15 puts the PPU's latch on the bus, and bit 5 of
+    // $4015 comes from the bus.
+    assert_eq!(results[4] & 0x20, 0x20);
+    assert_eq!(results[5] & 0x20, 0x00);
+}
@@ -0,0 +1,133 @@
+use backend::Emu;
+
+use crate::make_rom;
+
+/// Makes a ROM whose header asks for vertical mirroring and runs `code`.
+fn make_vertical_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = make_rom(code);
+    rom[6] |= 1;
+    rom
+}
+
+/// Runs `code` on `rom` until it's done.
+fn run(rom: &[u8], len: usize) -> Emu {
+    let mut emu = Emu::new(rom);
+    while emu.cpu_state().pc != 0x8000 + len as u16 {
+        emu.step();
+    }
+    emu
+}
+
+#[test]
+fn vram_writes() {
+    let code = [
+        0xA9, 0x21, // LDA #$21
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x08, // LDA #$08
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x11, // LDA #$11
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x22, // LDA #$22
+        0x8D, 0x07, 0x20, // STA $2007
+        // Increment by 32 instead.
+        0xA9, 0x04, // LDA #$04
+        0x8D, 0x00, 0x20, // STA $2000
+        0xA9, 0x33, // LDA #$33
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x44, // LDA #$44
+        0x8D, 0x07, 0x20, // STA $2007
+    ];
+    let emu = run(&make_rom(&code), code.len());
+    assert_eq!(emu.peek_ppu(0x2108), 0x11);
+    assert_eq!(emu.peek_ppu(0x2109), 0x22);
+    assert_eq!(emu.peek_ppu(0x210A), 0x33);
+    assert_eq!(emu.peek_ppu(0x210B), 0x00);
+    assert_eq!(emu.peek_ppu(0x212A), 0x44);
+}
+
+#[test]
+fn read_buffer() {
+    let code = [
+        0xA9, 0x20, // LDA #$20
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x5A, // LDA #$5A
+        0x8D, 0x07, 0x20, // STA $2007
+        // Reading $2002 makes the next write to $2006 the first one again.
+        0x8D, 0x06, 0x20, // STA $2006
+        0xAD, 0x02, 0x20, // LDA $2002
+        0xA9, 0x20, // LDA #$20
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xAD, 0x07, 0x20, // LDA $2007
+        0x85, 0x00, // STA $00
+        0xAD, 0x07, 0x20, // LDA $2007
+        0x85, 0x01, // STA $01
+    ];
+    let mut emu = run(&make_rom(&code), code.len());
+    // The first read returns the stale buffer.
+    assert_eq!(emu.peek(0x0000), Some(0x00));
+    assert_eq!(emu.peek(0x0001), Some(0x5A));
+}
+
+#[test]
+fn palette() {
+    let code = [
+        0xA9, 0x3F, // LDA #$3F
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x10, // LDA #$10
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0xFF, // LDA #$FF
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x3F, // LDA #$3F
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xAD, 0x07, 0x20, // LDA $2007
+        0x85, 0x00, // STA $00
+    ];
+    let mut emu = run(&make_rom(&code), code.len());
+    // $3F10 is a mirror of $3F00, and entries are 6 bits.
+    assert_eq!(emu.peek_ppu(0x3F00), 0x3F);
+    assert_eq!(emu.peek_ppu(0x3F20), 0x3F);
+    // Palette reads aren't buffered, and the top two bits are the latch's,
+    // which the write to $2006 left at $00.
+    assert_eq!(emu.peek(0x0000), Some(0x3F));
+}
+
+#[test]
+fn mirroring() {
+    let code = [
+        0xA9, 0x24, // LDA #$24
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x77, // LDA #$77
+        0x8D, 0x07, 0x20, // STA $2007
+    ];
+    let emu = run(&make_rom(&code), code.len());
+    assert_eq!(emu.peek_ppu(0x2000), 0x77);
+    assert_eq!(emu.peek_ppu(0x2800), 0x00);
+    assert_eq!(emu.peek_ppu(0x3400), 0x77);
+
+    let emu = run(&make_vertical_rom(&code), code.len());
+    assert_eq!(emu.peek_ppu(0x2000), 0x00);
+    assert_eq!(emu.peek_ppu(0x2C00), 0x77);
+}
+
+#[test]
+fn chr_ram() {
+    let code = [
+        0xA9, 0x01, // LDA #$01
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x23, // LDA #$23
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x99, // LDA #$99
+        0x8D, 0x07, 0x20, // STA $2007
+    ];
+    // ROMs without CHR ROM have CHR RAM.
+    let emu = run(&make_rom(&code), code.len());
+    assert_eq!(emu.peek_ppu(0x0123), 0x99);
+}
@@ -0,0 +1,61 @@
+use backend::{config::Config, debugger::BreakReason, region::Region, Emu};
+
+use crate::make_rom;
+
+/// Makes an NES 2.0 ROM for `region`.
+fn make_nes2_rom(region: u8) -> Vec<u8> {
+    let mut rom = make_rom(&[]);
+    rom[7] = 0x08;
+    rom[12] = region;
+    rom
+}
+
+#[test]
+fn from_header() {
+    assert_eq!(Emu::new(&make_rom(&[])).region(), Region::Ntsc);
+    assert_eq!(Emu::new(&make_nes2_rom(1)).region(), Region::Pal);
+    assert_eq!(Emu::new(&make_nes2_rom(2)).region(), Region::Ntsc);
+    assert_eq!(Emu::new(&make_nes2_rom(3)).region(), Region::Dendy);
+
+    // iNES has a PAL flag too.
+    let mut rom = make_rom(&[]);
+    rom[9] = 0x01;
+    assert_eq!(Emu::new(&rom).region(), Region::Pal);
+}
+
+#[test]
+fn override_header() {
+    let config = Config { region: Some(Region::Dendy), ..Config::default() };
+    let emu = Emu::with_config(&make_nes2_rom(1), config);
+    assert_eq!(emu.region(), Region::Dendy);
+}
+
+#[test]
+fn clock_rates() {
+    assert_eq!(Region::Ntsc.cpu_clock_rate(), 1_789_773);
+    assert_eq!(Region::Pal.cpu_clock_rate(), 1_662_607);
+    assert_eq!(Region::Dendy.cpu_clock_rate(), 1_773_447);
+}
+
+/// Returns the number of CPU cycles in the first frame.
+fn first_frame_cycles(region: Region) -> u64 {
+    let config = Config { region: Some(region), ..Config::default() };
+    let mut emu = Emu::with_config(&make_rom(&[]), config);
+    assert_eq!(emu.run_to_frame(), BreakReason::Frame);
+    let cycles = emu.cpu_state().cycles;
+    assert_eq!(emu.master_clock(), cycles * region.cpu_divider());
+    cycles
+}
+
+#[test]
+fn frame_length() {
+    // Frames are 262 scanlines of 341 dots on NTSC and 312 on PAL and the
+    // Dendy. The run stops after the instruction that crosses into the next
+    // frame, which is a 2-cycle NOP.
+    for (region, cycles) in
+        [(Region::Ntsc, 29781), (Region::Pal, 33248), (Region::Dendy, 35464)]
+    {
+        let frame_cycles = first_frame_cycles(region);
+        assert!((cycles..cycles + 2).contains(&frame_cycles), "{region:?}");
+    }
+}
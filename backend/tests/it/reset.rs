@@ -0,0 +1,140 @@
+use backend::{
+    config::{Config, RamInit},
+    Emu, Status,
+};
+
+use crate::make_rom;
+
+const CODE: [u8; 9] = [
+    0xA9, 0x42, // LDA #$42
+    0x85, 0x00, // STA $00
+    0xE6, 0x01, // INC $01
+    0x4C, 0x06, 0x80, // JMP $8006
+];
+
+/// Makes an emulator that has run the reset sequence and `CODE` up to the
+/// JMP.
+fn make_emu(config: Config) -> Emu {
+    let mut emu = Emu::with_config(&make_rom(&CODE), config);
+    for _ in 0..4 {
+        emu.step();
+    }
+    assert_eq!(emu.cpu_state().pc, 0x8006);
+    emu
+}
+
+#[test]
+fn reset() {
+    let mut emu = make_emu(Config::default());
+    assert_eq!(emu.peek(0x0001), Some(0x01));
+    let s = emu.cpu_state().s;
+
+    emu.reset();
+    // The reset sequence runs before the next instruction, the LDA.
+    emu.step();
+    let state = emu.cpu_state();
+    assert_eq!(state.pc, 0x8002);
+    assert_eq!(state.s, s.wrapping_sub(3));
+    assert_eq!(state.p & 0x04, 0x04);
+
+    // RAM keeps its contents.
+    emu.step();
+    emu.step();
+    assert_eq!(emu.peek(0x0001), Some(0x02));
+}
+
+#[test]
+fn power_cycle() {
+    let config = Config { cpu_ram: RamInit::Ones, ..Config::default() };
+    let mut emu = make_emu(config);
+    emu.poke(0x0002, 0x12);
+
+    emu.power_cycle();
+    emu.step();
+    let state = emu.cpu_state();
+    assert_eq!(state.pc, 0x8002);
+    assert_eq!(state.a, 0x42);
+    assert_eq!(state.s, 0xFD);
+
+    // RAM is filled with its power-on contents again.
+    emu.step();
+    emu.step();
+    assert_eq!(emu.peek(0x0000), Some(0x42));
+    assert_eq!(emu.peek(0x0001), Some(0x00));
+    assert_eq!(emu.peek(0x0002), Some(0xFF));
+}
+
+#[test]
+fn power_cycle_resets_magic() {
+    let code = [
+        0xA9, 0x00, // LDA #$00
+        0xAB, 0xFF, // LXA #$FF
+    ];
+    let mut emu = Emu::new(&make_rom(&code));
+    emu.set_magic(0x00);
+    emu.power_cycle();
+    for _ in 0..2 {
+        emu.step();
+    }
+    // LXA sets A to (A | magic) & #$FF.
+    assert_eq!(emu.cpu_state().a, 0xEE);
+}
+
+#[test]
+fn reset_unjams() {
+    let code = [
+        0xE6, 0x00, // INC $00
+        0xA6, 0x00, // LDX $00
+        0xCA, // DEX
+        0xD0, 0x01, // BNE $8008
+        0x02, // JAM
+    ];
+    let mut emu = Emu::new(&make_rom(&code));
+    for _ in 0..6 {
+        emu.step();
+    }
+    assert_eq!(emu.status(), Status::Jammed { pc: 0x8007 });
+
+    // $00 is 2 after the reset, so the JAM is skipped.
+    emu.reset();
+    for _ in 0..5 {
+        emu.step();
+    }
+    assert_eq!(emu.status(), Status::Running);
+    assert_eq!(emu.cpu_state().pc, 0x8009);
+}
+
+#[test]
+fn reset_clears_ppu_registers() {
+    let code = [
+        0xA5, 0x00, // LDA $00
+        0xD0, 0x0F, // BNE $8013
+        // Before the reset, increment by 32 and leave the write toggle set.
+        0xE6, 0x00, // INC $00
+        0xA9, 0x04, // LDA #$04
+        0x8D, 0x00, 0x20, // STA $2000
+        0xA9, 0x21, // LDA #$21
+        0x8D, 0x06, 0x20, // STA $2006
+        0x4C, 0x10, 0x80, // JMP $8010
+        // After it.
+        0xA9, 0x20, // LDA #$20
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x11, // LDA #$11
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x22, // LDA #$22
+        0x8D, 0x07, 0x20, // STA $2007
+        0x4C, 0x27, 0x80, // JMP $8027
+    ];
+    let mut emu = Emu::new(&make_rom(&code));
+    while emu.cpu_state().pc != 0x8010 {
+        emu.step();
+    }
+    emu.reset();
+    while emu.cpu_state().pc != 0x8027 {
+        emu.step();
+    }
+    assert_eq!(emu.peek_ppu(0x2000), 0x11);
+    assert_eq!(emu.peek_ppu(0x2001), 0x22);
+}
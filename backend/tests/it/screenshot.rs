@@ -0,0 +1,165 @@
+//! Screenshot tests run a ROM for a number of frames, hash the last frame,
+//! and compare the hash to the one checked in to `screenshots.txt` under the
+//! test's name. Set `DUNES_BLESS` to write the new hashes instead. A mismatch
+//! dumps the frame to a PNG in the target directory so it can be looked at.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use backend::{controller, palette, Emu};
+
+use crate::make_rom;
+
+/// The size of a frame in pixels.
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 240;
+
+/// Tests run in parallel, so blessing has to update the file one test at a
+/// time.
+static HASHES: Mutex<()> = Mutex::new(());
+
+/// Runs `rom` for `frames` frames and checks the hash of the last one.
+/// `input` lists the frames that the buttons on the first controller change
+/// on and what they change to.
+pub fn check(name: &str, rom: &[u8], frames: u64, input: &[(u64, u8)]) {
+    let mut emu = Emu::new(rom);
+    for frame in 0..frames {
+        for &(_, buttons) in input.iter().filter(|&&(at, _)| at == frame) {
+            emu.set_buttons(0, buttons);
+        }
+        emu.run_to_frame();
+        emu.discard_samples();
+    }
+
+    let hash = format!("{:016x}", hash(emu.framebuffer()));
+    let _lock = HASHES.lock().unwrap_or_else(|err| err.into_inner());
+    let path = hashes_path();
+    let mut hashes = read_hashes(&path);
+    if env::var_os("DUNES_BLESS").is_some() {
+        hashes.insert(name.to_string(), hash);
+        write_hashes(&path, &hashes);
+        return;
+    }
+
+    let Some(expected) = hashes.get(name) else {
+        panic!("{name} doesn't have a hash; run with DUNES_BLESS=1 to add it");
+    };
+    if *expected != hash {
+        let png = dump_png(name, emu.framebuffer());
+        panic!(
+            "{name} hashed to {hash} instead of {expected}; the frame is in \
+             {}, and DUNES_BLESS=1 accepts it",
+            png.display()
+        );
+    }
+}
+
+/// Returns the FNV-1a hash of `frame`.
+fn hash(frame: &[u8]) -> u64 {
+    frame.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+fn hashes_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/it/screenshots.txt")
+}
+
+/// Reads the lines of `name hash` pairs at `path`.
+fn read_hashes(path: &Path) -> BTreeMap<String, String> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, hash)| (name.to_string(), hash.to_string()))
+        .collect()
+}
+
+fn write_hashes(path: &Path, hashes: &BTreeMap<String, String>) {
+    let text: String =
+        hashes.iter().map(|(name, hash)| format!("{name} {hash}\n")).collect();
+    fs::write(path, text).unwrap();
+}
+
+/// Writes `frame` to a PNG named after the test and returns its path.
+fn dump_png(name: &str, frame: &[u8]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("screenshots");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.png"));
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(&path).unwrap()),
+        WIDTH,
+        HEIGHT,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data: Vec<u8> =
+        frame.iter().flat_map(|&color| palette::rgb(color)).collect();
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&data).unwrap();
+    path
+}
+
+/// Makes a ROM that draws an 8x8 white square in the top left corner while A
+/// is held on the first controller.
+fn make_square_rom() -> Vec<u8> {
+    let mut code = vec![
+        // Black backdrop and white for color 1 of the first palette.
+        0xA9, 0x3F, // LDA #$3F
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x0F, // LDA #$0F
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x30, // LDA #$30
+        0x8D, 0x07, 0x20, // STA $2007
+        // Tile 1 is solid color 1.
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x10, // LDA #$10
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0xFF, // LDA #$FF
+        0xA2, 0x08, // LDX #$08
+        0x8D, 0x07, 0x20, // STA $2007
+        0xCA, // DEX
+        0xD0, 0xFA, // BNE -6
+        // Show the background.
+        0xA9, 0x08, // LDA #$08
+        0x8D, 0x01, 0x20, // STA $2001
+    ];
+    let [low, high] = (0x8000 + code.len() as u16).to_le_bytes();
+    code.extend([
+        // Read A.
+        0xA9, 0x01, // LDA #$01
+        0x8D, 0x16, 0x40, // STA $4016
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x16, 0x40, // STA $4016
+        0xAD, 0x16, 0x40, // LDA $4016
+        0x29, 0x01, // AND #$01
+        // Put tile 0 or 1 in the top left corner.
+        0xA2, 0x20, // LDX #$20
+        0x8E, 0x06, 0x20, // STX $2006
+        0xA2, 0x00, // LDX #$00
+        0x8E, 0x06, 0x20, // STX $2006
+        0x8D, 0x07, 0x20, // STA $2007
+        0x4C, low, high, // JMP to the read
+    ]);
+    make_rom(&code)
+}
+
+#[test]
+fn square_hidden() {
+    check("square_hidden", &make_square_rom(), 3, &[]);
+}
+
+#[test]
+fn square_shown() {
+    let input = [(1, controller::A), (2, controller::A | controller::B)];
+    check("square_shown", &make_square_rom(), 3, &input);
+}
@@ -0,0 +1,151 @@
+//! Runs a ROM without a window or audio device. See `frontend::headless`.
+
+use std::{env, fs, process::ExitCode};
+
+use backend::region::Region;
+use frontend::headless::{self, Options, Stop};
+
+/// Parses a hex number with an optional `$` or `0x` prefix.
+fn parse_hex(s: &str) -> Option<u16> {
+    let hex = s.strip_prefix('$').or(s.strip_prefix("0x")).unwrap_or(s);
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// Parses an address and a byte in hex separated by `=`, e.g., `$6000=80`.
+fn parse_ram_value(s: &str) -> Option<(u16, u8)> {
+    let (addr, data) = s.split_once('=')?;
+    Some((parse_hex(addr)?, parse_hex(data)?.try_into().ok()?))
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args();
+    // Skip the executable path.
+    args.next();
+
+    let mut options = Options { frames: 60, ..Options::default() };
+    let mut file_path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let Some(n) = args.next().and_then(|n| n.parse().ok()) else {
+                    eprintln!(
+                        "duNES: error: expected a number after --frames"
+                    );
+                    return ExitCode::FAILURE;
+                };
+                options.frames = n;
+            }
+            "--seconds" => {
+                let Some(n) = args.next().and_then(|n| n.parse().ok()) else {
+                    eprintln!(
+                        "duNES: error: expected a number after --seconds"
+                    );
+                    return ExitCode::FAILURE;
+                };
+                options.seconds = Some(n);
+            }
+            "--track" => {
+                // Tracks are one-based on the command line.
+                let Some(n) = args.next().and_then(|n| n.parse::<u8>().ok())
+                else {
+                    eprintln!("duNES: error: expected a number after --track");
+                    return ExitCode::FAILURE;
+                };
+                options.song = Some(n.saturating_sub(1));
+            }
+            "--until-pc" => {
+                let Some(addr) = args.next().as_deref().and_then(parse_hex)
+                else {
+                    eprintln!(
+                        "duNES: error: expected an address after --until-pc"
+                    );
+                    return ExitCode::FAILURE;
+                };
+                options.until_pc = Some(addr);
+            }
+            "--until-ram" => {
+                let Some(value) =
+                    args.next().as_deref().and_then(parse_ram_value)
+                else {
+                    eprintln!(
+                        "duNES: error: expected an address and a value like \
+                         $6000=80 after --until-ram"
+                    );
+                    return ExitCode::FAILURE;
+                };
+                options.until_ram.push(value);
+            }
+            "--region" => {
+                let region = match args.next().as_deref() {
+                    Some("ntsc") => Region::Ntsc,
+                    Some("pal") => Region::Pal,
+                    Some("dendy") => Region::Dendy,
+                    _ => {
+                        eprintln!(
+                            "duNES: error: expected ntsc, pal, or dendy after \
+                             --region"
+                        );
+                        return ExitCode::FAILURE;
+                    }
+                };
+                options.config.region = Some(region);
+            }
+            "--ram" | "--wav" | "--trace" | "--screenshot" => {
+                let Some(path) = args.next() else {
+                    eprintln!("duNES: error: expected a path after {arg}");
+                    return ExitCode::FAILURE;
+                };
+                let output = match arg.as_str() {
+                    "--ram" => &mut options.ram,
+                    "--wav" => &mut options.wav,
+                    "--trace" => &mut options.trace,
+                    _ => &mut options.screenshot,
+                };
+                *output = Some(path.into());
+            }
+            "--stems" => options.stems = true,
+            _ if arg.starts_with('-') => {
+                eprintln!("duNES: error: unknown option {arg}");
+                return ExitCode::FAILURE;
+            }
+            _ => file_path = Some(arg),
+        }
+    }
+
+    let Some(file_path) = file_path else {
+        eprintln!("duNES: error: expected a ROM file");
+        return ExitCode::FAILURE;
+    };
+    let rom = match fs::read(&file_path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("duNES: error: failed to read {file_path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match headless::run(&rom, &options) {
+        Ok((Stop::Frames, frames)) => {
+            println!("ran {frames} frames");
+            ExitCode::SUCCESS
+        }
+        Ok((Stop::Reached(pc), frames)) => {
+            println!("reached ${pc:04X} after {frames} frames");
+            ExitCode::SUCCESS
+        }
+        Ok((Stop::RamValue { addr, data }, frames)) => {
+            println!(
+                "${addr:04X} was set to ${data:02X} after {frames} frames"
+            );
+            ExitCode::SUCCESS
+        }
+        Ok((Stop::Jammed(pc), frames)) => {
+            println!("the CPU jammed at ${pc:04X} after {frames} frames");
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("duNES: error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
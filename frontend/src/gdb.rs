@@ -0,0 +1,401 @@
+//! A GDB remote serial protocol stub.
+//!
+//! GDB doesn't know about the 6502, so the registers are described here
+//! instead of in a target description. `g` packets send them in this order,
+//! as little-endian hex:
+//!
+//! | Number | Register | Size    |
+//! |--------|----------|---------|
+//! | 0      | A        | 1 byte  |
+//! | 1      | X        | 1 byte  |
+//! | 2      | Y        | 1 byte  |
+//! | 3      | P        | 1 byte  |
+//! | 4      | SP       | 1 byte  |
+//! | 5      | PC       | 2 bytes |
+//!
+//! Memory is read and written without side effects, so I/O registers can't
+//! be accessed. Software breakpoints (`Z0`) and write, read, and access
+//! watchpoints (`Z2`-`Z4`) use the backend's debugger.
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use backend::{
+    debugger::{Access, BreakReason, Watchpoint},
+    Emu,
+};
+use tracing::info;
+
+/// The number of CPU cycles to run between checks for an interrupt from GDB,
+/// about a frame.
+const SLICE_CYCLES: u64 = 29781;
+
+/// The size of the registers in a `g` packet in bytes.
+const REGISTERS_SIZE: usize = 7;
+
+/// The largest packet that GDB can send.
+const PACKET_SIZE: usize = 4096;
+
+/// The byte GDB sends to interrupt the target.
+const INTERRUPT: u8 = 0x03;
+
+/// Waits for GDB to connect to `addr` and then serves it until it detaches.
+/// The emulator is stopped before the reset sequence, which runs with the
+/// first instruction.
+pub fn serve(rom: &[u8], addr: impl ToSocketAddrs) -> io::Result<()> {
+    let mut emu = Emu::try_new(rom)?;
+    let listener = TcpListener::bind(addr)?;
+    info!("waiting for GDB on {}", listener.local_addr()?);
+    let (stream, _) = listener.accept()?;
+    Stub::new(&mut emu, stream)?.run()
+}
+
+/// Serves one GDB connection.
+pub struct Stub<'a> {
+    emu: &'a mut Emu,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// The reply to `?`, which says why the emulator last stopped. It's made
+    /// when the emulator stops since the watchpoint that was hit can be
+    /// removed before GDB asks.
+    stop: String,
+}
+
+impl<'a> Stub<'a> {
+    pub fn new(emu: &'a mut Emu, stream: TcpStream) -> io::Result<Stub<'a>> {
+        stream.set_nodelay(true)?;
+        Ok(Stub {
+            emu,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            // SIGTRAP.
+            stop: "S05".to_string(),
+        })
+    }
+
+    /// Handles packets until GDB detaches, kills the target, or disconnects.
+    pub fn run(&mut self) -> io::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            let reply = match packet.first() {
+                Some(b'?') => Some(self.stop.clone()),
+                Some(b'c') => self.resume(&packet[1..], false)?,
+                Some(b's') => self.resume(&packet[1..], true)?,
+                Some(b'g') => self.read_registers(),
+                Some(b'G') => self.write_registers(&packet[1..]),
+                Some(b'p') => self.read_register(&packet[1..]),
+                Some(b'P') => self.write_register(&packet[1..]),
+                Some(b'm') => self.read_memory(&packet[1..]),
+                Some(b'M') => self.write_memory(&packet[1..]),
+                Some(b'Z') => self.set_breakpoint(&packet[1..], true),
+                Some(b'z') => self.set_breakpoint(&packet[1..], false),
+                Some(b'H') => Some("OK".to_string()),
+                Some(b'D') => {
+                    self.write_packet("OK")?;
+                    return Ok(());
+                }
+                Some(b'k') => return Ok(()),
+                Some(b'q') => self.query(&packet[1..]),
+                _ => None,
+            };
+            // Unsupported packets get an empty reply.
+            self.write_packet(&reply.unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    /// Reads the next packet and acknowledges it. Returns `None` if GDB
+    /// disconnected.
+    fn read_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if self.reader.read_until(b'$', &mut Vec::new())? == 0 {
+                return Ok(None);
+            }
+            let mut packet = Vec::new();
+            self.reader.read_until(b'#', &mut packet)?;
+            if packet.pop() != Some(b'#') {
+                return Ok(None);
+            }
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum)?;
+
+            let expected = parse_hex(&checksum);
+            let actual =
+                packet.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+            if expected == Some(actual as u64) {
+                self.writer.write_all(b"+")?;
+                return Ok(Some(unescape(&packet)));
+            }
+            self.writer.write_all(b"-")?;
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.writer, "${data}#{checksum:02x}")
+    }
+
+    /// Returns true if GDB sent an interrupt. Acks before it are dropped, and
+    /// packets are left for `read_packet`.
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.reader.get_ref().set_nonblocking(true)?;
+        let result = match self.reader.fill_buf() {
+            Ok(buf) => {
+                let start = buf.iter().position(|&b| b == b'$');
+                let skipped = &buf[..start.unwrap_or(buf.len())];
+                match skipped.iter().position(|&b| b == INTERRUPT) {
+                    Some(i) => Ok((i + 1, true)),
+                    // `read_packet` would drop these bytes anyway. They're
+                    // dropped now so that later bytes are read.
+                    None => Ok((skipped.len(), false)),
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok((0, false)),
+            Err(err) => Err(err),
+        };
+        self.reader.get_ref().set_nonblocking(false)?;
+        let (len, interrupted) = result?;
+        self.reader.consume(len);
+        Ok(interrupted)
+    }
+
+    fn resume(
+        &mut self,
+        args: &[u8],
+        step: bool,
+    ) -> io::Result<Option<String>> {
+        if !args.is_empty() {
+            let Some(pc) = parse_hex(args) else {
+                return Ok(Some("E01".to_string()));
+            };
+            let mut state = self.emu.cpu_state();
+            state.pc = pc as u16;
+            self.emu.set_cpu_state(&state);
+        }
+
+        let reason = if step {
+            self.emu.step_into()
+        } else {
+            loop {
+                let reason = self.emu.run_until_break(SLICE_CYCLES);
+                self.emu.discard_samples();
+                if reason != BreakReason::CycleLimit {
+                    break reason;
+                }
+                if self.interrupted()? {
+                    break reason;
+                }
+            }
+        };
+        self.stop = self.stop_reply(reason);
+        Ok(Some(self.stop.clone()))
+    }
+
+    fn stop_reply(&mut self, reason: BreakReason) -> String {
+        match reason {
+            BreakReason::Watchpoint { index, addr, access, .. } => {
+                let watchpoints = self.emu.debugger().watchpoints();
+                let both = watchpoints.get(index).is_some_and(|watchpoint| {
+                    watchpoint.read && watchpoint.write
+                });
+                let kind = match access {
+                    _ if both => "awatch",
+                    Access::Read => "rwatch",
+                    Access::Write => "watch",
+                    Access::Execute => return "S05".to_string(),
+                };
+                format!("T05{kind}:{addr:x};")
+            }
+            // SIGILL.
+            BreakReason::Jammed => "S04".to_string(),
+            // SIGINT, since GDB interrupted the run.
+            BreakReason::CycleLimit => "S02".to_string(),
+            // SIGTRAP.
+            _ => "S05".to_string(),
+        }
+    }
+
+    fn registers(&self) -> [u8; REGISTERS_SIZE] {
+        let state = self.emu.cpu_state();
+        let [pcl, pch] = state.pc.to_le_bytes();
+        [state.a, state.x, state.y, state.p, state.s, pcl, pch]
+    }
+
+    fn set_registers(&mut self, registers: [u8; REGISTERS_SIZE]) {
+        let mut state = self.emu.cpu_state();
+        let [a, x, y, p, s, pcl, pch] = registers;
+        state.a = a;
+        state.x = x;
+        state.y = y;
+        state.p = p;
+        state.s = s;
+        state.pc = u16::from_le_bytes([pcl, pch]);
+        self.emu.set_cpu_state(&state);
+    }
+
+    fn read_registers(&mut self) -> Option<String> {
+        Some(to_hex(&self.registers()))
+    }
+
+    fn write_registers(&mut self, args: &[u8]) -> Option<String> {
+        let Some(registers) =
+            from_hex(args).and_then(|bytes| bytes.try_into().ok())
+        else {
+            return Some("E01".to_string());
+        };
+        self.set_registers(registers);
+        Some("OK".to_string())
+    }
+
+    /// Returns the range of register `n` in the `g` packet's bytes.
+    fn register_range(n: u64) -> Option<std::ops::Range<usize>> {
+        match n {
+            0..=4 => Some(n as usize..n as usize + 1),
+            5 => Some(5..7),
+            _ => None,
+        }
+    }
+
+    fn read_register(&mut self, args: &[u8]) -> Option<String> {
+        let Some(range) = parse_hex(args).and_then(Stub::register_range)
+        else {
+            return Some("E01".to_string());
+        };
+        Some(to_hex(&self.registers()[range]))
+    }
+
+    fn write_register(&mut self, args: &[u8]) -> Option<String> {
+        let (n, value) = split(args, b'=')?;
+        let (Some(range), Some(value)) =
+            (parse_hex(n).and_then(Stub::register_range), from_hex(value))
+        else {
+            return Some("E01".to_string());
+        };
+        if value.len() != range.len() {
+            return Some("E01".to_string());
+        }
+        let mut registers = self.registers();
+        registers[range].copy_from_slice(&value);
+        self.set_registers(registers);
+        Some("OK".to_string())
+    }
+
+    fn read_memory(&mut self, args: &[u8]) -> Option<String> {
+        let Some((addr, len)) = parse_addr_len(args) else {
+            return Some("E01".to_string());
+        };
+        let bytes: Option<Vec<u8>> = (0..len)
+            .map(|i| self.emu.peek(addr.wrapping_add(i as u16)))
+            .collect();
+        // Memory that can't be read without side effects is an error.
+        Some(bytes.map_or("E14".to_string(), |bytes| to_hex(&bytes)))
+    }
+
+    fn write_memory(&mut self, args: &[u8]) -> Option<String> {
+        let Some((addr, data)) = split(args, b':').and_then(|(args, data)| {
+            let (addr, len) = parse_addr_len(args)?;
+            let data = from_hex(data).filter(|data| data.len() == len)?;
+            Some((addr, data))
+        }) else {
+            return Some("E01".to_string());
+        };
+        let mut poked = true;
+        for (i, &byte) in data.iter().enumerate() {
+            poked &= self.emu.poke(addr.wrapping_add(i as u16), byte);
+        }
+        Some(if poked { "OK" } else { "E14" }.to_string())
+    }
+
+    fn set_breakpoint(&mut self, args: &[u8], set: bool) -> Option<String> {
+        // For watchpoints, the length is the number of bytes watched.
+        let (kind, args) = split(args, b',')?;
+        let (addr, len) = parse_addr_len(args)?;
+        let debugger = self.emu.debugger();
+        match kind {
+            b"0" if set => debugger.add_breakpoint(addr),
+            b"0" => debugger.remove_breakpoint(addr),
+            b"2" | b"3" | b"4" => {
+                // The range stops at the end of the address space.
+                let end = (addr as usize + len.max(1) - 1).min(0xFFFF);
+                let watchpoint = Watchpoint {
+                    range: addr..=end as u16,
+                    read: kind != b"2",
+                    write: kind != b"3",
+                    execute: false,
+                    value: None,
+                };
+                if set {
+                    debugger.add_watchpoint(watchpoint);
+                } else if let Some(index) = debugger
+                    .watchpoints()
+                    .iter()
+                    .position(|wp| *wp == watchpoint)
+                {
+                    debugger.remove_watchpoint(index);
+                }
+            }
+            _ => return None,
+        }
+        Some("OK".to_string())
+    }
+
+    fn query(&mut self, args: &[u8]) -> Option<String> {
+        let (name, _) = split(args, b':').unwrap_or((args, &[]));
+        match name {
+            b"Supported" => Some(format!("PacketSize={PACKET_SIZE:x}")),
+            b"Attached" => Some("1".to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Splits `bytes` at the first `separator`.
+fn split(bytes: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let i = bytes.iter().position(|&b| b == separator)?;
+    Some((&bytes[..i], &bytes[i + 1..]))
+}
+
+fn parse_hex(hex: &[u8]) -> Option<u64> {
+    let hex = std::str::from_utf8(hex).ok()?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// Parses the `addr,length` arguments of memory packets.
+fn parse_addr_len(args: &[u8]) -> Option<(u16, usize)> {
+    let (addr, len) = split(args, b',')?;
+    let addr = parse_hex(addr).filter(|&addr| addr <= 0xFFFF)?;
+    let len = parse_hex(len).filter(|&len| len <= 0x10000)?;
+    Some((addr as u16, len as usize))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    let pairs = hex.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs.map(|pair| parse_hex(pair).map(|byte| byte as u8)).collect()
+}
+
+/// Undoes the escaping of `}`, `#`, `$`, and `*` in packet data.
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'}' {
+            if let Some(&escaped) = bytes.next() {
+                unescaped.push(escaped ^ 0x20);
+            }
+        } else {
+            unescaped.push(byte);
+        }
+    }
+    unescaped
+}
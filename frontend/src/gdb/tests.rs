@@ -0,0 +1,166 @@
+use std::{thread, time::Duration};
+
+use super::*;
+use crate::test_rom::make_rom;
+
+const CODE: [u8; 11] = [
+    0xEA, // NOP
+    0xA9, 0x42, // LDA #$42
+    0x8D, 0x00, 0x02, // STA $0200
+    0x4C, 0x08, 0x80, // JMP $8008
+    0xEA, 0xEA, // Padding
+];
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    stub: Option<thread::JoinHandle<()>>,
+}
+
+impl Client {
+    /// Starts a stub on a loopback port and connects to it.
+    fn connect() -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stub = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut emu = Emu::new(&make_rom(&CODE));
+            Stub::new(&mut emu, stream).unwrap().run().unwrap();
+        });
+        let writer = TcpStream::connect(addr).unwrap();
+        Client {
+            reader: BufReader::new(writer.try_clone().unwrap()),
+            writer,
+            stub: Some(stub),
+        }
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        self.writer.write_all(bytes).unwrap();
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    /// Sends a packet and returns the reply.
+    fn send(&mut self, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        self.send_raw(format!("${data}#{checksum:02x}").as_bytes());
+        assert_eq!(self.read_byte(), b'+');
+        self.reply()
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = Vec::new();
+        self.reader.read_until(b'#', &mut reply).unwrap();
+        reply.pop();
+        let mut checksum = [0; 2];
+        self.reader.read_exact(&mut checksum).unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    /// Detaches and waits for the stub to finish.
+    fn detach(mut self) {
+        assert_eq!(self.send("D"), "OK");
+        self.stub.take().unwrap().join().unwrap();
+    }
+}
+
+#[test]
+fn registers() {
+    let mut client = Client::connect();
+    assert_eq!(client.send("?"), "S05");
+
+    // The first step runs the reset sequence and the NOP.
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("g"), "42000034fd0380");
+    assert_eq!(client.send("p5"), "0380");
+
+    assert_eq!(client.send("P1=07"), "OK");
+    assert_eq!(client.send("p1"), "07");
+    assert_eq!(client.send("G010203a5ff0880"), "OK");
+    assert_eq!(client.send("g"), "010203a5ff0880");
+    client.detach();
+}
+
+#[test]
+fn memory() {
+    let mut client = Client::connect();
+    assert_eq!(client.send("m8000,3"), "eaa942");
+    assert_eq!(client.send("M0300,2:beef"), "OK");
+    assert_eq!(client.send("m0300,2"), "beef");
+    // I/O registers can't be accessed without side effects.
+    assert_eq!(client.send("m2000,1"), "E14");
+    assert_eq!(client.send("M4000,1:00"), "E14");
+    // Malformed packets are errors.
+    assert_eq!(client.send("M0300,2:be"), "E01");
+    assert_eq!(client.send("M0300"), "E01");
+    assert_eq!(client.send("m0300"), "E01");
+    client.detach();
+}
+
+#[test]
+fn breakpoint() {
+    let mut client = Client::connect();
+    assert_eq!(client.send("Z0,8003,1"), "OK");
+    assert_eq!(client.send("c"), "S05");
+    assert_eq!(client.send("p5"), "0380");
+    assert_eq!(client.send("m0200,1"), "00");
+
+    assert_eq!(client.send("z0,8003,1"), "OK");
+    assert_eq!(client.send("Z2,0200,1"), "OK");
+    assert_eq!(client.send("c"), "T05watch:200;");
+    assert_eq!(client.send("m0200,1"), "42");
+
+    // The stop reply doesn't change when the watchpoint is removed.
+    assert_eq!(client.send("z2,0200,1"), "OK");
+    assert_eq!(client.send("?"), "T05watch:200;");
+    client.detach();
+}
+
+#[test]
+fn watchpoint_to_end_of_memory() {
+    let mut client = Client::connect();
+    assert_eq!(client.send("Z2,0,10000"), "OK");
+    assert_eq!(client.send("Z3,ff00,10000"), "OK");
+    assert_eq!(client.send("z3,ff00,10000"), "OK");
+    client.detach();
+}
+
+#[test]
+fn interrupt() {
+    let mut client = Client::connect();
+    client.send_raw(b"$c#63");
+    assert_eq!(client.read_byte(), b'+');
+    client.send_raw(&[INTERRUPT]);
+    assert_eq!(client.reply(), "S02");
+    client.detach();
+}
+
+#[test]
+fn interrupt_after_acks() {
+    let mut client = Client::connect();
+    client.send_raw(b"$c#63");
+    assert_eq!(client.read_byte(), b'+');
+    // The interrupt isn't the first byte the stub has buffered.
+    client.send_raw(b"+");
+    thread::sleep(Duration::from_millis(20));
+    client.send_raw(&[b'+', INTERRUPT]);
+    assert_eq!(client.reply(), "S02");
+    client.detach();
+}
+
+#[test]
+fn bad_checksum() {
+    let mut client = Client::connect();
+    client.send_raw(b"$g#00");
+    assert_eq!(client.read_byte(), b'-');
+    assert_eq!(client.send("qAttached"), "1");
+    assert_eq!(client.send("vMustReplyEmpty"), "");
+    client.detach();
+}
@@ -0,0 +1,174 @@
+//! Runs a ROM without a window or audio device, e.g., for CI and scripts.
+//! The emulator runs for a number of frames or seconds of audio or until it
+//! reaches an address or RAM holds a value, and then the requested outputs
+//! are written.
+
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    mem::MaybeUninit,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use backend::{
+    config::Config,
+    debugger::{BreakReason, Watchpoint},
+    palette, Emu, Status, SAMPLE_RATE,
+};
+
+/// The size of a frame in pixels.
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 240;
+
+#[derive(Default)]
+pub struct Options {
+    /// The number of frames to run for.
+    pub frames: u64,
+    /// Run for this many seconds of audio instead of `frames`.
+    pub seconds: Option<u32>,
+    /// Play this NSF song (zero-based) instead of the first one.
+    pub song: Option<u8>,
+    /// Stop early once the CPU reaches this address.
+    pub until_pc: Option<u16>,
+    /// Stop early once one of these addresses is written with its value.
+    pub until_ram: Vec<(u16, u8)>,
+    pub config: Config,
+    /// Write the CPU's internal RAM to this path at the end.
+    pub ram: Option<PathBuf>,
+    /// Record audio to this WAV file.
+    pub wav: Option<PathBuf>,
+    /// Record each channel to its own file too.
+    pub stems: bool,
+    /// Write a trace in the format of nestest.log to this path.
+    pub trace: Option<PathBuf>,
+    /// Write the last frame to this path as a PNG at the end.
+    pub screenshot: Option<PathBuf>,
+}
+
+/// Why a run stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// All of the frames (or seconds) ran.
+    Frames,
+    /// The CPU reached `Options::until_pc`.
+    Reached(u16),
+    /// An address in `Options::until_ram` was written with its value.
+    RamValue { addr: u16, data: u8 },
+    /// The CPU executed the JAM opcode at the address.
+    Jammed(u16),
+}
+
+/// Runs `rom` with `options` and returns why it stopped and the number of
+/// frames that ran.
+pub fn run(rom: &[u8], options: &Options) -> io::Result<(Stop, u64)> {
+    let mut emu = Emu::try_with_config(rom, options.config)?;
+    if let Some(song) = options.song {
+        if !emu.start_song(song) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("there isn't a track {}", song as u16 + 1),
+            ));
+        }
+    }
+    if let Some(path) = &options.wav {
+        emu.start_recording(path, options.stems)?;
+    }
+    let trace = match &options.trace {
+        Some(path) => {
+            let writer =
+                Rc::new(RefCell::new(BufWriter::new(File::create(path)?)));
+            emu.set_tracer(Some(Box::new({
+                let writer = writer.clone();
+                move |line| {
+                    // Errors come up again when the trace is flushed.
+                    let _ = writeln!(writer.borrow_mut(), "{line}");
+                }
+            })));
+            Some(writer)
+        }
+        None => None,
+    };
+    if let Some(pc) = options.until_pc {
+        emu.debugger().add_breakpoint(pc);
+    }
+    for &(addr, data) in &options.until_ram {
+        emu.debugger().add_watchpoint(Watchpoint {
+            range: addr..=addr,
+            read: false,
+            write: true,
+            execute: false,
+            value: Some(data),
+        });
+    }
+
+    let mut stop = Stop::Frames;
+    let mut frames = 0;
+    let mut samples_left =
+        options.seconds.map(|seconds| seconds as usize * SAMPLE_RATE as usize);
+    while samples_left.map_or(frames < options.frames, |left| left > 0) {
+        let reason = emu.run_to_frame();
+        match &mut samples_left {
+            // Discarded samples are recorded too, so the ones past the end
+            // are left in the buffers instead.
+            Some(left) => {
+                let len = emu.samples().min(*left);
+                let mut chunk = vec![MaybeUninit::uninit(); len * 2];
+                emu.fill(&mut chunk);
+                *left -= len;
+            }
+            None => emu.discard_samples(),
+        }
+        match reason {
+            BreakReason::Frame => frames += 1,
+            BreakReason::Breakpoint(pc) => {
+                stop = Stop::Reached(pc);
+                break;
+            }
+            BreakReason::Watchpoint { addr, data, .. } => {
+                stop = Stop::RamValue { addr, data };
+                break;
+            }
+            BreakReason::Jammed => {
+                if let Status::Jammed { pc } = emu.status() {
+                    stop = Stop::Jammed(pc);
+                }
+                break;
+            }
+            _ => {
+                return Err(io::Error::other(format!(
+                    "the run stopped for an unexpected reason: {reason:?}"
+                )))
+            }
+        }
+    }
+
+    emu.set_tracer(None);
+    if let Some(trace) = trace {
+        trace.borrow_mut().flush()?;
+    }
+    emu.stop_recording()?;
+    if let Some(path) = &options.ram {
+        fs::write(path, emu.ram())?;
+    }
+    if let Some(path) = &options.screenshot {
+        write_png(path, emu.framebuffer())?;
+    }
+
+    Ok((stop, frames))
+}
+
+/// Writes `frame`, a frame of palette entries, to the PNG at `path`.
+fn write_png(path: &Path, frame: &[u8]) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data: Vec<u8> =
+        frame.iter().flat_map(|&color| palette::rgb(color)).collect();
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
@@ -1,25 +1,81 @@
+pub mod gdb;
+pub mod headless;
 mod tb;
+#[cfg(test)]
+mod test_rom;
 
-use std::sync::Arc;
+use std::{
+    io,
+    path::PathBuf,
+    sync::{mpsc, Arc},
+};
 
-use backend::Emu;
+use backend::{
+    config::Config, region::Region, Channel, Emu, Status, SAMPLE_RATE,
+};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     StreamConfig,
 };
 use pixels::{Pixels, SurfaceTexture};
 use rtrb::{chunks::ChunkError, RingBuffer};
-use tracing::warn;
+use tracing::{error, warn};
 use winit::{
     dpi::LogicalSize,
-    event::{Event, WindowEvent},
+    event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::EventLoop,
+    keyboard::{KeyCode, PhysicalKey},
     window::WindowBuilder,
 };
 
 use crate::tb::triple_buffer;
 
-pub fn run(rom: Vec<u8>) {
+/// The path that recordings are written to if one isn't given.
+const DEFAULT_RECORDING_PATH: &str = "dunes.wav";
+
+#[derive(Default)]
+pub struct Options {
+    /// Start recording audio to this path.
+    pub record: Option<PathBuf>,
+    /// Record each channel to its own file too.
+    pub stems: bool,
+    /// Log code and data to this .cdl file, continuing the log in it if it
+    /// exists.
+    pub cdl: Option<PathBuf>,
+    /// Emulate this region instead of the one in the ROM's header.
+    pub region: Option<Region>,
+}
+
+/// A request from the UI thread to the emulator thread.
+enum Command {
+    ToggleMute(Channel),
+    ToggleRecording,
+    /// Press the reset button.
+    Reset,
+    /// Turn the power off and on again.
+    PowerCycle,
+    /// Finish up and stop the emulator thread.
+    Exit,
+}
+
+/// Returns the channel whose mute is toggled by `key`.
+fn mute_hotkey(key: KeyCode) -> Option<Channel> {
+    match key {
+        KeyCode::Digit1 => Some(Channel::Pulse1),
+        KeyCode::Digit2 => Some(Channel::Pulse2),
+        KeyCode::Digit3 => Some(Channel::Triangle),
+        KeyCode::Digit4 => Some(Channel::Noise),
+        KeyCode::Digit5 => Some(Channel::Dmc),
+        KeyCode::Digit6 => Some(Channel::Vrc6),
+        KeyCode::Digit7 => Some(Channel::Namco163),
+        KeyCode::Digit8 => Some(Channel::Sunsoft5b),
+        _ => None,
+    }
+}
+
+/// Runs `rom` in a window until it's closed. Returns an error if the ROM
+/// can't be loaded.
+pub fn run(rom: Vec<u8>, options: Options) -> io::Result<()> {
     let event_loop = EventLoop::new().unwrap();
     let window = Arc::new(
         WindowBuilder::new()
@@ -37,12 +93,51 @@ pub fn run(rom: Vec<u8>) {
 
     let buffer = vec![0; pixels.frame().len()].into_boxed_slice();
     let (mut _writer, reader) = triple_buffer(buffer);
-    let (mut _producer, mut consumer) = RingBuffer::new(2048);
+    let (mut producer, mut consumer) = RingBuffer::new(2048);
+    let (commands, command_receiver) = mpsc::channel();
+    let (loaded, load_result) = mpsc::channel();
 
     let emu_thread = std::thread::spawn({
-        let _window = window.clone();
+        let window = window.clone();
         move || {
-            let mut _emu = Emu::new(&rom);
+            let config =
+                Config { region: options.region, ..Config::default() };
+            // The emulator isn't Send, so it's loaded on this thread and the
+            // result is sent back.
+            let mut emu = match Emu::try_with_config(&rom, config) {
+                Ok(emu) => {
+                    loaded.send(Ok(())).unwrap();
+                    emu
+                }
+                Err(err) => {
+                    loaded.send(Err(err)).unwrap();
+                    return;
+                }
+            };
+            let mut status = emu.status();
+            let mut muted = [false; Channel::ALL.len()];
+            let recording_path = options
+                .record
+                .clone()
+                .unwrap_or_else(|| DEFAULT_RECORDING_PATH.into());
+            if options.record.is_some() {
+                if let Err(err) =
+                    emu.start_recording(&recording_path, options.stems)
+                {
+                    error!("failed to start recording: {err}");
+                }
+            }
+            if let Some(path) = &options.cdl {
+                let result = if path.exists() {
+                    emu.load_cdl(path)
+                } else {
+                    emu.start_cdl();
+                    Ok(())
+                };
+                if let Err(err) = result {
+                    error!("failed to load {}: {err}", path.display());
+                }
+            }
             // emu.ppu.on_frame(move |buffer| {
             //     writer.get_mut().copy_from_slice(buffer);
             //     writer.swap();
@@ -50,53 +145,113 @@ pub fn run(rom: Vec<u8>) {
             // });
 
             loop {
-                // let slots = producer.slots();
-                // if slots > 0 {
-                // while emu.apu.samples() < slots as u64 {
-                //     emu.tick();
-                // }
-
-                // let mut chunk =
-                //     producer.write_chunk_uninit(slots).unwrap();
-                // let (first, second) = chunk.as_mut_slices();
-                // emu.apu.fill(first);
-                // emu.apu.fill(second);
-                // unsafe { chunk.commit_all() };
-                // }
+                for command in command_receiver.try_iter() {
+                    match command {
+                        Command::ToggleMute(channel) => {
+                            let muted = &mut muted[channel as usize];
+                            *muted = !*muted;
+                            emu.set_muted(channel, *muted);
+                        }
+                        Command::ToggleRecording => {
+                            let result = if emu.is_recording() {
+                                emu.stop_recording()
+                            } else {
+                                emu.start_recording(
+                                    &recording_path,
+                                    options.stems,
+                                )
+                            };
+                            if let Err(err) = result {
+                                error!("failed to toggle recording: {err}");
+                            }
+                        }
+                        Command::Reset => emu.reset(),
+                        Command::PowerCycle => emu.power_cycle(),
+                        Command::Exit => {
+                            if let Err(err) = emu.stop_recording() {
+                                error!("failed to stop recording: {err}");
+                            }
+                            if let (Some(path), Some(cdl)) =
+                                (&options.cdl, emu.cdl())
+                            {
+                                if let Err(err) = cdl.save(path) {
+                                    error!(
+                                        "failed to save {}: {err}",
+                                        path.display()
+                                    );
+                                }
+                            }
+                            return;
+                        }
+                    }
+                }
+
+                // Keep the stereo samples in pairs.
+                let slots = producer.slots() & !1;
+                if slots > 0 {
+                    while emu.samples() * 2 < slots {
+                        emu.step();
+                    }
+                    if emu.status() != status {
+                        status = emu.status();
+                        match status {
+                            Status::Jammed { pc } => {
+                                error!("the CPU jammed at ${pc:04X}");
+                                window.set_title("duNES (CPU jammed)");
+                            }
+                            // A reset recovers the CPU.
+                            Status::Running => window.set_title("duNES"),
+                        }
+                    }
+
+                    let mut chunk =
+                        producer.write_chunk_uninit(slots).unwrap();
+                    let (first, second) = chunk.as_mut_slices();
+                    emu.fill(first);
+                    emu.fill(second);
+                    unsafe { chunk.commit_all() };
+                }
 
                 std::thread::park();
             }
         }
     });
+    load_result.recv().unwrap()?;
+    let emu_thread_handle = emu_thread.thread().clone();
+    // The emulator thread is joined when the window closes.
+    let mut emu_thread = Some(emu_thread);
 
     let host = cpal::default_host();
     let device = host.default_output_device().unwrap();
     let config = StreamConfig {
-        channels: 1,
-        sample_rate: cpal::SampleRate(44100),
+        channels: 2,
+        sample_rate: cpal::SampleRate(SAMPLE_RATE as u32),
         buffer_size: cpal::BufferSize::Fixed(512),
     };
     let stream = device
         .build_output_stream(
             &config,
-            move |data: &mut [i16], _| {
-                let chunk = match consumer.read_chunk(data.len()) {
-                    Ok(chunk) => chunk,
-                    Err(ChunkError::TooFewSlots(n)) => {
-                        warn!("not enough samples to fill the buffer");
-                        let chunk = consumer.read_chunk(n).unwrap();
-                        chunk
-                    }
-                };
+            {
+                let emu_thread_handle = emu_thread_handle.clone();
+                move |data: &mut [i16], _| {
+                    let chunk = match consumer.read_chunk(data.len()) {
+                        Ok(chunk) => chunk,
+                        Err(ChunkError::TooFewSlots(n)) => {
+                            warn!("not enough samples to fill the buffer");
+                            let chunk = consumer.read_chunk(n).unwrap();
+                            chunk
+                        }
+                    };
 
-                let (first, second) = chunk.as_slices();
-                let mid = first.len();
-                let end = chunk.len();
-                data[..mid].copy_from_slice(first);
-                data[mid..end].copy_from_slice(second);
-                chunk.commit_all();
+                    let (first, second) = chunk.as_slices();
+                    let mid = first.len();
+                    let end = chunk.len();
+                    data[..mid].copy_from_slice(first);
+                    data[mid..end].copy_from_slice(second);
+                    chunk.commit_all();
 
-                emu_thread.thread().unpark();
+                    emu_thread_handle.unpark();
+                }
             },
             move |_| {},
             None,
@@ -109,8 +264,38 @@ pub fn run(rom: Vec<u8>) {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested, ..
             } => {
+                commands.send(Command::Exit).unwrap();
+                emu_thread_handle.unpark();
+                if let Some(emu_thread) = emu_thread.take() {
+                    emu_thread.join().unwrap();
+                }
                 elwt.exit();
             }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                physical_key: PhysicalKey::Code(key),
+                                state: ElementState::Pressed,
+                                repeat: false,
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                let command = match key {
+                    KeyCode::KeyR => Some(Command::ToggleRecording),
+                    KeyCode::F1 => Some(Command::Reset),
+                    KeyCode::F2 => Some(Command::PowerCycle),
+                    _ => mute_hotkey(key).map(Command::ToggleMute),
+                };
+                if let Some(command) = command {
+                    commands.send(command).unwrap();
+                    emu_thread_handle.unpark();
+                }
+            }
             Event::WindowEvent {
                 event: WindowEvent::RedrawRequested, ..
             } => {
@@ -121,4 +306,5 @@ pub fn run(rom: Vec<u8>) {
             _ => (),
         })
         .unwrap();
+    Ok(())
 }
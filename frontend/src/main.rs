@@ -1,26 +1,137 @@
 use std::{env, fs};
 use tracing::Level;
 
-use frontend::run;
+use backend::{config::Config, region::Region};
+use frontend::{
+    gdb,
+    headless::{self, Stop},
+    run, Options,
+};
 use tracing_subscriber::FmtSubscriber;
 
 fn main() {
+    let mut args = env::args();
+    // Skip the executable path.
+    args.next();
+
+    let mut options = Options::default();
+    let mut file_path = None;
+    let mut render_path = None;
+    let mut gdb_addr = None;
+    let mut track = None;
+    let mut seconds = 60;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--render" => {
+                let Some(path) = args.next() else {
+                    eprintln!("duNES: error: expected a path after --render");
+                    return;
+                };
+                render_path = Some(path);
+            }
+            "--gdb" => {
+                let Some(addr) = args.next() else {
+                    eprintln!("duNES: error: expected an address after --gdb");
+                    return;
+                };
+                gdb_addr = Some(addr);
+            }
+            "--track" => {
+                // Tracks are one-based on the command line.
+                let Some(n) = args.next().and_then(|n| n.parse::<u8>().ok())
+                else {
+                    eprintln!("duNES: error: expected a number after --track");
+                    return;
+                };
+                track = Some(n.saturating_sub(1));
+            }
+            "--seconds" => {
+                let Some(n) = args.next().and_then(|n| n.parse().ok()) else {
+                    eprintln!(
+                        "duNES: error: expected a number after --seconds"
+                    );
+                    return;
+                };
+                seconds = n;
+            }
+            "--record" => {
+                let Some(path) = args.next() else {
+                    eprintln!("duNES: error: expected a path after --record");
+                    return;
+                };
+                options.record = Some(path.into());
+            }
+            "--cdl" => {
+                let Some(path) = args.next() else {
+                    eprintln!("duNES: error: expected a path after --cdl");
+                    return;
+                };
+                options.cdl = Some(path.into());
+            }
+            "--region" => {
+                let region = match args.next().as_deref() {
+                    Some("ntsc") => Region::Ntsc,
+                    Some("pal") => Region::Pal,
+                    Some("dendy") => Region::Dendy,
+                    _ => {
+                        eprintln!(
+                            "duNES: error: expected ntsc, pal, or dendy after \
+                             --region"
+                        );
+                        return;
+                    }
+                };
+                options.region = Some(region);
+            }
+            "--stems" => options.stems = true,
+            _ => file_path = Some(arg),
+        }
+    }
+
+    let Some(file_path) = file_path else {
+        eprintln!("duNES: error: expected a ROM file");
+        return;
+    };
+    let rom = fs::read(&file_path).unwrap();
+
+    // The GDB stub says where it's listening at the info level.
+    let level = if gdb_addr.is_some() { Level::INFO } else { Level::WARN };
     let subscriber = FmtSubscriber::builder()
         .without_time()
         .with_target(false)
-        .with_max_level(Level::WARN)
+        .with_max_level(level)
         .finish();
     tracing::subscriber::set_global_default(subscriber).unwrap();
 
-    let mut args = env::args();
-    // Skip the executable path.
-    args.next();
+    if let Some(addr) = gdb_addr {
+        if let Err(err) = gdb::serve(&rom, addr.as_str()) {
+            eprintln!("duNES: error: failed to serve GDB on {addr}: {err}");
+        }
+        return;
+    }
 
-    let Some(file_path) = args.next() else {
-        eprintln!("duNES: error: expected a ROM file");
+    if let Some(path) = render_path {
+        let options = headless::Options {
+            seconds: Some(seconds),
+            song: track,
+            config: Config { region: options.region, ..Config::default() },
+            wav: Some(path.as_str().into()),
+            stems: options.stems,
+            ..headless::Options::default()
+        };
+        match headless::run(&rom, &options) {
+            Ok((Stop::Jammed(pc), _)) => {
+                eprintln!("duNES: error: the CPU jammed at ${pc:04X}");
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("duNES: error: failed to render {path}: {err}");
+            }
+        }
         return;
-    };
-    let rom = fs::read(file_path).unwrap();
+    }
 
-    run(rom);
+    if let Err(err) = run(rom, options) {
+        eprintln!("duNES: error: failed to load {file_path}: {err}");
+    }
 }
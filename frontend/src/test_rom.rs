@@ -0,0 +1,14 @@
+//! ROMs for the frontend's tests.
+
+/// Makes an NROM ROM that runs `code` at $8000. The rest of PRG ROM is
+/// filled with NOPs.
+pub fn make_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = b"NES\x1A\x01\x00".to_vec();
+    rom.resize(16, 0);
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..code.len()].copy_from_slice(code);
+    // The reset vector.
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    rom.extend_from_slice(&prg);
+    rom
+}
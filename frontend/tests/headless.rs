@@ -0,0 +1,136 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process,
+};
+
+use frontend::headless::{run, Options, Stop};
+
+const CODE: [u8; 15] = [
+    0xA9, 0x42, // LDA #$42
+    0x8D, 0x00, 0x02, // STA $0200
+    // Count loops in $00-$01.
+    0xE6, 0x00, // INC $00
+    0xD0, 0x02, // BNE $800B
+    0xE6, 0x01, // INC $01
+    0x4C, 0x05, 0x80, // JMP $8005
+    0x02, // JAM
+];
+
+/// Makes an NROM ROM that runs `code` at $8000.
+fn make_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = b"NES\x1A\x01\x00".to_vec();
+    rom.resize(16, 0);
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..code.len()].copy_from_slice(code);
+    // The reset vector.
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    rom.extend_from_slice(&prg);
+    rom
+}
+
+/// Returns a path for a file that only the test `test` in this process
+/// writes.
+fn temp_path(test: &str, name: &str) -> PathBuf {
+    let file = format!("headless-{}-{test}-{name}", process::id());
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(file)
+}
+
+#[test]
+fn frames_and_ram() {
+    let path = temp_path("frames_and_ram", "ram.bin");
+    let options =
+        Options { frames: 2, ram: Some(path.clone()), ..Options::default() };
+    let result = run(&make_rom(&CODE), &options).unwrap();
+    assert_eq!(result, (Stop::Frames, 2));
+
+    let ram = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(ram.len(), 0x0800);
+    assert_eq!(ram[0x0200], 0x42);
+    // Two frames are about 59562 cycles. The reset sequence and the store
+    // take 13, and the loop takes 11 (15 when $00 wraps).
+    let count = u16::from_le_bytes([ram[0x0000], ram[0x0001]]);
+    assert_eq!(count, 5406);
+}
+
+#[test]
+fn until_pc_and_trace() {
+    let path = temp_path("until_pc_and_trace", "trace.log");
+    let options = Options {
+        frames: 10,
+        until_pc: Some(0x8005),
+        trace: Some(path.clone()),
+        ..Options::default()
+    };
+    let result = run(&make_rom(&CODE), &options).unwrap();
+    assert_eq!(result, (Stop::Reached(0x8005), 0));
+
+    let trace = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let lines: Vec<_> = trace.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("8000  A9 42     LDA #$42"));
+}
+
+#[test]
+fn until_ram() {
+    let options = Options {
+        frames: 10,
+        until_ram: vec![(0x0300, 0x01), (0x0000, 0x80)],
+        ..Options::default()
+    };
+    let result = run(&make_rom(&CODE), &options).unwrap();
+    assert_eq!(result, (Stop::RamValue { addr: 0x0000, data: 0x80 }, 0));
+}
+
+#[test]
+fn jammed() {
+    let options = Options { frames: 10, ..Options::default() };
+    let result = run(&make_rom(&[0xEA, 0x02]), &options).unwrap();
+    assert_eq!(result, (Stop::Jammed(0x8001), 0));
+}
+
+#[test]
+fn screenshot() {
+    let path = temp_path("screenshot", "frame.png");
+    let options = Options {
+        frames: 1,
+        screenshot: Some(path.clone()),
+        ..Options::default()
+    };
+    run(&make_rom(&CODE), &options).unwrap();
+
+    let decoder = png::Decoder::new(File::open(&path).unwrap());
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (256, 240));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn seconds_of_audio() {
+    let path = temp_path("seconds_of_audio", "audio.wav");
+    let options = Options {
+        frames: 1,
+        seconds: Some(2),
+        wav: Some(path.clone()),
+        ..Options::default()
+    };
+    let (stop, frames) = run(&make_rom(&CODE), &options).unwrap();
+    assert_eq!(stop, Stop::Frames);
+    // Two seconds are about 120 frames.
+    assert!((119..=121).contains(&frames));
+
+    let wav = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    // 44 bytes of headers and then two seconds of 16-bit stereo samples.
+    assert_eq!(wav.len(), 44 + 2 * 44100 * 4);
+}
+
+#[test]
+fn track_needs_nsf() {
+    let options = Options { frames: 1, song: Some(0), ..Options::default() };
+    let err = run(&make_rom(&CODE), &options).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}